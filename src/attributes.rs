@@ -0,0 +1,445 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::diagnostics::{self, Code, Diagnostic, DiagnosticEmitter};
+use crate::lexer::unescape;
+use crate::lint::LintRegistry;
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::token::Token;
+use crate::tokenized_buffer::TokenizedBuffer;
+
+/// The kinds of declaration an attribute can be written on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Target {
+    Func,
+    Type,
+    Impl,
+    Interface,
+    Module,
+    Binding,
+    Import,
+}
+
+impl Target {
+    fn of(kind: NodeKind) -> Option<Target> {
+        match kind {
+            NodeKind::FuncDecl => Some(Target::Func),
+            NodeKind::TypeDecl => Some(Target::Type),
+            NodeKind::ImplDecl => Some(Target::Impl),
+            NodeKind::InterfaceDecl => Some(Target::Interface),
+            NodeKind::ModuleDecl => Some(Target::Module),
+            NodeKind::LetDecl | NodeKind::VarDecl | NodeKind::ConstDecl => Some(Target::Binding),
+            NodeKind::Import => Some(Target::Import),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Func => write!(f, "functions"),
+            Target::Type => write!(f, "types"),
+            Target::Impl => write!(f, "implementations"),
+            Target::Interface => write!(f, "interfaces"),
+            Target::Module => write!(f, "modules"),
+            Target::Binding => write!(f, "variables"),
+            Target::Import => write!(f, "imports"),
+        }
+    }
+}
+
+/// The arguments an attribute takes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Args {
+    None,
+    String,
+    OptionalString,
+    /// A single string out of a fixed set, such as the ABI of `@extern`.
+    OneOf(&'static [&'static str]),
+    /// One or more names.
+    Names,
+    /// One or more names of lints, as `@allow` takes.
+    Lints,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttributeSpec {
+    pub name: &'static str,
+    pub targets: &'static [Target],
+    pub args: Args,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AttributeArg {
+    String(String),
+    Int(u64),
+    Name(String),
+}
+
+/// An attribute that passed validation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<AttributeArg>,
+    /// The declaration the attribute is written on.
+    pub decl: Node,
+    pub node: Node,
+}
+
+/// Finds the attribute `name` on `decl`, if it has one.
+pub fn find<'a>(attributes: &'a [Attribute], decl: Node, name: &str) -> Option<&'a Attribute> {
+    attributes
+        .iter()
+        .find(|attribute| attribute.decl == decl && attribute.name == name)
+}
+
+/// The attributes the compiler knows about. Every attribute in a tree must
+/// have been registered, and is checked against where it may appear and
+/// what arguments it takes.
+pub struct AttributeRegistry {
+    specs: Vec<AttributeSpec>,
+    /// The lints that `Args::Lints` can name.
+    lints: Vec<&'static str>,
+    diagnostics: Rc<RefCell<dyn DiagnosticEmitter>>,
+}
+
+impl Default for AttributeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AttributeRegistry {
+    /// A registry holding the builtin attributes.
+    pub fn new() -> Self {
+        let specs = vec![
+            AttributeSpec {
+                name: "inline",
+                targets: &[Target::Func],
+                args: Args::None,
+            },
+            AttributeSpec {
+                name: "test",
+                targets: &[Target::Func],
+                args: Args::None,
+            },
+            AttributeSpec {
+                name: "extern",
+                targets: &[Target::Func],
+                args: Args::OneOf(&["c"]),
+            },
+            AttributeSpec {
+                name: "deprecated",
+                targets: &[
+                    Target::Func,
+                    Target::Type,
+                    Target::Interface,
+                    Target::Module,
+                ],
+                args: Args::OptionalString,
+            },
+        ];
+        // The levels of lints, for the declaration they are written on.
+        let lints = ["allow", "warn", "deny"].map(|name| AttributeSpec {
+            name,
+            targets: &[
+                Target::Func,
+                Target::Type,
+                Target::Impl,
+                Target::Interface,
+                Target::Module,
+                Target::Binding,
+                Target::Import,
+            ],
+            args: Args::Lints,
+        });
+        let specs = specs.into_iter().chain(lints).collect();
+        Self {
+            specs,
+            lints: LintRegistry::new().lints().map(|lint| lint.name).collect(),
+            diagnostics: Rc::new(RefCell::new(diagnostics::DefaultEmitter)),
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
+        self
+    }
+
+    /// Takes the lints attributes can name from `lints`, rather than the
+    /// builtin ones.
+    pub fn with_lints(mut self, lints: &LintRegistry) -> Self {
+        self.lints = lints.lints().map(|lint| lint.name).collect();
+        self
+    }
+
+    /// Adds `spec`, replacing an attribute of the same name.
+    pub fn register(&mut self, spec: AttributeSpec) {
+        self.specs.retain(|known| known.name != spec.name);
+        self.specs.push(spec);
+    }
+
+    pub fn spec(&self, name: &str) -> Option<&AttributeSpec> {
+        self.specs.iter().find(|spec| spec.name == name)
+    }
+
+    /// Validates every attribute written on a declaration, returning those
+    /// that are well formed. Attributes with parse errors were already
+    /// reported and are skipped.
+    pub fn check(&self, tree: &ParseTree, tokens: &TokenizedBuffer) -> Vec<Attribute> {
+        let mut attributes = vec![];
+        for decl in tree.postorder() {
+            let Some(target) = Target::of(tree.kind_of(decl)) else {
+                continue;
+            };
+            let mut seen: Vec<&str> = vec![];
+            for node in tree.children(decl) {
+                if tree.kind_of(node) != NodeKind::Attribute || tree.has_error(node) {
+                    continue;
+                }
+                let mut children = tree.children(node);
+                let Some(name_node) = children.next() else {
+                    continue;
+                };
+                let name_token = tree.token_of(name_node);
+                let name = tokens.str_of(&name_token);
+
+                let Some(spec) = self.spec(name) else {
+                    let message = format!("unknown attribute `@{}`", name);
+                    self.emit_error(tokens, name_token, Code::A0001, message);
+                    continue;
+                };
+                if !spec.targets.contains(&target) {
+                    let targets: Vec<String> = spec.targets.iter().map(|t| t.to_string()).collect();
+                    let message = format!(
+                        "`@{}` cannot be used on {}, only on {}",
+                        name,
+                        target,
+                        targets.join(" and ")
+                    );
+                    self.emit_error(tokens, name_token, Code::A0002, message);
+                    continue;
+                }
+                if seen.contains(&name) {
+                    let message = format!("`@{}` is given more than once", name);
+                    self.emit_error(tokens, name_token, Code::A0004, message);
+                    continue;
+                }
+                seen.push(name);
+
+                let args = match children.next() {
+                    Some(args) => tree
+                        .children(args)
+                        .skip(1)
+                        .map(|arg| argument(tree, tokens, arg))
+                        .collect(),
+                    None => Some(vec![]),
+                };
+                let Some(args) = args else {
+                    let message = format!("arguments to `@{}` must be literals or names", name);
+                    self.emit_error(tokens, name_token, Code::A0003, message);
+                    continue;
+                };
+                if let Err(message) = check_args(spec, &args) {
+                    self.emit_error(tokens, name_token, Code::A0003, message);
+                    continue;
+                }
+                if spec.args == Args::Lints {
+                    let unknown = args.iter().find_map(|arg| match arg {
+                        AttributeArg::Name(lint) if !self.lints.contains(&lint.as_str()) => {
+                            Some(lint)
+                        }
+                        _ => None,
+                    });
+                    if let Some(lint) = unknown {
+                        let message = format!("unknown lint `{}`", lint);
+                        self.emit_error(tokens, name_token, Code::A0003, message);
+                        continue;
+                    }
+                }
+
+                attributes.push(Attribute {
+                    name: name.to_string(),
+                    args,
+                    decl,
+                    node,
+                });
+            }
+        }
+        attributes
+    }
+
+    fn emit_error(&self, tokens: &TokenizedBuffer, token: Token, code: Code, message: String) {
+        self.diagnostics.borrow_mut().emit(Diagnostic {
+            level: diagnostics::Level::Error,
+            code: Some(code),
+            message,
+            location: tokens.location_of(&token).into_owned(),
+            span: tokens.span_of(&token),
+        });
+    }
+}
+
+fn argument(tree: &ParseTree, tokens: &TokenizedBuffer, node: Node) -> Option<AttributeArg> {
+    let text = tree.text(tokens, node);
+    match tree.kind_of(node) {
+        NodeKind::StringLiteral => Some(AttributeArg::String(unescape(text))),
+        NodeKind::IntLiteral => text.parse().ok().map(AttributeArg::Int),
+        NodeKind::Name => Some(AttributeArg::Name(text.to_string())),
+        _ => None,
+    }
+}
+
+fn check_args(spec: &AttributeSpec, args: &[AttributeArg]) -> Result<(), String> {
+    if matches!(spec.args, Args::Names | Args::Lints) {
+        return match args.iter().all(|arg| matches!(arg, AttributeArg::Name(_))) {
+            true if !args.is_empty() => Ok(()),
+            _ => Err(format!("`@{}` {}", spec.name, expected(spec.args))),
+        };
+    }
+    let string = match args {
+        [] => None,
+        [AttributeArg::String(value)] => Some(value),
+        _ => return Err(format!("`@{}` {}", spec.name, expected(spec.args))),
+    };
+    match (spec.args, string) {
+        (Args::None, None) | (Args::OptionalString, _) | (Args::String, Some(_)) => Ok(()),
+        (Args::OneOf(values), Some(value)) if values.contains(&value.as_str()) => Ok(()),
+        (Args::OneOf(values), Some(value)) => {
+            let values: Vec<String> = values.iter().map(|v| format!("\"{}\"", v)).collect();
+            Err(format!(
+                "`@{}` does not support \"{}\", expected one of {}",
+                spec.name,
+                value,
+                values.join(", ")
+            ))
+        }
+        _ => Err(format!("`@{}` {}", spec.name, expected(spec.args))),
+    }
+}
+
+fn expected(args: Args) -> &'static str {
+    match args {
+        Args::None => "takes no arguments",
+        Args::String | Args::OneOf(_) => "takes a single string argument",
+        Args::OptionalString => "takes at most a single string argument",
+        Args::Names | Args::Lints => "takes one or more names",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Args, AttributeArg, AttributeRegistry, AttributeSpec, Target};
+    use crate::diagnostics::{Code, Diagnostic};
+    use crate::fixture::Fixture;
+
+    fn check(
+        registry: AttributeRegistry,
+        source: &str,
+    ) -> (Vec<super::Attribute>, Vec<Diagnostic<'static>>) {
+        let fixture = Fixture::parse(source);
+        let attributes = registry
+            .with_emitter(fixture.emitter.clone())
+            .check(&fixture.tree, &fixture.tokens);
+        (attributes, fixture.take())
+    }
+
+    #[test]
+    fn test_builtins() {
+        let source = "@inline @test\nfunc f() {}\n@deprecated(\"use \\\"f\\\"\")\ntype T = Int\n@extern(\"c\") func g() {}";
+        let (attributes, diagnostics) = check(AttributeRegistry::new(), source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let names: Vec<&str> = attributes.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["inline", "test", "deprecated", "extern"]);
+        assert_eq!(
+            attributes[2].args,
+            vec![AttributeArg::String("use \"f\"".to_string())]
+        );
+        assert!(super::find(&attributes, attributes[0].decl, "test").is_some());
+        assert!(super::find(&attributes, attributes[0].decl, "extern").is_none());
+
+        let source = "@deprecated interface I {}\n@allow(unused_variables) impl P {}";
+        let (attributes, diagnostics) = check(AttributeRegistry::new(), source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(attributes.len(), 2);
+    }
+
+    #[test]
+    fn test_lint_levels() {
+        let source = "@allow(unused_variables, redundant_parens)\nfunc f() {}\n\
+                      @deny(\"unused_imports\") func g() {}\n@warn func h() {}";
+        let (attributes, diagnostics) = check(AttributeRegistry::new(), source);
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(
+            attributes[0].args,
+            vec![
+                AttributeArg::Name("unused_variables".to_string()),
+                AttributeArg::Name("redundant_parens".to_string())
+            ]
+        );
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "`@deny` takes one or more names",
+                "`@warn` takes one or more names"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_lints() {
+        let source = "@allow(unused_variables, unused_parens) func f() {}";
+        let (attributes, diagnostics) = check(AttributeRegistry::new(), source);
+        assert!(attributes.is_empty());
+        assert_eq!(diagnostics[0].code, Some(Code::A0003));
+        assert_eq!(diagnostics[0].message, "unknown lint `unused_parens`");
+        // Reported even when the program does not type check.
+        let compiled = crate::test_runner::compile(
+            "test.mik",
+            "@deny(unused_parens) func f() -> Int { true }",
+        );
+        let codes: Vec<Code> = compiled.diagnostics.iter().filter_map(|d| d.code).collect();
+        assert_eq!(codes, vec![Code::A0003, Code::T0002]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let source = "@inlined func f() {}\n@test type T = Int\n@extern(\"rust\") func g() {}\n@inline(1) func h() {}\n@test @test func i() {}\n@deprecated(1 + 2) func j() {}";
+        let (attributes, diagnostics) = check(AttributeRegistry::new(), source);
+        assert!(attributes.iter().all(|a| a.name == "test"));
+        let codes: Vec<Code> = diagnostics.iter().filter_map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                Code::A0001,
+                Code::A0002,
+                Code::A0003,
+                Code::A0003,
+                Code::A0004,
+                Code::A0003
+            ]
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "`@test` cannot be used on types, only on functions"
+        );
+        assert_eq!(
+            diagnostics[2].message,
+            "`@extern` does not support \"rust\", expected one of \"c\""
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let mut registry = AttributeRegistry::new();
+        registry.register(AttributeSpec {
+            name: "doc",
+            targets: &[Target::Type],
+            args: Args::String,
+        });
+        let (attributes, diagnostics) = check(registry, "@doc(\"A vector\") type V = [Real; 4]");
+        assert!(diagnostics.is_empty());
+        assert_eq!(attributes.len(), 1);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::check::Typing;
+use crate::consteval::{ConstEvaluator, Value};
+use crate::diagnostics::CollectingEmitter;
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::resolve::Resolution;
+use crate::token::{SourceLocation, Span};
+use crate::tokenized_buffer::TokenizedBuffer;
+use crate::types::Type;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TrapKind {
+    /// `a[i]`, which needs `0 <= i < a.len()`.
+    Index,
+    /// `a[i..j]`, which needs `0 <= i <= j <= a.len()`.
+    Slice,
+}
+
+/// A bounds check the program makes when it runs, which stops it with
+/// `message()` when the index is out of bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trap {
+    /// The indexing expression checked.
+    pub node: Node,
+    pub kind: TrapKind,
+    /// Where the index starts.
+    pub location: SourceLocation<'static>,
+    pub span: Span,
+}
+
+impl Trap {
+    pub fn message(&self) -> String {
+        let file = self.location.file.as_deref().unwrap_or("unknown file");
+        let what = match self.kind {
+            TrapKind::Index => "index",
+            TrapKind::Slice => "slice",
+        };
+        // Locations are 0-based, as in diagnostics.
+        format!(
+            "{}:{}:{}: {} out of bounds",
+            file,
+            self.location.row + 1,
+            self.location.col + 1,
+            what
+        )
+    }
+}
+
+/// Finds the indices to check when the program runs: every index into a
+/// slice, and those into an array that are not constants, which the checker
+/// has checked already. It runs on type checked trees.
+pub struct BoundsChecker {
+    enabled: bool,
+}
+
+impl Default for BoundsChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoundsChecker {
+    pub fn new() -> Self {
+        Self { enabled: true }
+    }
+
+    /// With `false`, nothing is checked when the program runs, as release
+    /// builds may want.
+    pub fn with_checks(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn check(
+        &self,
+        tree: &ParseTree,
+        tokens: &TokenizedBuffer,
+        resolution: &Resolution,
+        typing: &Typing,
+    ) -> Vec<Trap> {
+        if !self.enabled {
+            return vec![];
+        }
+        let parents = tree.parents();
+        // Whatever fails to evaluate was reported when evaluating constants.
+        let evaluator =
+            ConstEvaluator::new().with_emitter(Rc::new(RefCell::new(CollectingEmitter::new())));
+        let constant = |node: Node| match evaluator
+            .evaluate_node(tree, tokens, resolution, typing, &parents, node)
+        {
+            Some(Value::Int(value)) => Some(value),
+            _ => None,
+        };
+        let mut traps = vec![];
+        for node in tree.postorder() {
+            if tree.kind_of(node) != NodeKind::IndexExpr {
+                continue;
+            }
+            let mut children = tree.children(node);
+            let (Some(object), Some(index)) = (children.next(), children.next()) else {
+                continue;
+            };
+            let len = match typing.type_of(object).map(|ty| typing.types.get(ty)) {
+                Some(Type::Array(_, len)) => Some(*len),
+                Some(Type::Slice(_)) => None,
+                _ => continue,
+            };
+            let slicing = matches!(
+                typing.type_of(index).map(|ty| typing.types.get(ty)),
+                Some(Type::Range(_))
+            );
+            let known = match slicing {
+                true => {
+                    let mut ends = tree.children(index);
+                    let bounds = (tree.kind_of(index) == NodeKind::InfixOperator)
+                        .then(|| Some((ends.next()?, ends.next()?)))
+                        .flatten();
+                    bounds.is_some_and(|(start, end)| {
+                        constant(start).is_some() && constant(end).is_some()
+                    })
+                }
+                false => constant(index).is_some(),
+            };
+            // Constant indices into slices are only known to be in bounds
+            // of an array.
+            if known && len.is_some() {
+                continue;
+            }
+            let token = tree
+                .postorder_of(index)
+                .map(|inner| tree.token_of(inner))
+                .min_by_key(|token| token.0)
+                .unwrap_or(tree.token_of(node));
+            traps.push(Trap {
+                node,
+                kind: match slicing {
+                    true => TrapKind::Slice,
+                    false => TrapKind::Index,
+                },
+                location: tokens.location_of(&token).into_owned(),
+                span: tokens.span_of(&token),
+            });
+        }
+        traps
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BoundsChecker, TrapKind};
+    use crate::fixture::Fixture;
+
+    /// The rows of the indices checked at runtime, and what they check.
+    fn traps(source: &str, enabled: bool) -> Vec<(usize, TrapKind)> {
+        let fixture = Fixture::parse(source);
+        let (resolution, typing) = fixture.check();
+        fixture.assert_clean();
+        BoundsChecker::new()
+            .with_checks(enabled)
+            .check(&fixture.tree, &fixture.tokens, &resolution, &typing)
+            .iter()
+            .map(|trap| (trap.location.row, trap.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_traps() {
+        let source = "const LAST = 3\n\
+                      func f(i: Int, s: [Int]) -> Int {\n\
+                      let a = [1, 2, 3, 4]\n\
+                      a[LAST] + a[1 + 1] +\n\
+                      a[i] +\n\
+                      a[0..2][1] +\n\
+                      s[0] + s[1..i].len() + a[i..4].len()\n\
+                      }";
+        assert_eq!(
+            traps(source, true),
+            vec![
+                (4, TrapKind::Index),
+                (5, TrapKind::Index),
+                (6, TrapKind::Index),
+                (6, TrapKind::Slice),
+                (6, TrapKind::Slice),
+            ]
+        );
+        assert_eq!(traps(source, false), vec![]);
+    }
+
+    #[test]
+    fn test_message() {
+        let source = "func f(s: [Int]) -> Int {\n  s[0]\n}";
+        let fixture = Fixture::parse(source);
+        let (resolution, typing) = fixture.check();
+        let traps =
+            BoundsChecker::new().check(&fixture.tree, &fixture.tokens, &resolution, &typing);
+        assert_eq!(traps[0].message(), "unknown file:2:5: index out of bounds");
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::consteval::{ConstEvaluator, NodeTypes};
+use crate::diagnostics::{self, Code, CollectingEmitter, Diagnostic, DiagnosticEmitter};
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::resolve::{DeclId, Resolution};
+use crate::token::Token;
+use crate::tokenized_buffer::TokenizedBuffer;
+use crate::types::{Type, TypeId, Types};
+
+mod expr;
+mod infer;
+mod items;
+mod pattern;
+
+/// How deep the checker looks into a type. A generic one can hold ever
+/// larger instances of itself, as `W[T] = struct { w: [W[(T, T)]] }` does,
+/// whose size doubles at each level.
+const MAX_NESTING: usize = 16;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Field {
+    pub name: String,
+    pub ty: TypeId,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Variant {
+    pub name: String,
+    pub payload: Vec<TypeId>,
+}
+
+/// What `value.name` refers to, or the variant a pattern names.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Member {
+    /// The field at this index.
+    Field(usize),
+    Method(DeclId),
+    /// The variant at this index.
+    Variant(usize),
+}
+
+/// The types the checker assigned, to expressions and to declarations.
+#[derive(Debug)]
+pub struct Typing {
+    pub types: Types,
+    node_types: Vec<Option<TypeId>>,
+    decl_types: Vec<Option<TypeId>>,
+    /// The fields of each struct, in declaration order. Those of a generic
+    /// struct have its type parameters in their types.
+    structs: HashMap<DeclId, Vec<Field>>,
+    /// The variants of each enum, in declaration order.
+    enums: HashMap<DeclId, Vec<Variant>>,
+    /// The fields and variants of the instances of generic types, as in
+    /// `Opt[Int]`, with their type arguments in place of the parameters.
+    instances: HashMap<TypeId, (Vec<Field>, Vec<Variant>)>,
+    members: Vec<Option<Member>>,
+}
+
+impl Typing {
+    /// The fields of the struct `ty`.
+    pub fn fields_of(&self, ty: TypeId) -> Option<&[Field]> {
+        match self.types.get(ty) {
+            Type::Named(decl, args) if args.is_empty() => self.structs.get(decl).map(Vec::as_slice),
+            Type::Named(decl, _) if self.structs.contains_key(decl) => {
+                self.instances.get(&ty).map(|(fields, _)| fields.as_slice())
+            }
+            _ => None,
+        }
+    }
+
+    /// The variants of the enum `ty`.
+    pub fn variants_of(&self, ty: TypeId) -> Option<&[Variant]> {
+        match self.types.get(ty) {
+            Type::Named(decl, args) if args.is_empty() => self.enums.get(decl).map(Vec::as_slice),
+            Type::Named(decl, _) if self.enums.contains_key(decl) => self
+                .instances
+                .get(&ty)
+                .map(|(_, variants)| variants.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn member_of(&self, node: Node) -> Option<Member> {
+        self.members.get(*node).copied().flatten()
+    }
+
+    pub fn type_of(&self, node: Node) -> Option<TypeId> {
+        self.node_types.get(*node).copied().flatten()
+    }
+
+    pub fn type_of_decl(&self, decl: DeclId) -> Option<TypeId> {
+        self.decl_types.get(decl.0).copied().flatten()
+    }
+}
+
+/// Assigns a type to every expression. There are no implicit conversions:
+/// arithmetic and comparisons need operands of the same type, and numbers
+/// convert between `Int`, the sized integers and `Real` by calling the
+/// target type, as in `Real(n)`.
+///
+/// Types are inferred by unification. An integer literal takes the integer
+/// type its context asks for, as in `let a: u8 = 200`, and is `Int` when
+/// nothing does.
+pub struct TypeChecker {
+    diagnostics: Rc<RefCell<dyn DiagnosticEmitter>>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self {
+            diagnostics: Rc::new(RefCell::new(diagnostics::DefaultEmitter)),
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
+        self
+    }
+
+    pub fn check(
+        &self,
+        tree: &ParseTree,
+        tokens: &TokenizedBuffer,
+        resolution: &Resolution,
+    ) -> Typing {
+        let mut context = Context {
+            checker: self,
+            tree,
+            tokens,
+            resolution,
+            typing: Typing {
+                types: Types::new(),
+                node_types: vec![None; tree.len()],
+                decl_types: vec![None; resolution.decls().count()],
+                structs: HashMap::new(),
+                enums: HashMap::new(),
+                instances: HashMap::new(),
+                members: vec![None; tree.len()],
+            },
+            returns: vec![],
+            aliases: vec![],
+            evaluator: ConstEvaluator::new()
+                .with_emitter(Rc::new(RefCell::new(CollectingEmitter::new()))),
+            methods: vec![],
+            impls: vec![],
+            instances: vec![],
+            matches: vec![],
+            vars: vec![],
+            deferred: vec![],
+            parents: tree.parents(),
+        };
+        // Types and signatures first, so that uses can come before the
+        // declarations.
+        for node in tree.postorder() {
+            match tree.kind_of(node) {
+                NodeKind::TypeDecl => {
+                    context.declare_struct(node);
+                    context.declare_enum(node);
+                }
+                NodeKind::ImplDecl => context.declare_impl(node),
+                _ => {}
+            }
+        }
+        for node in tree.postorder() {
+            if tree.kind_of(node) == NodeKind::FuncDecl {
+                context.declare_func(node);
+            }
+        }
+        for root in tree.roots() {
+            context.check_stmt(root);
+        }
+        context.finish();
+        context.typing
+    }
+}
+
+struct Context<'c, 't> {
+    checker: &'c TypeChecker,
+    tree: &'t ParseTree,
+    tokens: &'t TokenizedBuffer<'t>,
+    resolution: &'t Resolution,
+    typing: Typing,
+    /// The return type of each function being checked, innermost last.
+    returns: Vec<TypeId>,
+    /// Type aliases being lowered, to stop at cycles.
+    aliases: Vec<DeclId>,
+    /// Evaluates array lengths and indices, without reporting anything.
+    evaluator: ConstEvaluator,
+    /// The functions declared in `impl`s, by the type they belong to.
+    methods: Vec<(TypeId, DeclId)>,
+    /// The interfaces implemented, with the type implementing them and the
+    /// `impl` doing so.
+    impls: Vec<(DeclId, TypeId, Node)>,
+    /// The uses of generic functions, whose type arguments have to
+    /// implement the bounds of their parameters.
+    instances: Vec<Instance>,
+    /// The `match`es whose patterns fit the value they match, with its
+    /// type, to check their coverage once all types are known.
+    matches: Vec<(Node, TypeId)>,
+    /// The type variables, indexed by `Type::Var` and `Type::IntVar`.
+    vars: Vec<Var>,
+    /// Operators checked before the types of their operands were known.
+    deferred: Vec<Deferred<'t>>,
+    /// The parent of each node, for the checks that look up the tree.
+    parents: Vec<Option<Node>>,
+}
+
+struct Var {
+    integer: bool,
+    bound: Option<TypeId>,
+}
+
+struct Instance {
+    token: Token,
+    /// The generic function or type.
+    func: DeclId,
+    /// Each type parameter with its type argument.
+    args: Vec<(DeclId, TypeId)>,
+}
+
+struct Deferred<'t> {
+    op: &'t str,
+    token: Token,
+    operands: Vec<TypeId>,
+}
+
+impl<'t> Context<'_, 't> {
+    fn display(&mut self, ty: TypeId) -> String {
+        let ty = self.resolve(ty);
+        self.typing.types.display(ty, self.resolution)
+    }
+
+    fn set_decl_type(&mut self, node: Node, ty: TypeId) {
+        if let Some(decl) = self.resolution.decl_of(node) {
+            self.typing.decl_types[decl.0] = Some(ty);
+        }
+    }
+
+    fn decl_type(&self, decl: DeclId) -> TypeId {
+        self.typing.decl_types[decl.0].unwrap_or(Types::ERROR)
+    }
+
+    /// Like `ParseTree::parent`, without walking down the tree.
+    fn parent(&self, node: Node) -> Option<Node> {
+        self.parents[*node]
+    }
+
+    fn emit_error(&self, token: Token, code: Code, message: String) {
+        self.emit(diagnostics::Level::Error, token, code, message);
+    }
+
+    fn emit_warning(&self, token: Token, code: Code, message: String) {
+        self.emit(diagnostics::Level::Warning, token, code, message);
+    }
+
+    fn emit(&self, level: diagnostics::Level, token: Token, code: Code, message: String) {
+        self.checker.diagnostics.borrow_mut().emit(Diagnostic {
+            level,
+            code: Some(code),
+            message,
+            location: self.tokens.location_of(&token).into_owned(),
+            span: self.tokens.span_of(&token),
+        });
+    }
+}
+
+impl NodeTypes for Context<'_, '_> {
+    fn type_of_node(&self, node: Node) -> Option<&Type> {
+        let ty = self.typing.type_of(node)?;
+        Some(self.typing.types.get(self.shallow(ty)))
+    }
+}
+
+/// The value of an integer literal, unless it is too large for any type.
+pub(crate) fn literal_value(text: &str) -> Option<i128> {
+    text.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::{Code, Diagnostic};
+    use crate::fixture::Fixture;
+    use crate::parse_tree::NodeKind;
+
+    /// The types of the top-level expression statements, and what the
+    /// checker reported.
+    pub(super) fn check(source: &str) -> (Vec<String>, Vec<Diagnostic<'static>>) {
+        let fixture = Fixture::parse(source);
+        let resolution = fixture.resolve();
+        fixture.assert_clean();
+        let typing = fixture.check_resolved(&resolution);
+        let tree = &fixture.tree;
+        let types = tree
+            .roots()
+            .filter(|&root| tree.kind_of(root) == NodeKind::ExprStmt)
+            .map(|root| {
+                let expr = tree.children(root).next().unwrap();
+                let ty = typing.type_of(expr).unwrap();
+                typing.types.display(ty, &resolution)
+            })
+            .collect();
+        (types, fixture.take())
+    }
+
+    pub(super) fn codes(source: &str) -> Vec<Code> {
+        check(source).1.iter().filter_map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_errors() {
+        let (_, diagnostics) = check("1 + 2.0");
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot apply `+` to `{integer}` and `Real`"
+        );
+        assert_eq!(codes("let x: i32 = true"), vec![Code::T0002]);
+        assert_eq!(codes("-u8(1)"), vec![Code::T0001]);
+        assert_eq!(codes("!1"), vec![Code::T0001]);
+        assert_eq!(codes("1.5 % 2.0"), vec![Code::T0001]);
+        assert_eq!(codes("if 1 { }"), vec![Code::T0002]);
+        assert_eq!(codes("let x = 1\nx(2)"), vec![Code::T0003]);
+        assert_eq!(
+            codes("func f(a: Int, b: Int = 1) {}\nf()\nf(1)\nf(1, 2, 3)"),
+            vec![Code::T0004, Code::T0004]
+        );
+        assert_eq!(codes("Real(true)"), vec![Code::T0005]);
+        assert_eq!(codes("func f() -> Int { 1.0 }"), vec![Code::T0002]);
+        assert_eq!(codes("func f() -> Int { return true }"), vec![Code::T0002]);
+        assert_eq!(codes("for i in 3 {}"), vec![Code::T0002]);
+        assert_eq!(codes("let t: (Int, Bool) = (1, 2)"), vec![Code::T0002]);
+        // An error is reported once, not again by every expression it is in.
+        assert_eq!(codes("(1 + 2.0) * 3 + 4"), vec![Code::T0001]);
+        assert_eq!(
+            codes("func f(p: &Int) -> Bool { p }\nfunc g(p: *Real) -> Int { p + 1 }"),
+            vec![Code::T0018, Code::T0018]
+        );
+        assert_eq!(
+            codes("type A = A\nfunc f(a: A) -> Int { a }\nf(true)"),
+            vec![Code::T0019]
+        );
+        assert_eq!(
+            codes("type A = B\ntype B = A\nlet x: A = 1"),
+            vec![Code::T0019]
+        );
+        assert_eq!(
+            codes("type N = struct { n: N }\ntype E = enum {\nA((Int, [E; 1]))\n}"),
+            vec![Code::T0020, Code::T0020]
+        );
+        assert_eq!(
+            codes("type A = struct { b: B }\ntype B = struct { a: A }"),
+            vec![Code::T0020, Code::T0020]
+        );
+        assert_eq!(
+            codes("type L = struct { next: [L], f: func(L) -> L, none: [L; 0] }"),
+            vec![]
+        );
+        assert_eq!(
+            codes("type Opt[T] = enum {\nSome(T)\n}\nfunc f(a: Opt[Int]) -> Opt[Real] { a }"),
+            vec![Code::T0002]
+        );
+        assert_eq!(
+            codes("type Opt[T] = enum {\nSome(T)\n}\nfunc f(a: Opt, b: Opt[Int, Int]) {}"),
+            vec![Code::T0004, Code::T0004]
+        );
+        assert_eq!(
+            codes("type A = struct { b: Box[A] }\ntype Box[T] = struct { value: T }"),
+            vec![Code::T0020]
+        );
+    }
+}
@@ -0,0 +1,1037 @@
+use super::{Context, Deferred, Member};
+use crate::diagnostics::Code;
+use crate::parse_tree::{Node, NodeKind};
+use crate::resolve::{DeclId, DeclKind};
+use crate::token::Token;
+use crate::types::{IntType, Type, TypeId, Types};
+
+impl<'t> Context<'_, 't> {
+    // Statements.
+
+    pub(super) fn check_stmt(&mut self, node: Node) {
+        match self.tree.kind_of(node) {
+            NodeKind::ExprStmt => {
+                if let Some(expr) = self.tree.children(node).next() {
+                    self.check_expr(expr);
+                }
+            }
+            NodeKind::Assign => self.check_assign(node),
+            NodeKind::LetDecl | NodeKind::VarDecl | NodeKind::ConstDecl => self.check_binding(node),
+            NodeKind::TypeDecl => {
+                // Aliases are lowered where they are used, and here for
+                // those that are not.
+                let decl = self
+                    .tree
+                    .child(node, NodeKind::DeclName)
+                    .and_then(|name| self.resolution.decl_of(name));
+                if let Some(decl) = decl {
+                    let ty = self.type_decl(decl);
+                    let name = self.tree.child(node, NodeKind::DeclName);
+                    if let Some(name) = name.filter(|_| self.contains(ty, decl)) {
+                        let message = format!(
+                            "`{}` contains itself, so its size would be infinite",
+                            self.tree.text(self.tokens, name)
+                        );
+                        self.emit_error(self.tree.token_of(name), Code::T0020, message);
+                    }
+                }
+                self.check_discriminants(node);
+            }
+            NodeKind::ImplDecl => {
+                self.check_impl(node);
+                for child in self.tree.children(node) {
+                    self.check_stmt(child);
+                }
+            }
+            NodeKind::InterfaceDecl => {
+                for child in self.tree.children(node) {
+                    self.check_stmt(child);
+                }
+            }
+            NodeKind::If => {
+                self.check_if(node, None);
+            }
+            NodeKind::While => {
+                let mut children = self.tree.children(node);
+                if let Some(condition) = children.next() {
+                    self.expect_expr(condition, Types::BOOL);
+                }
+                for child in children {
+                    self.check_stmt(child);
+                }
+            }
+            NodeKind::For => self.check_for(node),
+            NodeKind::Block => {
+                self.check_block(node, None);
+            }
+            NodeKind::Return => self.check_return(node),
+            NodeKind::FuncDecl => {
+                self.check_bounds(node);
+                self.check_defaults(node);
+                let ret = self.return_type(node);
+                if let Some(body) = self.tree.child(node, NodeKind::Block) {
+                    self.check_body(body, ret);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_return(&mut self, node: Node) {
+        let expected = self.returns.last().copied().unwrap_or(Types::ERROR);
+        match self.tree.children(node).next() {
+            Some(value) => self.expect_expr(value, expected),
+            None => {
+                let token = self.tree.token_of(node);
+                self.expect(token, Types::UNIT, expected);
+            }
+        }
+    }
+
+    fn check_body(&mut self, body: Node, ret: TypeId) {
+        self.returns.push(ret);
+        let expected = match ret {
+            Types::UNIT => None,
+            ret => Some(ret),
+        };
+        self.check_block(body, expected);
+        self.returns.pop();
+    }
+
+    /// Checks the statements of a block. When `expected` is given, the block
+    /// produces a value: its last statement, if that is an expression or an
+    /// `if` with an `else`, has to be of that type.
+    fn check_block(&mut self, block: Node, expected: Option<TypeId>) -> TypeId {
+        let stmts: Vec<Node> = self
+            .tree
+            .children(block)
+            .filter(|&child| self.tree.kind_of(child) != NodeKind::BlockStart)
+            .collect();
+        let Some((&last, rest)) = stmts.split_last() else {
+            return Types::UNIT;
+        };
+        for &stmt in rest {
+            self.check_stmt(stmt);
+        }
+        match self.tree.kind_of(last) {
+            NodeKind::ExprStmt => {
+                let Some(expr) = self.tree.children(last).next() else {
+                    return Types::ERROR;
+                };
+                match expected {
+                    Some(expected) => {
+                        self.expect_expr(expr, expected);
+                        expected
+                    }
+                    None => self.check_expr(expr),
+                }
+            }
+            NodeKind::If => self.check_if(last, expected),
+            _ => {
+                self.check_stmt(last);
+                Types::UNIT
+            }
+        }
+    }
+
+    fn check_if(&mut self, node: Node, expected: Option<TypeId>) -> TypeId {
+        let mut children = self.tree.children(node);
+        if let Some(condition) = children.next() {
+            self.expect_expr(condition, Types::BOOL);
+        }
+        let has_else = self.tree.child(node, NodeKind::Else).is_some();
+        let expected = expected.filter(|_| has_else);
+        let mut ty = Types::UNIT;
+        for child in children {
+            ty = match self.tree.kind_of(child) {
+                NodeKind::Block => self.check_block(child, expected),
+                NodeKind::Else => match self.tree.children(child).next() {
+                    Some(branch) if self.tree.kind_of(branch) == NodeKind::If => {
+                        self.check_if(branch, expected)
+                    }
+                    Some(branch) => self.check_block(branch, expected),
+                    None => Types::ERROR,
+                },
+                _ => Types::ERROR,
+            };
+        }
+        match has_else {
+            true => ty,
+            false => Types::UNIT,
+        }
+    }
+
+    fn check_binding(&mut self, node: Node) {
+        let annotation = self
+            .tree
+            .child(node, NodeKind::TypeAnnotation)
+            .and_then(|annotation| self.tree.children(annotation).next())
+            .map(|ty| self.lower_type(ty));
+        let value = self
+            .tree
+            .child(node, NodeKind::Initializer)
+            .and_then(|initializer| self.tree.children(initializer).next());
+        let ty = match (annotation, value) {
+            (Some(annotation), Some(value)) => {
+                self.expect_expr(value, annotation);
+                annotation
+            }
+            (Some(annotation), None) => annotation,
+            (None, Some(value)) => self.check_expr(value),
+            (None, None) => self.fresh_var(false),
+        };
+        if let Some(name) = self.tree.child(node, NodeKind::DeclName) {
+            self.set_decl_type(name, ty);
+        }
+    }
+
+    fn check_assign(&mut self, node: Node) {
+        let mut children = self.tree.children(node);
+        let (Some(target), Some(value)) = (children.next(), children.next()) else {
+            return;
+        };
+        let target_ty = self.check_expr(target);
+        let op = self.tree.text(self.tokens, node);
+        match op.strip_suffix('=').filter(|op| !op.is_empty()) {
+            // `a += b` is checked as `a = a + b`.
+            Some(op) => {
+                let value_ty = self.check_expr(value);
+                let token = self.tree.token_of(node);
+                let result = self.binary(op, token, target_ty, value_ty);
+                self.expect(token, result, target_ty);
+            }
+            None => self.expect_expr(value, target_ty),
+        }
+    }
+
+    fn check_for(&mut self, node: Node) {
+        let name = self.tree.child(node, NodeKind::DeclName);
+        let mut children = self
+            .tree
+            .children(node)
+            .filter(|&child| Some(child) != name);
+        let element = match children.next() {
+            Some(iterable) => self.element_type(iterable),
+            None => Types::ERROR,
+        };
+        if let Some(name) = name {
+            self.set_decl_type(name, element);
+        }
+        for child in children {
+            self.check_stmt(child);
+        }
+    }
+
+    /// The type of the elements `for` takes from `iterable`: the integers
+    /// of a range, or the elements of an array or slice.
+    fn element_type(&mut self, iterable: Node) -> TypeId {
+        let ty = self.check_expr(iterable);
+        let ty = self.shallow(ty);
+        if let &Type::Array(element, _) | &Type::Slice(element) = self.typing.types.get(ty) {
+            return element;
+        }
+        let element = self.fresh_var(true);
+        let range = self.typing.types.intern(Type::Range(element));
+        if self.unify(ty, range) {
+            return element;
+        }
+        let message = format!(
+            "expected a range, array or slice to iterate over, found `{}`",
+            self.display(ty)
+        );
+        self.emit_error(self.tree.token_of(iterable), Code::T0002, message);
+        Types::ERROR
+    }
+
+    // Expressions.
+
+    /// Checks `node` and reports a mismatch unless it has type `expected`.
+    pub(super) fn expect_expr(&mut self, node: Node, expected: TypeId) {
+        let ty = self.check_expr(node);
+        let token = self.first_token(node);
+        self.expect(token, ty, expected);
+    }
+
+    pub(super) fn expect(&mut self, token: Token, ty: TypeId, expected: TypeId) -> bool {
+        if self.unify(ty, expected) {
+            return true;
+        }
+        let message = format!(
+            "mismatched types: expected `{}`, found `{}`",
+            self.display(expected),
+            self.display(ty)
+        );
+        self.emit_error(token, Code::T0002, message);
+        false
+    }
+
+    /// The token an expression starts at, where mismatches are reported.
+    pub(super) fn first_token(&self, node: Node) -> Token {
+        let start = *node + 1 - self.tree.subtree_size_of(node);
+        let first = (start..=*node)
+            .map(|index| self.tree.token_of(Node(index)).0)
+            .min()
+            .unwrap_or(self.tree.token_of(node).0);
+        Token(first)
+    }
+
+    pub(super) fn check_expr(&mut self, node: Node) -> TypeId {
+        let ty = match self.tree.kind_of(node) {
+            NodeKind::IntLiteral => self.fresh_var(true),
+            NodeKind::RealLiteral => Types::REAL,
+            NodeKind::StringLiteral => Types::STRING,
+            NodeKind::BoolLiteral => Types::BOOL,
+            NodeKind::Name => match self.resolution.decl_of(node) {
+                Some(decl) if self.resolution.decl(decl).kind == DeclKind::Func => {
+                    self.instantiate(node, decl, None)
+                }
+                Some(decl) => self.decl_type(decl),
+                None => Types::ERROR,
+            },
+            NodeKind::GenericName => {
+                let mut children = self.tree.children(node);
+                let (Some(name), args) = (children.next(), children.next()) else {
+                    return Types::ERROR;
+                };
+                match self.resolution.decl_of(name) {
+                    Some(decl) if self.resolution.decl(decl).kind == DeclKind::Func => {
+                        self.instantiate(name, decl, args)
+                    }
+                    _ => Types::ERROR,
+                }
+            }
+            NodeKind::ParenExpr => match self.tree.children(node).nth(1) {
+                Some(inner) => self.check_expr(inner),
+                None => Types::ERROR,
+            },
+            NodeKind::PrefixOperator => self.check_prefix(node),
+            NodeKind::InfixOperator => {
+                let mut children = self.tree.children(node);
+                let (Some(lhs), Some(rhs)) = (children.next(), children.next()) else {
+                    return Types::ERROR;
+                };
+                let lhs = self.check_expr(lhs);
+                let rhs = self.check_expr(rhs);
+                let op = self.tree.text(self.tokens, node);
+                self.binary(op, self.tree.token_of(node), lhs, rhs)
+            }
+            NodeKind::Call => self.check_call(node),
+            NodeKind::FieldAccess => self.check_field_access(node),
+            NodeKind::IndexExpr => self.check_index(node),
+            NodeKind::TupleLiteral => {
+                let elements: Vec<TypeId> = self
+                    .tree
+                    .children(node)
+                    .skip(1)
+                    .map(|child| self.check_expr(child))
+                    .collect();
+                // Like `(T,)` in a type, `(a,)` is just `a`.
+                match elements.as_slice() {
+                    [] => Types::UNIT,
+                    [single] => *single,
+                    _ if elements.contains(&Types::ERROR) => Types::ERROR,
+                    _ => self.typing.types.intern(Type::Tuple(elements)),
+                }
+            }
+            NodeKind::ArrayLiteral => {
+                let element = self.fresh_var(false);
+                let mut len = 0;
+                for child in self.tree.children(node).skip(1) {
+                    self.expect_expr(child, element);
+                    len += 1;
+                }
+                self.typing.types.intern(Type::Array(element, len))
+            }
+            NodeKind::ArrayRepeat => {
+                let mut children = self.tree.children(node).skip(1);
+                let (Some(value), Some(length)) = (children.next(), children.next()) else {
+                    return Types::ERROR;
+                };
+                let element = self.check_expr(value);
+                self.array_type(element, length)
+            }
+            NodeKind::StructLiteral => self.check_struct_literal(node),
+            NodeKind::ClosureExpr | NodeKind::FuncExpr => self.check_closure(node),
+            NodeKind::MatchExpr => self.check_match(node),
+            // It never produces a value, so it fits wherever it is.
+            NodeKind::Return => {
+                self.check_return(node);
+                self.fresh_var(false)
+            }
+            _ => Types::ERROR,
+        };
+        self.typing.node_types[*node] = Some(ty);
+        ty
+    }
+
+    fn check_prefix(&mut self, node: Node) -> TypeId {
+        let Some(operand) = self.tree.children(node).next() else {
+            return Types::ERROR;
+        };
+        let ty = self.check_expr(operand);
+        let ty = self.shallow(ty);
+        let op = self.tree.text(self.tokens, node);
+        let token = self.tree.token_of(node);
+        match (op, self.typing.types.get(ty)) {
+            // Whether an integer literal can be negated depends on the
+            // type it ends up with.
+            ("-", Type::Var(_) | Type::IntVar(_)) => {
+                self.deferred.push(Deferred {
+                    op,
+                    token,
+                    operands: vec![ty],
+                });
+                return ty;
+            }
+            ("!", Type::Var(_)) => {
+                self.unify(ty, Types::BOOL);
+                return Types::BOOL;
+            }
+            _ => {}
+        }
+        if !self.prefix_applies(op, ty) {
+            self.operator_error(op, token, &[ty]);
+            return Types::ERROR;
+        }
+        ty
+    }
+
+    pub(super) fn prefix_applies(&self, op: &str, ty: TypeId) -> bool {
+        match (op, self.typing.types.get(ty)) {
+            (_, Type::Error) => true,
+            ("-", Type::Real) => true,
+            ("-", Type::Int(int)) => int.is_signed(),
+            ("!", Type::Bool) => true,
+            _ => false,
+        }
+    }
+
+    /// The type of `lhs op rhs`, reporting operators that do not apply.
+    fn binary(&mut self, op: &'t str, token: Token, lhs: TypeId, rhs: TypeId) -> TypeId {
+        if matches!(op, "<<" | ">>") {
+            return self.shift(op, token, lhs, rhs);
+        }
+        let same = self.unify(lhs, rhs);
+        let (lhs, rhs) = (self.shallow(lhs), self.shallow(rhs));
+        if lhs == Types::ERROR || rhs == Types::ERROR {
+            return Types::ERROR;
+        }
+        if same && matches!(self.typing.types.get(lhs), Type::Var(_)) {
+            self.deferred.push(Deferred {
+                op,
+                token,
+                operands: vec![lhs, rhs],
+            });
+            return match op {
+                "<" | "<=" | ">" | ">=" | "==" | "!=" | "&&" | "||" => Types::BOOL,
+                ".." => self.typing.types.intern(Type::Range(lhs)),
+                _ => lhs,
+            };
+        }
+        match self.binary_type(op, same, lhs, rhs) {
+            Some(ty) => ty,
+            None => {
+                self.operator_error(op, token, &[lhs, rhs]);
+                Types::ERROR
+            }
+        }
+    }
+
+    /// Shifts take any integer type on the right, so their operands are
+    /// not unified.
+    fn shift(&mut self, op: &str, token: Token, lhs: TypeId, rhs: TypeId) -> TypeId {
+        for operand in [lhs, rhs] {
+            if let Type::Var(_) = self.typing.types.get(self.shallow(operand)) {
+                let integer = self.fresh_var(true);
+                self.unify(operand, integer);
+            }
+        }
+        let (lhs, rhs) = (self.shallow(lhs), self.shallow(rhs));
+        if lhs == Types::ERROR || rhs == Types::ERROR {
+            return Types::ERROR;
+        }
+        let types = &self.typing.types;
+        if !types.is_integer(lhs) || !types.is_integer(rhs) {
+            self.operator_error(op, token, &[lhs, rhs]);
+            return Types::ERROR;
+        }
+        lhs
+    }
+
+    pub(super) fn binary_type(
+        &mut self,
+        op: &str,
+        same: bool,
+        lhs: TypeId,
+        rhs: TypeId,
+    ) -> Option<TypeId> {
+        if lhs == Types::ERROR || rhs == Types::ERROR {
+            return Some(Types::ERROR);
+        }
+        let types = &self.typing.types;
+        match op {
+            "+" | "-" | "*" | "/" if same && types.is_numeric(lhs) => Some(lhs),
+            "%" if same && types.is_integer(lhs) => Some(lhs),
+            "<" | "<=" | ">" | ">=" if same && types.is_numeric(lhs) => Some(Types::BOOL),
+            "==" | "!=" if same && !matches!(types.get(lhs), Type::Func { .. }) => {
+                Some(Types::BOOL)
+            }
+            "&&" | "||" if same && lhs == Types::BOOL => Some(Types::BOOL),
+            ".." if same && types.is_integer(lhs) => {
+                Some(self.typing.types.intern(Type::Range(lhs)))
+            }
+            _ => None,
+        }
+    }
+
+    pub(super) fn operator_error(&mut self, op: &str, token: Token, operands: &[TypeId]) {
+        let operands: Vec<String> = operands
+            .iter()
+            .map(|&ty| format!("`{}`", self.display(ty)))
+            .collect();
+        let message = format!("cannot apply `{}` to {}", op, operands.join(" and "));
+        self.emit_error(token, Code::T0001, message);
+    }
+
+    fn check_call(&mut self, node: Node) -> TypeId {
+        let mut children = self.tree.children(node);
+        let Some(callee) = children.next() else {
+            return Types::ERROR;
+        };
+        let args: Vec<Node> = children.collect();
+
+        // Calling a numeric type converts to it.
+        let callee_decl = match self.tree.kind_of(callee) {
+            NodeKind::Name => self.resolution.decl_of(callee),
+            NodeKind::GenericName => self
+                .tree
+                .children(callee)
+                .next()
+                .and_then(|name| self.resolution.decl_of(name)),
+            _ => None,
+        };
+        if let Some(decl) = callee_decl {
+            if self.resolution.decl(decl).kind == DeclKind::BuiltinType {
+                return self.check_conversion(node, decl, &args);
+            }
+        }
+
+        let callee_ty = self.check_expr(callee);
+        let mut callee_ty = self.shallow(callee_ty);
+        if let Type::Var(_) = self.typing.types.get(callee_ty) {
+            let params = args.iter().map(|_| self.fresh_var(false)).collect();
+            let ret = self.fresh_var(false);
+            let func = self.typing.types.intern(Type::Func { params, ret });
+            self.unify(callee_ty, func);
+            callee_ty = func;
+        }
+        let (params, ret) = match self.typing.types.get(callee_ty) {
+            Type::Func { params, ret } => (params.clone(), *ret),
+            Type::Error => {
+                for &arg in &args {
+                    self.check_expr(arg);
+                }
+                return Types::ERROR;
+            }
+            _ => {
+                let message = format!(
+                    "`{}` is not a function, its type is `{}`",
+                    self.tree.text(self.tokens, callee),
+                    self.display(callee_ty)
+                );
+                self.emit_error(self.tree.token_of(callee), Code::T0003, message);
+                return Types::ERROR;
+            }
+        };
+
+        let required = match self.typing.member_of(callee) {
+            // A method called on a value is passed `self` without counting
+            // it as an argument.
+            Some(Member::Method(method)) => self.required_params(method).map(|required| {
+                required - usize::from(self.has_receiver(method) && !self.names_type(callee))
+            }),
+            _ => callee_decl
+                .filter(|&decl| self.resolution.decl(decl).kind == DeclKind::Func)
+                .and_then(|decl| self.required_params(decl)),
+        }
+        .unwrap_or(params.len());
+        if args.len() < required || args.len() > params.len() {
+            let expected = match required == params.len() {
+                true => params.len().to_string(),
+                false => format!("{} to {}", required, params.len()),
+            };
+            let message = format!(
+                "expected {} argument{}, found {}",
+                expected,
+                if params.len() == 1 { "" } else { "s" },
+                args.len()
+            );
+            self.emit_error(self.first_token(callee), Code::T0004, message);
+        }
+        for (i, &arg) in args.iter().enumerate() {
+            match params.get(i) {
+                Some(&param) => self.expect_expr(arg, param),
+                None => {
+                    self.check_expr(arg);
+                }
+            }
+        }
+        ret
+    }
+
+    /// `value[index]` for an element, or `value[start..end]` for a slice of
+    /// the elements from `start` up to `end`.
+    fn check_index(&mut self, node: Node) -> TypeId {
+        let mut children = self.tree.children(node);
+        let (Some(object), Some(index)) = (children.next(), children.next()) else {
+            return Types::ERROR;
+        };
+        let ty = self.check_expr(object);
+        let ty = self.shallow(ty);
+        let index_ty = self.check_expr(index);
+        let int = self.typing.types.int(IntType::Int);
+        let slicing = matches!(
+            self.typing.types.get(self.shallow(index_ty)),
+            Type::Range(_)
+        );
+        let expected = match slicing {
+            true => self.typing.types.intern(Type::Range(int)),
+            false => int,
+        };
+        self.expect(self.first_token(index), index_ty, expected);
+        let (element, len) = match *self.typing.types.get(ty) {
+            Type::Array(element, len) => (element, Some(len)),
+            Type::Slice(element) => (element, None),
+            // Left to be reported where the value is declared.
+            Type::Error | Type::Var(_) => return Types::ERROR,
+            _ => {
+                let message = format!("cannot index into `{}`", self.display(ty));
+                self.emit_error(self.first_token(object), Code::T0001, message);
+                return Types::ERROR;
+            }
+        };
+        match slicing {
+            true => {
+                let bounds = (self.tree.kind_of(index) == NodeKind::InfixOperator)
+                    .then(|| {
+                        let mut ends = self.tree.children(index);
+                        Some((ends.next()?, ends.next()?))
+                    })
+                    .flatten();
+                if let Some((start, end)) = bounds {
+                    let first = self.check_bound("slice start", start, ty, len.map(|len| len + 1));
+                    let last = self.check_bound("slice end", end, ty, len.map(|len| len + 1));
+                    if let (Some(first), Some(last)) = (first, last) {
+                        if first > last {
+                            let message =
+                                format!("slice start {} is after its end {}", first, last);
+                            self.emit_error(self.first_token(start), Code::T0017, message);
+                        }
+                    }
+                }
+                self.typing.types.intern(Type::Slice(element))
+            }
+            false => {
+                self.check_bound("index", index, ty, len);
+                element
+            }
+        }
+    }
+
+    /// Reports an index into `ty` known to be negative, or to be at least
+    /// `limit`, and returns it if it is in bounds.
+    fn check_bound(
+        &mut self,
+        what: &str,
+        index: Node,
+        ty: TypeId,
+        limit: Option<u64>,
+    ) -> Option<i128> {
+        let value = self.constant(index)?;
+        if value >= 0 && limit.is_none_or(|limit| value < i128::from(limit)) {
+            return Some(value);
+        }
+        let message = format!(
+            "{} {} is out of bounds of `{}`",
+            what,
+            value,
+            self.display(ty)
+        );
+        self.emit_error(self.first_token(index), Code::T0017, message);
+        None
+    }
+
+    /// `value.field`, `value.method` with `self` bound to `value`, or
+    /// `Type.function`.
+    fn check_field_access(&mut self, node: Node) -> TypeId {
+        let Some(object) = self.tree.children(node).next() else {
+            return Types::ERROR;
+        };
+        let name = self.tree.text(self.tokens, node);
+        let token = self.tree.token_of(node);
+        if self.names_type(node) {
+            let Some(decl) = self.resolution.decl_of(object) else {
+                return Types::ERROR;
+            };
+            let owner = self.fresh_instance(decl);
+            if let Some(index) = self.variant(owner, name) {
+                // A variant with a payload constructs the enum when called.
+                self.typing.members[*node] = Some(Member::Variant(index));
+                let params = self.variant_payload(owner, index);
+                return match params.is_empty() {
+                    true => owner,
+                    false => self.typing.types.intern(Type::Func { params, ret: owner }),
+                };
+            }
+            let Some((method, method_ty)) = self.method(owner, name) else {
+                let what = match self.typing.types.get(owner) {
+                    Type::Named(decl, _) if self.typing.enums.contains_key(decl) => {
+                        "variant or function"
+                    }
+                    _ => "function",
+                };
+                let message = format!("no {} `{}` in `{}`", what, name, self.display(owner));
+                self.emit_error(token, Code::T0008, message);
+                return Types::ERROR;
+            };
+            self.typing.members[*node] = Some(Member::Method(method));
+            return method_ty;
+        }
+
+        let ty = self.check_expr(object);
+        let ty = self.shallow(ty);
+        if name == "len" && matches!(self.typing.types.get(ty), Type::Array(..) | Type::Slice(_)) {
+            let ret = self.typing.types.int(IntType::Int);
+            return self.typing.types.intern(Type::Func {
+                params: vec![],
+                ret,
+            });
+        }
+        let field = match self.typing.types.get(ty) {
+            // Left to be reported where the value is declared.
+            Type::Error | Type::Var(_) => return Types::ERROR,
+            Type::Named(_, _) => self.fields(ty).and_then(|fields| {
+                let index = fields.iter().position(|field| field.name == name)?;
+                Some((index, fields[index].ty))
+            }),
+            _ => None,
+        };
+        if let Some((index, field_ty)) = field {
+            self.typing.members[*node] = Some(Member::Field(index));
+            return field_ty;
+        }
+        match self.method(ty, name) {
+            Some((method, method_ty)) if self.has_receiver(method) => {
+                self.typing.members[*node] = Some(Member::Method(method));
+                match self.typing.types.get(method_ty).clone() {
+                    Type::Func { params, ret } => self.typing.types.intern(Type::Func {
+                        params: params[1..].to_vec(),
+                        ret,
+                    }),
+                    _ => Types::ERROR,
+                }
+            }
+            Some(_) => {
+                let owner = self.display(ty);
+                let message = format!(
+                    "`{}` does not take `self`, call it as `{}.{}(...)`",
+                    name, owner, name
+                );
+                self.emit_error(token, Code::T0008, message);
+                Types::ERROR
+            }
+            None => {
+                let message = format!("no field or method `{}` on `{}`", name, self.display(ty));
+                self.emit_error(token, Code::T0008, message);
+                Types::ERROR
+            }
+        }
+    }
+
+    /// Whether `node` is `Type.name`, with a type on the left.
+    fn names_type(&self, node: Node) -> bool {
+        let object = self.tree.children(node).next();
+        self.tree.kind_of(node) == NodeKind::FieldAccess
+            && object.is_some_and(|object| {
+                self.tree.kind_of(object) == NodeKind::Name
+                    && self.resolution.decl_of(object).is_some_and(|decl| {
+                        let kind = self.resolution.decl(decl).kind;
+                        kind.is_type() && !matches!(kind, DeclKind::Import | DeclKind::Interface)
+                    })
+            })
+    }
+
+    /// `Point { x: 1.0, y: 2.0 }`, which has to give every field once.
+    fn check_struct_literal(&mut self, node: Node) -> TypeId {
+        let mut children = self.tree.children(node);
+        let Some(name) = children.next() else {
+            return Types::ERROR;
+        };
+        let inits: Vec<Node> = children
+            .filter(|&child| self.tree.kind_of(child) == NodeKind::FieldInit)
+            .collect();
+        let ty = match self.resolution.decl_of(name) {
+            Some(decl) => self.fresh_instance(decl),
+            None => Types::ERROR,
+        };
+        let fields = self.fields(ty);
+        let Some(fields) = fields else {
+            if ty != Types::ERROR {
+                let message = format!("`{}` is not a struct", self.display(ty));
+                self.emit_error(self.tree.token_of(name), Code::T0011, message);
+            }
+            for init in inits {
+                if let Some(value) = self.tree.children(init).next() {
+                    self.check_expr(value);
+                }
+            }
+            return Types::ERROR;
+        };
+
+        let mut given = vec![];
+        for init in inits {
+            let field = self.tree.text(self.tokens, init);
+            let token = self.tree.token_of(init);
+            let value = self.tree.children(init).next();
+            let expected = match fields.iter().position(|f| f.name == field) {
+                Some(index) if given.contains(&index) => {
+                    let message = format!("field `{}` is given more than once", field);
+                    self.emit_error(token, Code::T0010, message);
+                    None
+                }
+                Some(index) => {
+                    given.push(index);
+                    Some(fields[index].ty)
+                }
+                None => {
+                    let message = format!("no field `{}` on `{}`", field, self.display(ty));
+                    self.emit_error(token, Code::T0008, message);
+                    None
+                }
+            };
+            match (value, expected) {
+                (Some(value), Some(expected)) => self.expect_expr(value, expected),
+                (Some(value), None) => {
+                    self.check_expr(value);
+                }
+                _ => {}
+            }
+        }
+        let missing: Vec<String> = (0..fields.len())
+            .filter(|index| !given.contains(index))
+            .map(|index| format!("`{}`", fields[index].name))
+            .collect();
+        if !missing.is_empty() {
+            let message = format!(
+                "missing field{} {} in `{}` literal",
+                if missing.len() == 1 { "" } else { "s" },
+                missing.join(", "),
+                self.display(ty)
+            );
+            self.emit_error(self.tree.token_of(name), Code::T0009, message);
+        }
+        ty
+    }
+
+    fn check_conversion(&mut self, node: Node, decl: DeclId, args: &[Node]) -> TypeId {
+        let target = self.named_type(decl);
+        let tys: Vec<TypeId> = args.iter().map(|&arg| self.check_expr(arg)).collect();
+        let types = &self.typing.types;
+        let valid = match tys.as_slice() {
+            [ty] => *ty == Types::ERROR || (types.is_numeric(*ty) && types.is_numeric(target)),
+            _ => false,
+        };
+        if !valid {
+            let from: Vec<String> = tys.iter().map(|&ty| self.display(ty)).collect();
+            let message = format!(
+                "cannot convert `({})` to `{}`, conversions take a single number",
+                from.join(", "),
+                self.display(target)
+            );
+            self.emit_error(self.tree.token_of(node), Code::T0005, message);
+        }
+        target
+    }
+
+    fn check_closure(&mut self, node: Node) -> TypeId {
+        let params = match self.tree.children(node).find(|&child| {
+            matches!(
+                self.tree.kind_of(child),
+                NodeKind::ClosureParams | NodeKind::ParamList
+            )
+        }) {
+            Some(list) => self.declare_params(list),
+            None => vec![],
+        };
+        let declared = self
+            .tree
+            .child(node, NodeKind::ReturnType)
+            .map(|_| self.return_type(node));
+        let body = self.tree.children(node).last();
+        let ret = match body {
+            Some(body) if self.tree.kind_of(body) == NodeKind::Block => {
+                // Without `->`, a function expression returns `()`, and a
+                // closure whatever its block produces.
+                let ret = match (declared, self.tree.kind_of(node)) {
+                    (Some(ret), _) => ret,
+                    (None, NodeKind::ClosureExpr) => self.fresh_var(false),
+                    (None, _) => Types::UNIT,
+                };
+                self.check_body(body, ret);
+                // A block ending in a statement produces nothing.
+                if declared.is_none() && self.shallow(ret) == ret {
+                    self.unify(ret, Types::UNIT);
+                }
+                ret
+            }
+            Some(body) => {
+                let ret = declared.unwrap_or_else(|| self.fresh_var(false));
+                self.returns.push(ret);
+                self.expect_expr(body, ret);
+                self.returns.pop();
+                ret
+            }
+            None => Types::ERROR,
+        };
+        self.typing.types.intern(Type::Func { params, ret })
+    }
+
+    fn check_match(&mut self, node: Node) -> TypeId {
+        let mut children = self.tree.children(node);
+        let scrutinee = match children.next() {
+            Some(scrutinee) => self.check_expr(scrutinee),
+            None => Types::ERROR,
+        };
+        let mut fits = true;
+        let mut result: Option<TypeId> = None;
+        let arms = children
+            .filter(|&child| self.tree.kind_of(child) == NodeKind::MatchArmList)
+            .flat_map(|list| self.tree.children(list))
+            .filter(|&arm| self.tree.kind_of(arm) == NodeKind::MatchArm);
+        for arm in arms.collect::<Vec<_>>() {
+            let mut parts = self.tree.children(arm);
+            if let Some(pattern) = parts.next() {
+                fits &= self.check_pattern(pattern, scrutinee);
+            }
+            for part in parts {
+                if self.tree.kind_of(part) == NodeKind::MatchGuard {
+                    if let Some(guard) = self.tree.children(part).next() {
+                        self.expect_expr(guard, Types::BOOL);
+                    }
+                    continue;
+                }
+                match result {
+                    Some(expected) => self.expect_expr(part, expected),
+                    None => {
+                        let ty = self.check_expr(part);
+                        result = Some(ty).filter(|&ty| ty != Types::ERROR);
+                    }
+                }
+            }
+        }
+        if fits {
+            self.matches.push((node, scrutinee));
+        }
+        result.unwrap_or(Types::ERROR)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::check::test::{check, codes};
+    use crate::diagnostics::Code;
+
+    #[test]
+    fn test_expressions() {
+        let source = "func half(x: Real) -> Real { x / 2.0 }\n\
+                      let n: u8 = u8(200)\n\
+                      1.0 + 9.732e-6\n\
+                      1 + 2 * 3 < 4\n\
+                      half(Real(n)) == 1.0 && true\n\
+                      0..10\n\
+                      |a: Int, b: Int| a * b\n\
+                      n % u8(3)\n\
+                      (n, (true, 1.0))\n\
+                      (n,)\n\
+                      ()";
+        let (types, diagnostics) = check(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(
+            types,
+            vec![
+                "Real",
+                "Bool",
+                "Bool",
+                "Range[Int]",
+                "func(Int, Int) -> Int",
+                "u8",
+                "(u8, (Bool, Real))",
+                "u8",
+                "()"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_arrays() {
+        let source = "const N = 2 * 2\n\
+                      let a: [u8; N] = [1, 2, 3, 4]\n\
+                      let s: [Real] = [0.5; 3][0..2]\n\
+                      a\n\
+                      a[3]\n\
+                      a[1..3]\n\
+                      s.len()\n\
+                      [[1, 2], [3, 4]][1][0]\n\
+                      for x in s { let y: Real = x }";
+        let (types, diagnostics) = check(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(types, vec!["[u8; 4]", "u8", "[u8]", "Int", "Int"]);
+
+        let array = "let a = [1, 2, 3, 4]\n";
+        assert_eq!(codes(&format!("{}a[4]", array)), vec![Code::T0017]);
+        assert_eq!(codes(&format!("{}a[-1]", array)), vec![Code::T0017]);
+        assert_eq!(codes(&format!("{}a[1..5]", array)), vec![Code::T0017]);
+        assert_eq!(codes(&format!("{}a[3..1]", array)), vec![Code::T0017]);
+        assert_eq!(
+            codes(&format!("{}const I = N * 2\na[I]\nconst N: Int = 2", array)),
+            vec![Code::T0017]
+        );
+        assert_eq!(codes(&format!("{}a[1..4]\na[0..0]", array)), vec![]);
+        assert_eq!(codes(&format!("{}a[true]", array)), vec![Code::T0002]);
+        assert_eq!(codes("let n = 3\nn[0]"), vec![Code::T0001]);
+        assert_eq!(codes("let a: [Int; 2] = [1, 2, 3]"), vec![Code::T0002]);
+        assert_eq!(codes("let a = [1, true]"), vec![Code::T0002]);
+        // Lengths that are not constants are reported when evaluating them.
+        assert_eq!(codes("let n = 3\nlet a: [Int; n]"), vec![]);
+    }
+
+    #[test]
+    fn test_values() {
+        let source = "func pick(c: Bool, a: Int) -> Int {\n\
+                      if c { a } else if !c { 0 } else { 1.0 }\n}\n\
+                      func size(x: (Int, Real)) -> Real {\n\
+                      match x { (0, r) => r, (n, _) => Real(n) }\n}";
+        let (_, diagnostics) = check(source);
+        let codes: Vec<Code> = diagnostics.iter().filter_map(|d| d.code).collect();
+        assert_eq!(codes, vec![Code::T0002]);
+        assert_eq!(diagnostics[0].location.row, 1);
+    }
+
+    #[test]
+    fn test_returns() {
+        assert_eq!(
+            codes(
+                "func f(c: Bool) -> Int {\nlet n = match c { true => return 1, false => 2 }\nn }"
+            ),
+            vec![]
+        );
+        assert_eq!(
+            codes("func f(c: Bool) -> Int { match c { true => return 1.0, false => 2 } }"),
+            vec![Code::T0002]
+        );
+        assert_eq!(
+            codes("let f = |x: Int| -> Bool { match x { 0 => return true, _ => false } }"),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use super::{literal_value, Context, Instance, Var, MAX_NESTING};
+use crate::diagnostics::Code;
+use crate::parse_tree::{Node, NodeKind};
+use crate::resolve::DeclId;
+use crate::types::{IntType, Type, TypeId, Types};
+
+impl<'t> Context<'_, 't> {
+    pub(super) fn fresh_var(&mut self, integer: bool) -> TypeId {
+        let var = self.vars.len() as u32;
+        self.vars.push(Var {
+            integer,
+            bound: None,
+        });
+        match integer {
+            true => self.typing.types.intern(Type::IntVar(var)),
+            false => self.typing.types.intern(Type::Var(var)),
+        }
+    }
+
+    /// Follows bound variables to the type they stand for.
+    pub(super) fn shallow(&self, ty: TypeId) -> TypeId {
+        match self.typing.types.get(ty) {
+            Type::Var(var) | Type::IntVar(var) => match self.vars[*var as usize].bound {
+                Some(bound) => self.shallow(bound),
+                None => ty,
+            },
+            _ => ty,
+        }
+    }
+
+    /// Replaces the bound variables anywhere in `ty`.
+    pub(super) fn resolve(&mut self, ty: TypeId) -> TypeId {
+        let ty = self.shallow(ty);
+        let resolved = match self.typing.types.get(ty).clone() {
+            Type::Range(element) => Type::Range(self.resolve(element)),
+            Type::Array(element, len) => Type::Array(self.resolve(element), len),
+            Type::Slice(element) => Type::Slice(self.resolve(element)),
+            Type::Tuple(elements) => {
+                Type::Tuple(elements.into_iter().map(|ty| self.resolve(ty)).collect())
+            }
+            Type::Named(decl, args) if !args.is_empty() => {
+                Type::Named(decl, args.into_iter().map(|ty| self.resolve(ty)).collect())
+            }
+            Type::Func { params, ret } => Type::Func {
+                params: params.into_iter().map(|ty| self.resolve(ty)).collect(),
+                ret: self.resolve(ret),
+            },
+            _ => return ty,
+        };
+        self.typing.types.intern(resolved)
+    }
+
+    /// Makes `a` and `b` the same type by binding variables in them, and
+    /// returns whether that was possible.
+    pub(super) fn unify(&mut self, a: TypeId, b: TypeId) -> bool {
+        let (a, b) = (self.shallow(a), self.shallow(b));
+        if a == b || a == Types::ERROR || b == Types::ERROR {
+            return true;
+        }
+        let types = &self.typing.types;
+        match (types.get(a).clone(), types.get(b).clone()) {
+            // The newer variable is bound to the older one, so that a
+            // variable unified with fresh ones over and over, as `d` in
+            // `d += 1`, does not grow a chain that `shallow` has to follow.
+            (Type::Var(a_var), Type::Var(b_var)) | (Type::IntVar(a_var), Type::IntVar(b_var)) => {
+                match a_var < b_var {
+                    true => self.bind(b_var, a),
+                    false => self.bind(a_var, b),
+                }
+            }
+            (Type::Var(var), _) => self.bind(var, b),
+            (_, Type::Var(var)) => self.bind(var, a),
+            (Type::IntVar(var), Type::Int(_)) => self.bind(var, b),
+            (Type::Int(_), Type::IntVar(var)) => self.bind(var, a),
+            (Type::Range(a), Type::Range(b)) | (Type::Slice(a), Type::Slice(b)) => self.unify(a, b),
+            (Type::Array(a, a_len), Type::Array(b, b_len)) if a_len == b_len => self.unify(a, b),
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => self.unify_all(&a, &b),
+            (Type::Named(a, a_args), Type::Named(b, b_args)) if a == b => {
+                self.unify_all(&a_args, &b_args)
+            }
+            (
+                Type::Func {
+                    params: a,
+                    ret: a_ret,
+                },
+                Type::Func {
+                    params: b,
+                    ret: b_ret,
+                },
+            ) if a.len() == b.len() => self.unify_all(&a, &b) & self.unify(a_ret, b_ret),
+            _ => false,
+        }
+    }
+
+    fn unify_all(&mut self, a: &[TypeId], b: &[TypeId]) -> bool {
+        a.iter()
+            .zip(b)
+            .fold(true, |same, (&a, &b)| self.unify(a, b) && same)
+    }
+
+    fn bind(&mut self, var: u32, ty: TypeId) -> bool {
+        if self.occurs(var, ty) {
+            return false;
+        }
+        self.vars[var as usize].bound = Some(ty);
+        true
+    }
+
+    fn occurs(&self, var: u32, ty: TypeId) -> bool {
+        match self.typing.types.get(self.shallow(ty)) {
+            Type::Var(other) | Type::IntVar(other) => *other == var,
+            Type::Range(element) | Type::Array(element, _) | Type::Slice(element) => {
+                self.occurs(var, *element)
+            }
+            Type::Tuple(elements) | Type::Named(_, elements) => {
+                elements.iter().any(|&ty| self.occurs(var, ty))
+            }
+            Type::Func { params, ret } => {
+                params.iter().any(|&ty| self.occurs(var, ty)) || self.occurs(var, *ret)
+            }
+            _ => false,
+        }
+    }
+
+    /// Settles what checking left open: integer literals nothing constrains
+    /// become `Int`, declarations whose type is still unknown are reported,
+    /// and deferred operators and literal ranges are checked.
+    pub(super) fn finish(&mut self) {
+        let int = self.typing.types.int(IntType::Int);
+        for var in &mut self.vars {
+            if var.integer && var.bound.is_none() {
+                var.bound = Some(int);
+            }
+        }
+
+        // Declarations that are unknown themselves come first, so that
+        // `let f = |x| x` is reported at `x` rather than at `f`.
+        let mut reported = vec![];
+        let decls = 0..self.typing.decl_types.len();
+        let (bare, rest): (Vec<usize>, Vec<usize>) = decls.partition(|&decl| {
+            self.typing.decl_types[decl]
+                .is_some_and(|ty| matches!(self.typing.types.get(self.shallow(ty)), Type::Var(_)))
+        });
+        for decl in bare.into_iter().chain(rest) {
+            let Some(ty) = self.typing.decl_types[decl] else {
+                continue;
+            };
+            let mut open = vec![];
+            self.open_vars(ty, &mut open);
+            if open.iter().all(|var| reported.contains(var)) {
+                continue;
+            }
+            reported.extend(open);
+            let decl = self.resolution.decl(DeclId(decl));
+            if let Some(node) = decl.node {
+                let message = format!(
+                    "cannot infer the type of `{}`, add a type annotation",
+                    decl.name
+                );
+                self.emit_error(self.tree.token_of(node), Code::T0007, message);
+            }
+        }
+        // Those have been reported, so nothing else is about them.
+        for var in &mut self.vars {
+            var.bound.get_or_insert(Types::ERROR);
+        }
+
+        for deferred in std::mem::take(&mut self.deferred) {
+            let operands: Vec<TypeId> = deferred
+                .operands
+                .iter()
+                .map(|&ty| self.resolve(ty))
+                .collect();
+            let valid = match operands.as_slice() {
+                [operand] => self.prefix_applies(deferred.op, *operand),
+                &[lhs, rhs] => self.binary_type(deferred.op, true, lhs, rhs).is_some(),
+                _ => true,
+            };
+            if !valid {
+                self.operator_error(deferred.op, deferred.token, &operands);
+            }
+        }
+
+        for index in 0..self.typing.node_types.len() {
+            if let Some(ty) = self.typing.node_types[index] {
+                self.typing.node_types[index] = Some(self.resolve(ty));
+            }
+        }
+        for index in 0..self.typing.decl_types.len() {
+            if let Some(ty) = self.typing.decl_types[index] {
+                self.typing.decl_types[index] = Some(self.resolve(ty));
+            }
+        }
+
+        for node in self.tree.postorder() {
+            if self.tree.kind_of(node) == NodeKind::IntLiteral {
+                self.check_literal_range(node);
+            }
+        }
+        self.check_instances();
+        self.register_instances();
+        for (node, scrutinee) in std::mem::take(&mut self.matches) {
+            self.check_coverage(node, scrutinee);
+        }
+    }
+
+    /// Lists the fields and variants of every instance of a generic type,
+    /// including those only its own fields use, for the passes after
+    /// checking.
+    fn register_instances(&mut self) {
+        // Instances made here are registered as they come.
+        let mut index = 0;
+        while index < self.typing.types.len() {
+            let ty = TypeId(index);
+            index += 1;
+            let Type::Named(_, args) = self.typing.types.get(ty) else {
+                continue;
+            };
+            if args.is_empty() || self.resolve(ty) != ty || self.nesting(ty) > MAX_NESTING {
+                continue;
+            }
+            let fields = self.fields(ty).unwrap_or_default();
+            let variants = self.variants(ty).unwrap_or_default();
+            self.typing.instances.insert(ty, (fields, variants));
+        }
+    }
+
+    /// How many types deep `ty` is.
+    fn nesting(&self, ty: TypeId) -> usize {
+        let inner = match self.typing.types.get(ty) {
+            Type::Range(element) | Type::Array(element, _) | Type::Slice(element) => vec![*element],
+            Type::Tuple(elements) | Type::Named(_, elements) => elements.clone(),
+            Type::Func { params, ret } => params.iter().copied().chain([*ret]).collect(),
+            _ => vec![],
+        };
+        1 + inner
+            .into_iter()
+            .map(|ty| self.nesting(ty))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Collects the unbound variables in `ty`.
+    fn open_vars(&self, ty: TypeId, open: &mut Vec<u32>) {
+        match self.typing.types.get(self.shallow(ty)) {
+            Type::Var(var) | Type::IntVar(var) if !open.contains(var) => open.push(*var),
+            Type::Range(element) | Type::Array(element, _) | Type::Slice(element) => {
+                self.open_vars(*element, open)
+            }
+            Type::Tuple(elements) | Type::Named(_, elements) => {
+                for &ty in elements {
+                    self.open_vars(ty, open);
+                }
+            }
+            Type::Func { params, ret } => {
+                for &ty in params {
+                    self.open_vars(ty, open);
+                }
+                self.open_vars(*ret, open);
+            }
+            _ => {}
+        }
+    }
+
+    fn check_literal_range(&mut self, node: Node) {
+        let Some(&Type::Int(int)) = self
+            .typing
+            .type_of(node)
+            .map(|ty| self.typing.types.get(ty))
+        else {
+            return;
+        };
+        let negation = self.parent(node).filter(|&parent| {
+            self.tree.kind_of(parent) == NodeKind::PrefixOperator
+                && self.tree.text(self.tokens, parent) == "-"
+        });
+        // Negating an unsigned integer is reported as an operator error.
+        if negation.is_some() && !int.is_signed() {
+            return;
+        }
+        let text = self.tree.text(self.tokens, node);
+        let value = literal_value(text).map(|value| match negation {
+            Some(_) => -value,
+            None => value,
+        });
+        // The end of a range pattern is excluded, so `0..256` covers every
+        // `u8`.
+        let range_end = self
+            .parent(node)
+            .filter(|&parent| self.tree.kind_of(parent) == NodeKind::LiteralPattern)
+            .and_then(|pattern| Some((pattern, self.parent(pattern)?)))
+            .is_some_and(|(pattern, range)| {
+                self.tree.kind_of(range) == NodeKind::RangePattern
+                    && self.tree.children(range).nth(1) == Some(pattern)
+            });
+        let max = int.max() + i128::from(range_end);
+        if value.is_some_and(|value| (int.min()..=max).contains(&value)) {
+            return;
+        }
+        let (token, literal) = match negation {
+            Some(negation) => (self.tree.token_of(negation), format!("-{}", text)),
+            None => (self.tree.token_of(node), text.to_string()),
+        };
+        let message = format!(
+            "integer literal `{}` does not fit in `{}`, whose values range from {} to {}",
+            literal,
+            int.name(),
+            int.min(),
+            int.max()
+        );
+        self.emit_error(token, Code::T0006, message);
+    }
+
+    /// A function's type with fresh variables, or the type arguments in
+    /// `args`, for its type parameters.
+    pub(super) fn instantiate(&mut self, node: Node, func: DeclId, args: Option<Node>) -> TypeId {
+        let ty = self.decl_type(func);
+        let params = self.type_params(func);
+        let token = self.tree.token_of(node);
+        let args: Vec<TypeId> = match args {
+            Some(args) => {
+                let args: Vec<Node> = self.tree.children(args).skip(1).collect();
+                let tys: Vec<TypeId> = args.iter().map(|&arg| self.lower_type(arg)).collect();
+                if tys.len() == params.len() {
+                    tys
+                } else {
+                    let message = format!(
+                        "expected {} type argument{}, found {}",
+                        params.len(),
+                        if params.len() == 1 { "" } else { "s" },
+                        tys.len()
+                    );
+                    self.emit_error(token, Code::T0004, message);
+                    return Types::ERROR;
+                }
+            }
+            None => params.iter().map(|_| self.fresh_var(false)).collect(),
+        };
+        if params.is_empty() {
+            return ty;
+        }
+        let map: Vec<(TypeId, TypeId)> = params
+            .iter()
+            .zip(&args)
+            .map(|(&param, &arg)| (self.typing.types.intern(Type::Param(param)), arg))
+            .collect();
+        self.instances.push(Instance {
+            token,
+            func,
+            args: params.into_iter().zip(args).collect(),
+        });
+        self.substitute(ty, &map)
+    }
+
+    /// `ty` with the types in `map` replaced.
+    pub(super) fn substitute(&mut self, ty: TypeId, map: &[(TypeId, TypeId)]) -> TypeId {
+        if let Some(&(_, to)) = map.iter().find(|&&(from, _)| from == ty) {
+            return to;
+        }
+        let substituted = match self.typing.types.get(ty).clone() {
+            Type::Range(element) => Type::Range(self.substitute(element, map)),
+            Type::Array(element, len) => Type::Array(self.substitute(element, map), len),
+            Type::Slice(element) => Type::Slice(self.substitute(element, map)),
+            Type::Tuple(elements) => Type::Tuple(
+                elements
+                    .into_iter()
+                    .map(|ty| self.substitute(ty, map))
+                    .collect(),
+            ),
+            Type::Named(decl, args) if !args.is_empty() => Type::Named(
+                decl,
+                args.into_iter()
+                    .map(|ty| self.substitute(ty, map))
+                    .collect(),
+            ),
+            Type::Func { params, ret } => Type::Func {
+                params: params
+                    .into_iter()
+                    .map(|ty| self.substitute(ty, map))
+                    .collect(),
+                ret: self.substitute(ret, map),
+            },
+            _ => return ty,
+        };
+        self.typing.types.intern(substituted)
+    }
+
+    /// Reports type arguments that do not implement the bounds of their
+    /// parameters, once inference has settled them.
+    fn check_instances(&mut self) {
+        for instance in std::mem::take(&mut self.instances) {
+            for (param, arg) in instance.args {
+                let arg = self.resolve(arg);
+                for interface in self.bounds_of(param) {
+                    if self.implements(arg, interface) {
+                        continue;
+                    }
+                    let message = format!(
+                        "`{}` does not implement `{}`, which `{}` requires of `{}`",
+                        self.display(arg),
+                        self.resolution.decl(interface).name,
+                        self.resolution.decl(instance.func).name,
+                        self.resolution.decl(param).name
+                    );
+                    self.emit_error(instance.token, Code::T0015, message);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::check::test::{check, codes};
+    use crate::diagnostics::Code;
+
+    #[test]
+    fn test_inference() {
+        let source = "let a: u8 = 200\n\
+                      let b = 3\n\
+                      func twice(f: func(u8) -> u8, x: u8 = 1) -> u8 { f(f(x)) }\n\
+                      1.0 + 9.732e-6\n\
+                      a + 1\n\
+                      b\n\
+                      |x| x + a\n\
+                      twice(|x| x * 2)\n\
+                      -128 + i8(0)\n\
+                      1e3\n\
+                      |x: Real| { x * 2.0 }\n\
+                      || { let c = b }";
+        let (types, diagnostics) = check(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(
+            types,
+            vec![
+                "Real",
+                "u8",
+                "Int",
+                "func(u8) -> u8",
+                "u8",
+                "i8",
+                "Real",
+                "func(Real) -> Real",
+                "func()"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inference_errors() {
+        let (_, diagnostics) = check("let a: u8 = 300");
+        assert_eq!(
+            diagnostics[0].message,
+            "integer literal `300` does not fit in `u8`, whose values range from 0 to 255"
+        );
+        assert_eq!(codes("let a: i8 = -129"), vec![Code::T0006]);
+        assert_eq!(codes("let a: i8 = -128"), vec![]);
+        assert_eq!(codes("let a: u16 = 70000"), vec![Code::T0006]);
+        assert_eq!(codes("let a = 1e-5\nlet b: Real = 1e5"), vec![]);
+        assert_eq!(codes("let a: u16 = 1e3"), vec![Code::T0002]);
+        assert_eq!(codes("99999999999999999999"), vec![Code::T0006]);
+        assert_eq!(codes("let a: u8 = -1"), vec![Code::T0001]);
+        assert_eq!(codes("func f(x: u8 = 256) {}"), vec![Code::T0006]);
+
+        let (_, diagnostics) = check("let f = |x| x");
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot infer the type of `x`, add a type annotation"
+        );
+        assert_eq!(
+            codes("var v\nlet w = |a, b| a < b"),
+            vec![Code::T0007, Code::T0007]
+        );
+        assert_eq!(codes("var v\nv = 1.5"), vec![]);
+    }
+}
@@ -0,0 +1,933 @@
+use super::{Context, Field, Instance, Variant, MAX_NESTING};
+use crate::consteval::Value;
+use crate::diagnostics::Code;
+use crate::parse_tree::{Node, NodeKind};
+use crate::resolve::{DeclId, DeclKind};
+use crate::types::{IntType, Type, TypeId, Types};
+
+impl<'t> Context<'_, 't> {
+    pub(super) fn lower_type(&mut self, node: Node) -> TypeId {
+        // Signatures are lowered again when their bodies are checked.
+        if let Some(ty) = self.typing.node_types[*node] {
+            return ty;
+        }
+        let ty = match self.tree.kind_of(node) {
+            NodeKind::TypeName => match self.resolution.decl_of(node) {
+                Some(decl) if self.resolution.decl(decl).kind == DeclKind::Interface => {
+                    let message = format!(
+                        "`{}` is an interface, not a type",
+                        self.tree.text(self.tokens, node)
+                    );
+                    self.emit_error(self.tree.token_of(node), Code::T0014, message);
+                    Types::ERROR
+                }
+                Some(decl) => {
+                    let ty = self.named_type(decl);
+                    self.apply_type_args(node, decl, ty, vec![])
+                }
+                None => Types::ERROR,
+            },
+            NodeKind::TupleType => {
+                let elements: Vec<TypeId> = self
+                    .tree
+                    .children(node)
+                    .skip(1)
+                    .map(|child| self.lower_type(child))
+                    .collect();
+                match elements.as_slice() {
+                    [] => Types::UNIT,
+                    [single] => *single,
+                    _ => self.typing.types.intern(Type::Tuple(elements)),
+                }
+            }
+            NodeKind::ArrayType => {
+                let mut children = self.tree.children(node).skip(1);
+                let element = match children.next() {
+                    Some(element) => self.lower_type(element),
+                    None => Types::ERROR,
+                };
+                match children.next() {
+                    Some(length) => self.array_type(element, length),
+                    None => Types::ERROR,
+                }
+            }
+            NodeKind::SliceType => match self.tree.children(node).nth(1) {
+                Some(element) => {
+                    let element = self.lower_type(element);
+                    self.typing.types.intern(Type::Slice(element))
+                }
+                None => Types::ERROR,
+            },
+            NodeKind::FuncType => {
+                let params = match self.tree.child(node, NodeKind::FuncTypeParams) {
+                    Some(params) => self
+                        .tree
+                        .children(params)
+                        .map(|param| self.lower_type(param))
+                        .collect(),
+                    None => vec![],
+                };
+                let ret = self.return_type(node);
+                self.typing.types.intern(Type::Func { params, ret })
+            }
+            NodeKind::GenericType => {
+                let mut children = self.tree.children(node);
+                let (base, args) = (children.next(), children.next());
+                let args: Vec<Node> = args
+                    .into_iter()
+                    .flat_map(|args| self.tree.children(args).skip(1))
+                    .collect();
+                let args = args.into_iter().map(|arg| self.lower_type(arg)).collect();
+                match base.and_then(|base| self.resolution.decl_of(base)) {
+                    Some(decl) => {
+                        let ty = self.named_type(decl);
+                        self.apply_type_args(node, decl, ty, args)
+                    }
+                    None => Types::ERROR,
+                }
+            }
+            NodeKind::PointerType | NodeKind::ReferenceType => {
+                let what = match self.tree.kind_of(node) {
+                    NodeKind::PointerType => "pointer",
+                    _ => "reference",
+                };
+                let message = format!("{} types are not supported yet", what);
+                self.emit_error(self.tree.token_of(node), Code::T0018, message);
+                Types::ERROR
+            }
+            _ => Types::ERROR,
+        };
+        self.typing.node_types[*node] = Some(ty);
+        ty
+    }
+
+    /// `ty`, the type `decl` names, with `args` for its type parameters,
+    /// reporting a different number of them.
+    fn apply_type_args(
+        &mut self,
+        node: Node,
+        decl: DeclId,
+        ty: TypeId,
+        args: Vec<TypeId>,
+    ) -> TypeId {
+        let params = match self.resolution.decl(decl).kind {
+            DeclKind::Type => self.type_params(decl),
+            _ => vec![],
+        };
+        if ty == Types::ERROR || (params.is_empty() && args.is_empty()) {
+            return ty;
+        }
+        if params.len() != args.len() {
+            let message = format!(
+                "`{}` takes {} type argument{}, found {}",
+                self.resolution.decl(decl).name,
+                params.len(),
+                if params.len() == 1 { "" } else { "s" },
+                args.len()
+            );
+            self.emit_error(self.tree.token_of(node), Code::T0004, message);
+            return Types::ERROR;
+        }
+        self.instances.push(Instance {
+            token: self.tree.token_of(node),
+            func: decl,
+            args: params.iter().copied().zip(args.iter().copied()).collect(),
+        });
+        let map = self.type_arg_map(decl, &args);
+        self.substitute(ty, &map)
+    }
+
+    /// The type `decl` names with fresh variables for its type parameters,
+    /// for a use that infers them, as in `Opt.Some(1)`.
+    pub(super) fn fresh_instance(&mut self, decl: DeclId) -> TypeId {
+        let ty = self.named_type(decl);
+        if self.resolution.decl(decl).kind != DeclKind::Type {
+            return ty;
+        }
+        let args: Vec<TypeId> = self
+            .type_params(decl)
+            .iter()
+            .map(|_| self.fresh_var(false))
+            .collect();
+        let map = self.type_arg_map(decl, &args);
+        self.substitute(ty, &map)
+    }
+
+    /// Pairs the type parameters of `decl` with `args`, to substitute one
+    /// for the other.
+    fn type_arg_map(&mut self, decl: DeclId, args: &[TypeId]) -> Vec<(TypeId, TypeId)> {
+        self.type_params(decl)
+            .into_iter()
+            .zip(args)
+            .map(|(param, &arg)| (self.typing.types.intern(Type::Param(param)), arg))
+            .collect()
+    }
+
+    /// The fields of the struct `ty`, with its type arguments in place.
+    pub(super) fn fields(&mut self, ty: TypeId) -> Option<Vec<Field>> {
+        let Type::Named(decl, args) = self.typing.types.get(ty).clone() else {
+            return None;
+        };
+        let fields = self.typing.structs.get(&decl)?.clone();
+        let map = self.type_arg_map(decl, &args);
+        let fields = fields
+            .into_iter()
+            .map(|field| Field {
+                ty: self.substitute(field.ty, &map),
+                ..field
+            })
+            .collect();
+        Some(fields)
+    }
+
+    /// The variants of the enum `ty`, with its type arguments in place.
+    pub(super) fn variants(&mut self, ty: TypeId) -> Option<Vec<Variant>> {
+        let Type::Named(decl, args) = self.typing.types.get(ty).clone() else {
+            return None;
+        };
+        let variants = self.typing.enums.get(&decl)?.clone();
+        let map = self.type_arg_map(decl, &args);
+        let variants = variants
+            .into_iter()
+            .map(|variant| Variant {
+                payload: variant
+                    .payload
+                    .iter()
+                    .map(|&ty| self.substitute(ty, &map))
+                    .collect(),
+                ..variant
+            })
+            .collect();
+        Some(variants)
+    }
+
+    /// `[element; length]`. A length that is not a constant, or is
+    /// negative, is left to be reported when constants are evaluated.
+    pub(super) fn array_type(&mut self, element: TypeId, length: Node) -> TypeId {
+        let int = self.typing.types.int(IntType::Int);
+        let ty = self.check_expr(length);
+        if !self.expect(self.first_token(length), ty, int) {
+            return Types::ERROR;
+        }
+        match self
+            .constant(length)
+            .and_then(|len| u64::try_from(len).ok())
+        {
+            Some(len) => self.typing.types.intern(Type::Array(element, len)),
+            None => Types::ERROR,
+        }
+    }
+
+    /// The value of an integer expression known at compile time, if it has
+    /// one. Nothing is reported here: constants are evaluated, and their
+    /// errors reported, after checking.
+    pub(super) fn constant(&self, node: Node) -> Option<i128> {
+        let value = self.evaluator.evaluate_node(
+            self.tree,
+            self.tokens,
+            self.resolution,
+            self,
+            &self.parents,
+            node,
+        );
+        match value? {
+            Value::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(super) fn named_type(&mut self, decl: DeclId) -> TypeId {
+        let name = self.resolution.decl(decl).name.as_str();
+        match self.resolution.decl(decl).kind {
+            DeclKind::BuiltinType => match name {
+                "Bool" => Types::BOOL,
+                "Real" => Types::REAL,
+                "String" => Types::STRING,
+                _ => match IntType::from_name(name) {
+                    Some(ty) => self.typing.types.int(ty),
+                    None => Types::ERROR,
+                },
+            },
+            DeclKind::Type => self.type_decl(decl),
+            DeclKind::TypeParam => self.typing.types.intern(Type::Param(decl)),
+            _ => Types::ERROR,
+        }
+    }
+
+    /// Whether a value of type `ty` holds a value of the struct or enum
+    /// `target` in place, rather than behind a slice or function.
+    pub(super) fn contains(&mut self, ty: TypeId, target: DeclId) -> bool {
+        self.holds(ty, target, 0, &mut vec![])
+    }
+
+    /// `contains`, `depth` structs and enums into `ty`, with the types
+    /// looked into already in `seen`.
+    fn holds(&mut self, ty: TypeId, target: DeclId, depth: usize, seen: &mut Vec<TypeId>) -> bool {
+        match self.typing.types.get(ty).clone() {
+            Type::Named(decl, _) => {
+                if decl == target && depth > 0 {
+                    return true;
+                }
+                // Other types that contain themselves are reported where
+                // they are declared, and instantiating a generic one can go
+                // on forever, as in `W[T] = struct { w: W[(T, T)] }`.
+                if seen.contains(&ty) || depth > MAX_NESTING {
+                    return false;
+                }
+                seen.push(ty);
+                let fields = self.fields(ty).into_iter().flatten();
+                let payloads = self.variants(ty).into_iter().flatten();
+                let parts: Vec<TypeId> = fields
+                    .map(|field| field.ty)
+                    .chain(payloads.flat_map(|variant| variant.payload))
+                    .collect();
+                parts
+                    .into_iter()
+                    .any(|part| self.holds(part, target, depth + 1, seen))
+            }
+            Type::Tuple(elements) => elements
+                .into_iter()
+                .any(|element| self.holds(element, target, depth, seen)),
+            Type::Array(element, len) => len > 0 && self.holds(element, target, depth, seen),
+            Type::Range(element) => self.holds(element, target, depth, seen),
+            _ => false,
+        }
+    }
+
+    /// Structs and enums are types of their own; aliases stand for the type
+    /// they name.
+    pub(super) fn type_decl(&mut self, decl: DeclId) -> TypeId {
+        if let Some(ty) = self.typing.decl_types[decl.0] {
+            return ty;
+        }
+        let Some(decl_node) = self.resolution.decl(decl).node.and_then(|n| self.parent(n)) else {
+            return Types::ERROR;
+        };
+        let body = self.tree.children(decl_node).find(|&child| {
+            !matches!(
+                self.tree.kind_of(child),
+                NodeKind::Attribute | NodeKind::Pub | NodeKind::DeclName | NodeKind::TypeParamList
+            )
+        });
+        let ty = match body.map(|body| (body, self.tree.kind_of(body))) {
+            Some((_, NodeKind::StructType | NodeKind::EnumType)) => {
+                let params = self
+                    .type_params(decl)
+                    .into_iter()
+                    .map(|param| self.typing.types.intern(Type::Param(param)))
+                    .collect();
+                self.typing.types.intern(Type::Named(decl, params))
+            }
+            Some((_, _)) if self.aliases.contains(&decl) => {
+                let alias = self.resolution.decl(decl);
+                if let Some(name) = alias.node {
+                    let message = format!("type alias `{}` refers to itself", alias.name);
+                    self.emit_error(self.tree.token_of(name), Code::T0019, message);
+                }
+                Types::ERROR
+            }
+            Some((body, _)) => {
+                self.aliases.push(decl);
+                let ty = self.lower_type(body);
+                self.aliases.pop();
+                ty
+            }
+            None => Types::ERROR,
+        };
+        self.typing.decl_types[decl.0] = Some(ty);
+        ty
+    }
+
+    /// The type after `->`, or `()` without one.
+    pub(super) fn return_type(&mut self, node: Node) -> TypeId {
+        let ty = self
+            .tree
+            .child(node, NodeKind::ReturnType)
+            .and_then(|ret| self.tree.children(ret).next());
+        match ty {
+            Some(ty) => self.lower_type(ty),
+            None => Types::UNIT,
+        }
+    }
+
+    /// Gives the parameters of a function or closure their types, and
+    /// returns the types in order.
+    pub(super) fn declare_params(&mut self, list: Node) -> Vec<TypeId> {
+        let mut params = vec![];
+        for param in self.tree.children(list) {
+            if self.tree.kind_of(param) != NodeKind::Param {
+                continue;
+            }
+            let ty = self.tree.children(param).find(|&child| {
+                !matches!(
+                    self.tree.kind_of(child),
+                    NodeKind::DeclName | NodeKind::DefaultValue
+                )
+            });
+            let name = self.tree.child(param, NodeKind::DeclName);
+            let ty = match ty {
+                Some(ty) => self.lower_type(ty),
+                None if name.is_some_and(|name| self.tree.text(self.tokens, name) == "self") => {
+                    self.receiver_type(list)
+                }
+                None => self.fresh_var(false),
+            };
+            if let Some(name) = name {
+                self.set_decl_type(name, ty);
+            }
+            params.push(ty);
+        }
+        params
+    }
+
+    /// The type of the `impl` a method with parameters `list` is in. In
+    /// an `interface`, it is the type implementing it, a parameter of the
+    /// interface's own.
+    fn receiver_type(&mut self, list: Node) -> TypeId {
+        let Some(parent) = self.parent(list).and_then(|func| self.parent(func)) else {
+            return Types::ERROR;
+        };
+        match self.tree.kind_of(parent) {
+            NodeKind::ImplDecl => match self.impl_type(parent) {
+                Some(ty) => self.lower_type(ty),
+                None => Types::ERROR,
+            },
+            NodeKind::InterfaceDecl => match self
+                .tree
+                .child(parent, NodeKind::DeclName)
+                .and_then(|name| self.resolution.decl_of(name))
+            {
+                Some(interface) => self.typing.types.intern(Type::Param(interface)),
+                None => Types::ERROR,
+            },
+            _ => Types::ERROR,
+        }
+    }
+
+    /// The type an `impl` is for, after the interface in
+    /// `impl Interface for Type`.
+    fn impl_type(&self, node: Node) -> Option<Node> {
+        self.tree.children(node).find(|&child| {
+            !matches!(
+                self.tree.kind_of(child),
+                NodeKind::Attribute | NodeKind::ImplInterface
+            )
+        })
+    }
+
+    /// The interface of `impl Interface for Type`, reporting a name that is
+    /// not one.
+    fn impl_interface(&mut self, node: Node, report: bool) -> Option<DeclId> {
+        let name = self
+            .tree
+            .child(node, NodeKind::ImplInterface)
+            .and_then(|interface| self.tree.children(interface).next())?;
+        let decl = self.resolution.decl_of(name)?;
+        if self.resolution.decl(decl).kind == DeclKind::Interface {
+            return Some(decl);
+        }
+        if report {
+            let message = format!(
+                "`{}` is not an interface",
+                self.tree.text(self.tokens, name)
+            );
+            self.emit_error(self.tree.token_of(name), Code::T0014, message);
+        }
+        None
+    }
+
+    pub(super) fn declare_struct(&mut self, node: Node) {
+        let Some(body) = self.tree.child(node, NodeKind::StructType) else {
+            return;
+        };
+        let Some(decl) = self
+            .tree
+            .child(node, NodeKind::DeclName)
+            .and_then(|name| self.resolution.decl_of(name))
+        else {
+            return;
+        };
+        let mut fields = vec![];
+        for field in self.tree.children(body) {
+            let (Some(name), Some(ty)) = (
+                self.tree.child(field, NodeKind::DeclName),
+                self.tree.children(field).nth(1),
+            ) else {
+                continue;
+            };
+            let ty = self.lower_type(ty);
+            fields.push(Field {
+                name: self.tree.text(self.tokens, name).to_string(),
+                ty,
+            });
+        }
+        self.typing.structs.insert(decl, fields);
+    }
+
+    pub(super) fn declare_enum(&mut self, node: Node) {
+        let Some(body) = self.tree.child(node, NodeKind::EnumType) else {
+            return;
+        };
+        let Some(decl) = self
+            .tree
+            .child(node, NodeKind::DeclName)
+            .and_then(|name| self.resolution.decl_of(name))
+        else {
+            return;
+        };
+        let mut variants = vec![];
+        for variant in self.tree.children(body) {
+            let Some(name) = self.tree.child(variant, NodeKind::DeclName) else {
+                continue;
+            };
+            let payload = match self.tree.child(variant, NodeKind::VariantPayload) {
+                Some(payload) => {
+                    let tys: Vec<Node> = self.tree.children(payload).collect();
+                    tys.into_iter().map(|ty| self.lower_type(ty)).collect()
+                }
+                None => vec![],
+            };
+            variants.push(Variant {
+                name: self.tree.text(self.tokens, name).to_string(),
+                payload,
+            });
+        }
+        self.typing.enums.insert(decl, variants);
+    }
+
+    /// The index of the variant `name` of `ty`, if `ty` is an enum with one.
+    pub(super) fn variant(&self, ty: TypeId, name: &str) -> Option<usize> {
+        match self.typing.types.get(ty) {
+            Type::Named(decl, _) => self
+                .typing
+                .enums
+                .get(decl)?
+                .iter()
+                .position(|variant| variant.name == name),
+            _ => None,
+        }
+    }
+
+    pub(super) fn variant_payload(&mut self, ty: TypeId, index: usize) -> Vec<TypeId> {
+        match self.variants(ty) {
+            Some(mut variants) => variants.swap_remove(index).payload,
+            None => vec![],
+        }
+    }
+
+    pub(super) fn declare_impl(&mut self, node: Node) {
+        let Some(ty) = self.impl_type(node) else {
+            return;
+        };
+        let owner = self.lower_type(ty);
+        if let Some(interface) = self.impl_interface(node, true) {
+            let conflict = self
+                .impls
+                .iter()
+                .any(|&(other, ty, _)| other == interface && ty == owner);
+            if conflict {
+                let message = format!(
+                    "conflicting implementations of `{}` for `{}`",
+                    self.resolution.decl(interface).name,
+                    self.display(owner)
+                );
+                self.emit_error(self.tree.token_of(node), Code::T0016, message);
+                return;
+            }
+            self.impls.push((interface, owner, node));
+        }
+        for func in self.tree.children(node) {
+            let decl = self
+                .tree
+                .child(func, NodeKind::DeclName)
+                .and_then(|name| self.resolution.decl_of(name));
+            if let Some(decl) = decl {
+                self.methods.push((owner, decl));
+            }
+        }
+    }
+
+    /// The method `name` of `owner` and its type. A type parameter has the
+    /// methods of the interfaces it is bound by.
+    pub(super) fn method(&mut self, owner: TypeId, name: &str) -> Option<(DeclId, TypeId)> {
+        let found = self
+            .methods
+            .iter()
+            .find(|&&(ty, decl)| ty == owner && self.resolution.decl(decl).name == name);
+        if let Some(&(_, decl)) = found {
+            return Some((decl, self.decl_type(decl)));
+        }
+        let Type::Param(param) = *self.typing.types.get(owner) else {
+            return None;
+        };
+        for interface in self.bounds_of(param) {
+            if let Some(method) = self.interface_method(interface, name) {
+                let this = self.typing.types.intern(Type::Param(interface));
+                let ty = self.decl_type(method);
+                return Some((method, self.substitute(ty, &[(this, owner)])));
+            }
+        }
+        None
+    }
+
+    fn interface_methods(&self, interface: DeclId) -> Vec<DeclId> {
+        let Some(node) = self
+            .resolution
+            .decl(interface)
+            .node
+            .and_then(|name| self.parent(name))
+        else {
+            return vec![];
+        };
+        self.tree
+            .children(node)
+            .filter_map(|func| self.tree.child(func, NodeKind::DeclName))
+            .filter_map(|name| self.resolution.decl_of(name))
+            .collect()
+    }
+
+    fn interface_method(&self, interface: DeclId, name: &str) -> Option<DeclId> {
+        self.interface_methods(interface)
+            .into_iter()
+            .find(|&method| self.resolution.decl(method).name == name)
+    }
+
+    /// The type parameters of a function or type, in order.
+    pub(super) fn type_params(&self, func: DeclId) -> Vec<DeclId> {
+        let list = self
+            .resolution
+            .decl(func)
+            .node
+            .and_then(|name| self.parent(name))
+            .and_then(|node| self.tree.child(node, NodeKind::TypeParamList));
+        let Some(list) = list else {
+            return vec![];
+        };
+        self.tree
+            .children(list)
+            .filter_map(|param| self.tree.child(param, NodeKind::DeclName))
+            .filter_map(|name| self.resolution.decl_of(name))
+            .collect()
+    }
+
+    /// The interfaces a type parameter has to implement, as in `T: A + B`.
+    pub(super) fn bounds_of(&self, param: DeclId) -> Vec<DeclId> {
+        let bound = self
+            .resolution
+            .decl(param)
+            .node
+            .and_then(|name| self.parent(name))
+            .and_then(|param| self.tree.child(param, NodeKind::TypeBound));
+        let Some(bound) = bound else {
+            return vec![];
+        };
+        self.tree
+            .children(bound)
+            .filter_map(|name| self.resolution.decl_of(name))
+            .filter(|&decl| self.resolution.decl(decl).kind == DeclKind::Interface)
+            .collect()
+    }
+
+    /// Reports bounds that are not interfaces.
+    pub(super) fn check_bounds(&mut self, node: Node) {
+        let Some(list) = self.tree.child(node, NodeKind::TypeParamList) else {
+            return;
+        };
+        let bounds: Vec<Node> = self
+            .tree
+            .children(list)
+            .filter_map(|param| self.tree.child(param, NodeKind::TypeBound))
+            .flat_map(|bound| self.tree.children(bound))
+            .collect();
+        for bound in bounds {
+            let kind = self
+                .resolution
+                .decl_of(bound)
+                .map(|decl| self.resolution.decl(decl).kind);
+            if kind.is_some_and(|kind| kind != DeclKind::Interface) {
+                let message = format!(
+                    "`{}` is not an interface",
+                    self.tree.text(self.tokens, bound)
+                );
+                self.emit_error(self.tree.token_of(bound), Code::T0014, message);
+            }
+        }
+    }
+
+    /// Whether `ty` implements `interface`, through an `impl` or, for a
+    /// type parameter, through its bounds.
+    pub(super) fn implements(&self, ty: TypeId, interface: DeclId) -> bool {
+        match self.typing.types.get(ty) {
+            Type::Error | Type::Var(_) => true,
+            Type::Param(param) => self.bounds_of(*param).contains(&interface),
+            _ => self
+                .impls
+                .iter()
+                .any(|&(other, implementor, _)| other == interface && implementor == ty),
+        }
+    }
+
+    /// Checks that `impl Interface for Type` has each method of the
+    /// interface, with `self` and everything else of the same types.
+    pub(super) fn check_impl(&mut self, node: Node) {
+        let (Some(interface), Some(ty)) = (self.impl_interface(node, false), self.impl_type(node))
+        else {
+            return;
+        };
+        // Conflicting implementations are reported once, where they are
+        // declared.
+        if !self.impls.iter().any(|&(_, _, other)| other == node) {
+            return;
+        }
+        let owner = self.lower_type(ty);
+        let this = self.typing.types.intern(Type::Param(interface));
+        let required = self.interface_methods(interface);
+        let name = self.resolution.decl(interface).name.clone();
+        let mut provided = vec![];
+        for func in self.tree.children(node) {
+            let Some(decl_name) = self.tree.child(func, NodeKind::DeclName) else {
+                continue;
+            };
+            let Some(method) = self.resolution.decl_of(decl_name) else {
+                continue;
+            };
+            let method_name = self.tree.text(self.tokens, decl_name);
+            let token = self.tree.token_of(decl_name);
+            let Some(&expected) = required
+                .iter()
+                .find(|&&other| self.resolution.decl(other).name == method_name)
+            else {
+                let message = format!("`{}` is not a method of `{}`", method_name, name);
+                self.emit_error(token, Code::T0008, message);
+                continue;
+            };
+            provided.push(expected);
+            let expected_ty = self.decl_type(expected);
+            let expected_ty = self.substitute(expected_ty, &[(this, owner)]);
+            let ty = self.decl_type(method);
+            if ty != expected_ty {
+                let message = format!(
+                    "`{}` has type `{}`, but `{}` requires `{}`",
+                    method_name,
+                    self.display(ty),
+                    name,
+                    self.display(expected_ty)
+                );
+                self.emit_error(token, Code::T0002, message);
+            }
+        }
+        let missing: Vec<String> = required
+            .iter()
+            .filter(|method| !provided.contains(method))
+            .map(|&method| format!("`{}`", self.resolution.decl(method).name))
+            .collect();
+        if !missing.is_empty() {
+            let message = format!(
+                "`{}` does not implement `{}`, which needs {}",
+                self.display(owner),
+                name,
+                missing.join(", ")
+            );
+            self.emit_error(self.tree.token_of(node), Code::T0015, message);
+        }
+    }
+
+    /// Whether a method takes `self`, so that it is called on a value.
+    pub(super) fn has_receiver(&self, method: DeclId) -> bool {
+        let first = self
+            .resolution
+            .decl(method)
+            .node
+            .and_then(|name| self.parent(name))
+            .and_then(|func| self.tree.child(func, NodeKind::ParamList))
+            .and_then(|list| self.tree.child(list, NodeKind::Param));
+        first.is_some_and(|param| {
+            self.tree.children(param).count() == 1
+                && self
+                    .tree
+                    .child(param, NodeKind::DeclName)
+                    .is_some_and(|name| self.tree.text(self.tokens, name) == "self")
+        })
+    }
+
+    pub(super) fn declare_func(&mut self, node: Node) {
+        let params = match self.tree.child(node, NodeKind::ParamList) {
+            Some(list) => self.declare_params(list),
+            None => vec![],
+        };
+        let ret = self.return_type(node);
+        let ty = self.typing.types.intern(Type::Func { params, ret });
+        if let Some(name) = self.tree.child(node, NodeKind::DeclName) {
+            self.set_decl_type(name, ty);
+        }
+    }
+
+    /// How many parameters of a function have no default value.
+    pub(super) fn required_params(&self, decl: DeclId) -> Option<usize> {
+        let func = self.parent(self.resolution.decl(decl).node?)?;
+        let list = self.tree.child(func, NodeKind::ParamList)?;
+        let required = self
+            .tree
+            .children(list)
+            .filter(|&param| {
+                self.tree.kind_of(param) == NodeKind::Param
+                    && self.tree.child(param, NodeKind::DefaultValue).is_none()
+            })
+            .count();
+        Some(required)
+    }
+
+    pub(super) fn check_defaults(&mut self, func: Node) {
+        let Some(list) = self.tree.child(func, NodeKind::ParamList) else {
+            return;
+        };
+        for param in self.tree.children(list) {
+            let name = self.tree.child(param, NodeKind::DeclName);
+            let value = self
+                .tree
+                .child(param, NodeKind::DefaultValue)
+                .and_then(|default| self.tree.children(default).next());
+            let (Some(name), Some(value)) = (name, value) else {
+                continue;
+            };
+            if let Some(decl) = self.resolution.decl_of(name) {
+                let ty = self.decl_type(decl);
+                self.expect_expr(value, ty);
+            }
+        }
+    }
+
+    /// Explicit discriminants, as in `enum { A = 1 }`, are `Int`s.
+    pub(super) fn check_discriminants(&mut self, node: Node) {
+        let int = self.typing.types.int(IntType::Int);
+        for discriminant in self.tree.postorder_of(node) {
+            if self.tree.kind_of(discriminant) != NodeKind::Discriminant {
+                continue;
+            }
+            if let Some(value) = self.tree.children(discriminant).next() {
+                self.expect_expr(value, int);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::check::test::{check, codes};
+    use crate::diagnostics::Code;
+
+    #[test]
+    fn test_structs() {
+        let source = "type Point = struct { x: Real, y: Real }\n\
+                      impl Point {\n\
+                      func origin() -> Point { Point { x: 0.0, y: 0.0 } }\n\
+                      func norm(self) -> Real { self.x * self.x + self.y * self.y }\n\
+                      func scaled(self, by: Real) -> Point { Point { x: self.x * by, y: self.y * by } }\n\
+                      }\n\
+                      let p = Point { y: 2.0, x: 1.0 }\n\
+                      p.x\n\
+                      p.scaled(2.0).norm()\n\
+                      Point.origin()\n\
+                      Point.norm(p)\n\
+                      p.scaled";
+        let (types, diagnostics) = check(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(
+            types,
+            vec!["Real", "Real", "Point", "Real", "func(Real) -> Point"]
+        );
+
+        let (_, diagnostics) = check("type P = struct { x: Int, y: Int, z: Int }\nP { x: 1 }");
+        assert_eq!(
+            diagnostics[0].message,
+            "missing fields `y`, `z` in `P` literal"
+        );
+        let errors = "type P = struct { x: Int }\n\
+                      impl P { func new() -> P { P { x: 0 } } }\n\
+                      let p = P { x: 1, x: 2, w: 3 }\n\
+                      p.w\n\
+                      p.new()\n\
+                      P.w\n\
+                      Int { x: 1 }";
+        assert_eq!(
+            codes(errors),
+            vec![
+                Code::T0010,
+                Code::T0008,
+                Code::T0008,
+                Code::T0008,
+                Code::T0008,
+                Code::T0011
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interfaces() {
+        let show = "interface Show {\nfunc show(self) -> String\n}\n\
+                    impl Show for Int {\nfunc show(self) -> String { \"int\" }\n}\n\
+                    func print[T: Show](value: T) -> String { value.show() }\n";
+        let (types, diagnostics) =
+            check(&format!("{}print(1)\nprint[Int](2)\nInt(3).show()", show));
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(types, vec!["String", "String", "String"]);
+
+        assert_eq!(codes(&format!("{}print(true)", show)), vec![Code::T0015]);
+        assert_eq!(
+            codes(&format!("{}impl Show for Int {{}}", show)),
+            vec![Code::T0016]
+        );
+        assert_eq!(
+            codes(&format!("{}impl Show for Bool {{}}", show)),
+            vec![Code::T0015]
+        );
+        assert_eq!(
+            codes(&format!("{}func f(s: Show) {{}}", show)),
+            vec![Code::T0014]
+        );
+    }
+
+    #[test]
+    fn test_generic_types() {
+        let opt = "type Opt[T] = enum {\nSome(T)\nNone\n}\n\
+                   type Pair[A, B] = struct { first: A, second: B }\n";
+        let uses = "Opt.Some(1)\n\
+                    Opt.Some(Pair { first: true, second: 2.0 })\n\
+                    func get(o: Opt[Pair[Int, Bool]]) -> Int {\n\
+                    match o { Opt.Some(Pair { first, second: _ }) => first, Opt.None => 0 }\n\
+                    }\n\
+                    Pair { first: 1, second: Opt.Some(\"\") }.second";
+        let (types, diagnostics) = check(&format!("{}{}", opt, uses));
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(
+            types,
+            vec!["Opt[Int]", "Opt[Pair[Bool, Real]]", "Opt[String]"]
+        );
+
+        assert_eq!(
+            codes(&format!(
+                "{}let p: Pair[Int, Bool] = Pair {{ first: 1, second: 2 }}",
+                opt
+            )),
+            vec![Code::T0002]
+        );
+        assert_eq!(
+            codes(&format!(
+                "{}func f(o: Opt[Int]) -> Int {{ match o {{ Opt.None => 0 }} }}",
+                opt
+            )),
+            vec![Code::T0012]
+        );
+        let show = "interface Show {\nfunc show(self) -> String\n}\n\
+                    impl Show for Int {\nfunc show(self) -> String { \"int\" }\n}\n\
+                    type Shown[T: Show] = struct { value: T }\n";
+        assert_eq!(
+            codes(&format!("{}func f(s: Shown[Int]) {{}}", show)),
+            vec![]
+        );
+        assert_eq!(
+            codes(&format!("{}func f(s: Shown[Bool]) {{}}", show)),
+            vec![Code::T0015]
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use super::{literal_value, Context, Member};
+use crate::diagnostics::Code;
+use crate::exhaustiveness::{self, Ctor, Pat};
+use crate::parse_tree::{Node, NodeKind};
+use crate::token::Token;
+use crate::types::{Type, TypeId, Types};
+
+impl<'t> Context<'_, 't> {
+    /// Gives the bindings of a pattern their types, as parts of a value of
+    /// type `expected`, and returns whether the pattern fits it.
+    pub(super) fn check_pattern(&mut self, node: Node, expected: TypeId) -> bool {
+        match self.tree.kind_of(node) {
+            NodeKind::WildcardPattern | NodeKind::RestPattern => true,
+            NodeKind::BindingPattern => {
+                // A name that is a variant of the enum being matched stands
+                // for that variant rather than binding the value.
+                let ty = self.shallow(expected);
+                self.set_decl_type(node, expected);
+                let Some(index) = self.variant(ty, self.tree.text(self.tokens, node)) else {
+                    return true;
+                };
+                if !self.variant_payload(ty, index).is_empty() {
+                    let message = format!(
+                        "`{}` has a payload, match it with `{}(...)`",
+                        self.tree.text(self.tokens, node),
+                        self.tree.text(self.tokens, node)
+                    );
+                    self.emit_error(self.tree.token_of(node), Code::T0004, message);
+                    return false;
+                }
+                self.typing.members[*node] = Some(Member::Variant(index));
+                true
+            }
+            NodeKind::LiteralPattern => match self.tree.children(node).next() {
+                Some(literal) => {
+                    let ty = self.check_expr(literal);
+                    self.expect(self.first_token(literal), ty, expected)
+                }
+                None => false,
+            },
+            NodeKind::RangePattern => {
+                let children: Vec<Node> = self.tree.children(node).collect();
+                let mut fits = true;
+                for child in children {
+                    fits &= self.check_pattern(child, expected);
+                }
+                fits
+            }
+            NodeKind::AltPattern => {
+                let children: Vec<Node> = self.tree.children(node).collect();
+                let mut fits = true;
+                for child in children {
+                    fits &= self.check_pattern(child, expected);
+                }
+                fits
+            }
+            NodeKind::TuplePattern => {
+                let elements: Vec<Node> = self.tree.children(node).skip(1).collect();
+                let mut expected = self.shallow(expected);
+                if let Type::Var(_) = self.typing.types.get(expected) {
+                    let tys = elements.iter().map(|_| self.fresh_var(false)).collect();
+                    let tuple = self.typing.types.intern(Type::Tuple(tys));
+                    self.unify(expected, tuple);
+                    expected = tuple;
+                }
+                let (mut fits, tys) = match self.typing.types.get(expected) {
+                    Type::Tuple(tys) if tys.len() == elements.len() => (true, tys.clone()),
+                    Type::Unit if elements.is_empty() => (true, vec![]),
+                    Type::Error => (false, vec![Types::ERROR; elements.len()]),
+                    _ => {
+                        let message = format!(
+                            "mismatched types: expected `{}`, found a tuple of {} elements",
+                            self.display(expected),
+                            elements.len()
+                        );
+                        self.emit_error(self.tree.token_of(node), Code::T0002, message);
+                        (false, vec![Types::ERROR; elements.len()])
+                    }
+                };
+                for (element, ty) in elements.into_iter().zip(tys) {
+                    fits &= self.check_pattern(element, ty);
+                }
+                fits
+            }
+            NodeKind::VariantPattern => self.check_variant_pattern(node, expected),
+            NodeKind::StructPattern => self.check_struct_pattern(node, expected),
+            _ => {
+                for child in self.tree.children(node) {
+                    self.check_pattern(child, Types::ERROR);
+                }
+                false
+            }
+        }
+    }
+
+    /// `Shape.Circle` or `Circle`, a variant of the enum `expected`. Returns
+    /// the enum and the index of the variant.
+    fn check_variant_path(&mut self, path: Node, expected: TypeId) -> Option<(TypeId, usize)> {
+        let segments: Vec<Node> = self.tree.children(path).collect();
+        let last = *segments.last()?;
+        let name = self.tree.text(self.tokens, last);
+        let ty = match segments.as_slice() {
+            [_] => self.shallow(expected),
+            [owner, _] => {
+                let ty = match self.resolution.decl_of(*owner) {
+                    Some(decl) if self.resolution.decl(decl).kind.is_type() => {
+                        self.fresh_instance(decl)
+                    }
+                    // Reported by name resolution.
+                    Some(_) | None => return None,
+                };
+                if !self.expect(self.tree.token_of(*owner), ty, expected) {
+                    return None;
+                }
+                ty
+            }
+            _ => return None,
+        };
+        let ty = self.shallow(ty);
+        if let Some(index) = self.variant(ty, name) {
+            self.typing.members[*path] = Some(Member::Variant(index));
+            return Some((ty, index));
+        }
+        match self.typing.types.get(ty) {
+            Type::Error => {}
+            Type::Var(_) => {
+                let message = format!(
+                    "cannot infer the enum `{}` is a variant of, write it as `Type.{}`",
+                    name, name
+                );
+                self.emit_error(self.tree.token_of(last), Code::T0007, message);
+            }
+            _ => {
+                let message = format!("no variant `{}` in `{}`", name, self.display(ty));
+                self.emit_error(self.tree.token_of(last), Code::T0008, message);
+            }
+        }
+        None
+    }
+
+    /// `Shape.Rect(w, h)` or `Shape.Empty`, whose payload has to have as
+    /// many elements as the variant.
+    fn check_variant_pattern(&mut self, node: Node, expected: TypeId) -> bool {
+        let mut children = self.tree.children(node);
+        let Some(path) = children.next() else {
+            return false;
+        };
+        let payload = children.next();
+        let elements: Vec<Node> = match payload {
+            Some(payload) => self.tree.children(payload).skip(1).collect(),
+            None => vec![],
+        };
+        let Some((ty, index)) = self.check_variant_path(path, expected) else {
+            for element in elements {
+                self.check_pattern(element, Types::ERROR);
+            }
+            return false;
+        };
+        let tys = self.variant_payload(ty, index);
+        let mut fits = tys.len() == elements.len() && (payload.is_some() || tys.is_empty());
+        if !fits {
+            let message = match tys.len() {
+                0 => format!("`{}` has no payload", self.path_text(path)),
+                _ if payload.is_none() => format!(
+                    "`{}` has a payload, match it with `{}(...)`",
+                    self.path_text(path),
+                    self.path_text(path)
+                ),
+                len => format!(
+                    "`{}` has {} element{} in its payload, found {}",
+                    self.path_text(path),
+                    len,
+                    if len == 1 { "" } else { "s" },
+                    elements.len()
+                ),
+            };
+            self.emit_error(self.last_segment(path), Code::T0004, message);
+        }
+        for (i, element) in elements.into_iter().enumerate() {
+            let ty = tys.get(i).copied().unwrap_or(Types::ERROR);
+            fits &= self.check_pattern(element, ty);
+        }
+        fits
+    }
+
+    /// `Point { x, y: 0, .. }`, which has to name every field unless it
+    /// ends in `..`.
+    fn check_struct_pattern(&mut self, node: Node, expected: TypeId) -> bool {
+        let mut children = self.tree.children(node);
+        let Some(path) = children.next() else {
+            return false;
+        };
+        let fields: Vec<Node> = children
+            .clone()
+            .filter(|&child| self.tree.kind_of(child) == NodeKind::FieldPattern)
+            .collect();
+        let rest = children.any(|child| self.tree.kind_of(child) == NodeKind::RestPattern);
+        let ty = match self.tree.children(path).collect::<Vec<_>>().as_slice() {
+            [name] => match self.resolution.decl_of(*name) {
+                Some(decl) => self.fresh_instance(decl),
+                None => Types::ERROR,
+            },
+            _ => Types::ERROR,
+        };
+        let declared = self.fields(ty);
+        let mut fits = true;
+        let declared = match declared {
+            Some(declared) => {
+                fits = self.expect(self.tree.token_of(path), ty, expected);
+                declared
+            }
+            None => {
+                let message = format!("`{}` is not a struct", self.path_text(path));
+                self.emit_error(self.tree.token_of(path), Code::T0011, message);
+                vec![]
+            }
+        };
+
+        let mut given = vec![];
+        for field in fields {
+            let name = self.tree.text(self.tokens, field);
+            let token = self.tree.token_of(field);
+            let field_ty = match declared.iter().position(|f| f.name == name) {
+                Some(index) if given.contains(&index) => {
+                    let message = format!("field `{}` is given more than once", name);
+                    self.emit_error(token, Code::T0010, message);
+                    fits = false;
+                    Types::ERROR
+                }
+                Some(index) => {
+                    given.push(index);
+                    self.typing.members[*field] = Some(Member::Field(index));
+                    declared[index].ty
+                }
+                None => {
+                    if !declared.is_empty() || ty != Types::ERROR {
+                        let message = format!("no field `{}` on `{}`", name, self.display(ty));
+                        self.emit_error(token, Code::T0008, message);
+                    }
+                    fits = false;
+                    Types::ERROR
+                }
+            };
+            match self.tree.children(field).next() {
+                Some(pattern) => fits &= self.check_pattern(pattern, field_ty),
+                // `{x}` binds the field to `x`.
+                None => self.set_decl_type(field, field_ty),
+            }
+        }
+        let missing: Vec<String> = (0..declared.len())
+            .filter(|index| !given.contains(index))
+            .map(|index| format!("`{}`", declared[index].name))
+            .collect();
+        if !rest && !missing.is_empty() && fits {
+            let message = format!(
+                "missing field{} {} in `{}` pattern, or end it with `..`",
+                if missing.len() == 1 { "" } else { "s" },
+                missing.join(", "),
+                self.display(ty)
+            );
+            self.emit_error(self.tree.token_of(path), Code::T0009, message);
+            fits = false;
+        }
+        fits
+    }
+
+    fn path_text(&self, path: Node) -> String {
+        let segments: Vec<&str> = self
+            .tree
+            .children(path)
+            .map(|segment| self.tree.text(self.tokens, segment))
+            .collect();
+        segments.join(".")
+    }
+
+    fn last_segment(&self, path: Node) -> Token {
+        let last = self.tree.children(path).last().unwrap_or(path);
+        self.tree.token_of(last)
+    }
+
+    /// Reports `match`es that leave out values, and arms that cannot match
+    /// anything the arms before them do not.
+    pub(super) fn check_coverage(&mut self, node: Node, scrutinee: TypeId) {
+        let ty = self.resolve(scrutinee);
+        if ty == Types::ERROR {
+            return;
+        }
+        let arms: Vec<Node> = self
+            .tree
+            .children(node)
+            .filter(|&child| self.tree.kind_of(child) == NodeKind::MatchArmList)
+            .flat_map(|list| self.tree.children(list))
+            .filter(|&arm| self.tree.kind_of(arm) == NodeKind::MatchArm)
+            .collect();
+        let mut lowered = vec![];
+        for &arm in &arms {
+            let Some(pattern) = self.tree.children(arm).next() else {
+                return;
+            };
+            let guarded = self.tree.child(arm, NodeKind::MatchGuard).is_some();
+            lowered.push((self.lower_pattern(pattern, ty), guarded));
+        }
+        let coverage = exhaustiveness::check_arms(&self.typing, self.resolution, ty, &lowered);
+        for index in coverage.unreachable {
+            let pattern = self.tree.children(arms[index]).next().unwrap();
+            let token = self.first_token(pattern);
+            self.emit_warning(token, Code::T0013, "unreachable pattern".to_string());
+        }
+        if let Some(missing) = coverage.missing {
+            let message = format!("non-exhaustive match: `{}` is not covered", missing);
+            self.emit_error(self.tree.token_of(node), Code::T0012, message);
+        }
+    }
+
+    /// The pattern as far as coverage goes, where `ty` is the resolved type
+    /// of the values it matches.
+    fn lower_pattern(&mut self, node: Node, ty: TypeId) -> Pat {
+        let children: Vec<Node> = self.tree.children(node).collect();
+        match self.tree.kind_of(node) {
+            NodeKind::BindingPattern => match self.typing.member_of(node) {
+                Some(Member::Variant(index)) => Pat::Ctor(Ctor::Variant(index), vec![]),
+                _ => Pat::Wild,
+            },
+            NodeKind::LiteralPattern => match self.pattern_value(node) {
+                Some(value) => Pat::Ctor(Ctor::Range(value, value), vec![]),
+                None => Pat::Ctor(Ctor::Literal(self.pattern_text(node)), vec![]),
+            },
+            NodeKind::RangePattern => {
+                let values = children.iter().map(|&child| self.pattern_value(child));
+                match values.collect::<Option<Vec<_>>>().as_deref() {
+                    Some(&[start, end]) => Pat::Ctor(Ctor::Range(start, end - 1), vec![]),
+                    _ => {
+                        let bounds: Vec<String> = children
+                            .iter()
+                            .map(|&child| self.pattern_text(child))
+                            .collect();
+                        Pat::Ctor(Ctor::Literal(bounds.join("..")), vec![])
+                    }
+                }
+            }
+            NodeKind::TuplePattern => {
+                let tys = match self.typing.types.get(ty) {
+                    Type::Tuple(tys) => tys.clone(),
+                    _ => vec![],
+                };
+                let elements = children[1..]
+                    .iter()
+                    .zip(tys)
+                    .map(|(&element, ty)| self.lower_pattern(element, ty))
+                    .collect();
+                Pat::Ctor(Ctor::Single, elements)
+            }
+            NodeKind::VariantPattern => {
+                let Some(Member::Variant(index)) = self.typing.member_of(children[0]) else {
+                    return Pat::Wild;
+                };
+                let tys = self.variant_payload(ty, index);
+                let payload: Vec<Node> = match children.get(1) {
+                    Some(&payload) => self.tree.children(payload).skip(1).collect(),
+                    None => vec![],
+                };
+                let payload = payload
+                    .into_iter()
+                    .zip(tys)
+                    .map(|(element, ty)| {
+                        let ty = self.resolve(ty);
+                        self.lower_pattern(element, ty)
+                    })
+                    .collect();
+                Pat::Ctor(Ctor::Variant(index), payload)
+            }
+            NodeKind::StructPattern => {
+                let Some(declared) = self.fields(ty) else {
+                    return Pat::Wild;
+                };
+                let mut fields = vec![Pat::Wild; declared.len()];
+                for field in children {
+                    let (Some(Member::Field(index)), Some(pattern)) = (
+                        self.typing.member_of(field),
+                        self.tree.children(field).next(),
+                    ) else {
+                        continue;
+                    };
+                    fields[index] = self.lower_pattern(pattern, declared[index].ty);
+                }
+                Pat::Ctor(Ctor::Single, fields)
+            }
+            NodeKind::AltPattern => {
+                let mut alternatives = vec![];
+                for child in children {
+                    match self.lower_pattern(child, ty) {
+                        Pat::Or(nested) => alternatives.extend(nested),
+                        pat => alternatives.push(pat),
+                    }
+                }
+                Pat::Or(alternatives)
+            }
+            _ => Pat::Wild,
+        }
+    }
+
+    /// The value of an integer or `Bool` literal pattern, as a number.
+    fn pattern_value(&self, node: Node) -> Option<i128> {
+        let literal = self.tree.children(node).next()?;
+        match self.tree.kind_of(literal) {
+            NodeKind::IntLiteral => literal_value(self.tree.text(self.tokens, literal)),
+            NodeKind::BoolLiteral => {
+                Some(i128::from(self.tree.text(self.tokens, literal) == "true"))
+            }
+            NodeKind::PrefixOperator => {
+                let operand = self.tree.children(literal).next()?;
+                match self.tree.kind_of(operand) {
+                    NodeKind::IntLiteral => {
+                        literal_value(self.tree.text(self.tokens, operand)).map(|value| -value)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn pattern_text(&self, node: Node) -> String {
+        let Some(literal) = self.tree.children(node).next() else {
+            return String::new();
+        };
+        match self.tree.kind_of(literal) {
+            NodeKind::PrefixOperator => {
+                let operand = self.tree.children(literal).next();
+                format!(
+                    "-{}",
+                    operand.map_or("", |operand| self.tree.text(self.tokens, operand))
+                )
+            }
+            _ => self.tree.text(self.tokens, literal).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::check::test::{check, codes};
+    use crate::diagnostics::Code;
+
+    #[test]
+    fn test_enums() {
+        let source = "type Shape = enum {\nCircle(Real)\nRect(Real, Real)\nEmpty\n}\n\
+                      Shape.Circle(1.0)\n\
+                      Shape.Empty\n\
+                      Shape.Rect\n\
+                      match Shape.Empty { Shape.Rect(w, _) => w, Circle(r) => r, Empty => 0.0 }";
+        let (types, diagnostics) = check(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(
+            types,
+            vec!["Shape", "Shape", "func(Real, Real) -> Shape", "Real"]
+        );
+
+        let shape = "type Shape = enum {\nCircle(Real)\nEmpty\n}\n";
+        assert_eq!(codes(&format!("{}Shape.Square", shape)), vec![Code::T0008]);
+        assert_eq!(
+            codes(&format!("{}Shape.Circle(true)", shape)),
+            vec![Code::T0002]
+        );
+        let patterns = "match Shape.Empty {\n\
+                        Shape.Square => 1\n\
+                        Circle => 2\n\
+                        Empty(x) => 3\n\
+                        Int.Empty => 4\n\
+                        }";
+        assert_eq!(
+            codes(&format!("{}{}", shape, patterns)),
+            vec![Code::T0008, Code::T0004, Code::T0004, Code::T0002]
+        );
+    }
+}
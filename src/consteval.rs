@@ -0,0 +1,608 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::check::{literal_value, Typing};
+use crate::diagnostics::{self, Code, Diagnostic, DiagnosticEmitter};
+use crate::lexer::unescape;
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::resolve::{DeclId, DeclKind, Resolution};
+use crate::token::Token;
+use crate::tokenized_buffer::TokenizedBuffer;
+use crate::types::{IntType, Type};
+
+/// A value known at compile time.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Int(i128),
+    Real(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Real(value) => write!(f, "{:?}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Why an operation has no value.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Fault {
+    /// The result does not fit in the integer type.
+    Overflow,
+    DivisionByZero,
+    /// A shift by a negative amount, or by at least the width of the type.
+    ShiftOutOfRange,
+}
+
+/// Applies a prefix operator. `int` is the type of the operand, when it is
+/// an integer.
+///
+/// These operations define what the operators compute, and are meant to be
+/// shared by everything that evaluates programs, so that results agree.
+pub fn apply_prefix(op: &str, operand: &Value, int: Option<IntType>) -> Result<Value, Fault> {
+    match (op, operand) {
+        ("-", Value::Int(value)) => fit(-value, int),
+        ("-", Value::Real(value)) => Ok(Value::Real(-value)),
+        ("!", Value::Bool(value)) => Ok(Value::Bool(!value)),
+        _ => unreachable!("`{}` on {:?} passed type checking", op, operand),
+    }
+}
+
+/// Applies an infix operator other than `&&` and `||`, which only evaluate
+/// their right operand when needed. `int` is the type of the left operand,
+/// when it is an integer.
+pub fn apply_binary(
+    op: &str,
+    lhs: &Value,
+    rhs: &Value,
+    int: Option<IntType>,
+) -> Result<Value, Fault> {
+    let value = match (lhs, rhs) {
+        (&Value::Int(a), &Value::Int(b)) => match op {
+            "+" => return fit(a + b, int),
+            "-" => return fit(a - b, int),
+            "*" => {
+                return a
+                    .checked_mul(b)
+                    .ok_or(Fault::Overflow)
+                    .and_then(|v| fit(v, int))
+            }
+            // Division truncates towards zero, and the remainder has the sign
+            // of the dividend.
+            "/" | "%" if b == 0 => return Err(Fault::DivisionByZero),
+            "/" => return fit(a / b, int),
+            "%" => return fit(a % b, int),
+            "<<" | ">>" => return shift(op, a, b, int),
+            _ => compare(op, a.cmp(&b)),
+        },
+        (&Value::Real(a), &Value::Real(b)) => match op {
+            "+" => Value::Real(a + b),
+            "-" => Value::Real(a - b),
+            "*" => Value::Real(a * b),
+            "/" => Value::Real(a / b),
+            "==" => Value::Bool(a == b),
+            "!=" => Value::Bool(a != b),
+            "<" => Value::Bool(a < b),
+            "<=" => Value::Bool(a <= b),
+            ">" => Value::Bool(a > b),
+            _ => Value::Bool(a >= b),
+        },
+        (Value::Bool(a), Value::Bool(b)) => compare(op, a.cmp(b)),
+        (Value::String(a), Value::String(b)) => compare(op, a.cmp(b)),
+        _ => unreachable!("`{}` on {:?} and {:?} passed type checking", op, lhs, rhs),
+    };
+    Ok(value)
+}
+
+/// Converts a number by calling a numeric type, as in `u8(n)`. Reals are
+/// truncated towards zero.
+pub fn convert(value: &Value, target: &Type) -> Result<Value, Fault> {
+    match (value, target) {
+        (&Value::Int(value), Type::Int(int)) => fit(value, Some(*int)),
+        (&Value::Real(value), Type::Int(int)) => {
+            let truncated = value.trunc();
+            match truncated.is_finite() && truncated.abs() < 2f64.powi(100) {
+                true => fit(truncated as i128, Some(*int)),
+                false => Err(Fault::Overflow),
+            }
+        }
+        (&Value::Int(value), Type::Real) => Ok(Value::Real(value as f64)),
+        (value, _) => Ok(value.clone()),
+    }
+}
+
+fn fit(value: i128, int: Option<IntType>) -> Result<Value, Fault> {
+    match int {
+        Some(int) if value < int.min() || value > int.max() => Err(Fault::Overflow),
+        _ => Ok(Value::Int(value)),
+    }
+}
+
+/// Bits shifted out to the left are lost, and shifting to the right keeps
+/// the sign.
+fn shift(op: &str, value: i128, amount: i128, int: Option<IntType>) -> Result<Value, Fault> {
+    let bits = int.map_or(64, IntType::bits);
+    if amount < 0 || amount >= i128::from(bits) {
+        return Err(Fault::ShiftOutOfRange);
+    }
+    let amount = amount as u32;
+    if op == ">>" {
+        return Ok(Value::Int(value >> amount));
+    }
+    let mask = (1i128 << bits) - 1;
+    let shifted = (value << amount) & mask;
+    let signed = int.is_none_or(IntType::is_signed);
+    match signed && shifted >> (bits - 1) == 1 {
+        true => Ok(Value::Int(shifted - (1i128 << bits))),
+        false => Ok(Value::Int(shifted)),
+    }
+}
+
+fn compare(op: &str, ordering: std::cmp::Ordering) -> Value {
+    Value::Bool(match op {
+        "==" => ordering.is_eq(),
+        "!=" => ordering.is_ne(),
+        "<" => ordering.is_lt(),
+        "<=" => ordering.is_le(),
+        ">" => ordering.is_gt(),
+        _ => ordering.is_ge(),
+    })
+}
+
+/// The values of everything that has to be known at compile time:
+/// constants, array lengths and enum discriminants.
+#[derive(Debug, Default)]
+pub struct Consts {
+    node_values: Vec<Option<Value>>,
+    decl_values: Vec<Option<Value>>,
+}
+
+impl Consts {
+    pub fn value_of(&self, node: Node) -> Option<&Value> {
+        self.node_values.get(*node).and_then(Option::as_ref)
+    }
+
+    pub fn value_of_decl(&self, decl: DeclId) -> Option<&Value> {
+        self.decl_values.get(decl.0).and_then(Option::as_ref)
+    }
+}
+
+/// What the evaluator needs to know of the types of expressions: those of
+/// a checked tree, or of one being checked.
+pub(crate) trait NodeTypes {
+    /// The type of `node`, as far as it is known.
+    fn type_of_node(&self, node: Node) -> Option<&Type>;
+}
+
+impl NodeTypes for Typing {
+    fn type_of_node(&self, node: Node) -> Option<&Type> {
+        self.type_of(node).map(|ty| self.types.get(ty))
+    }
+}
+
+/// Evaluates constant expressions over literals, constants and numeric
+/// conversions. It runs on type checked trees, and reports integer
+/// overflow, division by zero and shifts out of range at the operator that
+/// causes them.
+pub struct ConstEvaluator {
+    diagnostics: Rc<RefCell<dyn DiagnosticEmitter>>,
+}
+
+impl Default for ConstEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConstEvaluator {
+    pub fn new() -> Self {
+        Self {
+            diagnostics: Rc::new(RefCell::new(diagnostics::DefaultEmitter)),
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
+        self
+    }
+
+    pub fn evaluate(
+        &self,
+        tree: &ParseTree,
+        tokens: &TokenizedBuffer,
+        resolution: &Resolution,
+        typing: &Typing,
+    ) -> Consts {
+        let parents = tree.parents();
+        let mut context = Context {
+            evaluator: self,
+            tree,
+            tokens,
+            resolution,
+            typing,
+            parents: &parents,
+            consts: Consts {
+                node_values: vec![None; tree.len()],
+                decl_values: vec![None; resolution.decls().count()],
+            },
+            evaluating: vec![],
+            failed: vec![],
+        };
+        for node in tree.postorder() {
+            match tree.kind_of(node) {
+                NodeKind::ConstDecl => {
+                    let decl = tree
+                        .child(node, NodeKind::DeclName)
+                        .and_then(|name| resolution.decl_of(name));
+                    if let Some(decl) = decl {
+                        context.eval_decl(decl);
+                    }
+                }
+                NodeKind::ArrayType | NodeKind::ArrayRepeat => {
+                    if let Some(length) = tree.children(node).nth(2) {
+                        context.eval_length(length);
+                    }
+                }
+                NodeKind::Discriminant => {
+                    if let Some(value) = tree.children(node).next() {
+                        context.eval(value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        context.consts
+    }
+
+    /// The value of `node` alone, for the type checker, which needs array
+    /// lengths and indices before every type is known. `parents` are those
+    /// of `tree`.
+    pub(crate) fn evaluate_node(
+        &self,
+        tree: &ParseTree,
+        tokens: &TokenizedBuffer,
+        resolution: &Resolution,
+        typing: &dyn NodeTypes,
+        parents: &[Option<Node>],
+        node: Node,
+    ) -> Option<Value> {
+        let mut context = Context {
+            evaluator: self,
+            tree,
+            tokens,
+            resolution,
+            typing,
+            parents,
+            consts: Consts::default(),
+            evaluating: vec![],
+            failed: vec![],
+        };
+        context.eval(node)
+    }
+}
+
+struct Context<'e, 't> {
+    evaluator: &'e ConstEvaluator,
+    tree: &'t ParseTree,
+    tokens: &'t TokenizedBuffer<'t>,
+    resolution: &'t Resolution,
+    typing: &'t dyn NodeTypes,
+    parents: &'t [Option<Node>],
+    /// What was evaluated, for every node and constant when evaluating a
+    /// whole tree, and nothing when evaluating a single node.
+    consts: Consts,
+    /// Constants whose initializers are being evaluated, to stop at cycles.
+    evaluating: Vec<DeclId>,
+    /// Constants whose initializers have errors, which are not reported
+    /// again where they are used.
+    failed: Vec<DeclId>,
+}
+
+impl<'t> Context<'_, 't> {
+    fn int_type(&self, node: Node) -> Option<IntType> {
+        match self.typing.type_of_node(node)? {
+            Type::Int(int) => Some(*int),
+            _ => None,
+        }
+    }
+
+    fn eval_decl(&mut self, decl: DeclId) -> Option<Value> {
+        if let Some(Some(value)) = self.consts.decl_values.get(decl.0) {
+            return Some(value.clone());
+        }
+        if self.failed.contains(&decl) {
+            return None;
+        }
+        let name = self.resolution.decl(decl).node?;
+        if self.evaluating.contains(&decl) {
+            let message = format!(
+                "the value of `{}` depends on itself",
+                self.resolution.decl(decl).name
+            );
+            self.emit_error(self.tree.token_of(name), Code::C0005, message);
+            self.failed.push(decl);
+            return None;
+        }
+        let initializer = self.parents[*name]
+            .and_then(|binding| self.tree.child(binding, NodeKind::Initializer))
+            .and_then(|initializer| self.tree.children(initializer).next())?;
+        self.evaluating.push(decl);
+        let value = self.eval(initializer);
+        self.evaluating.pop();
+        match (&value, self.consts.decl_values.get_mut(decl.0)) {
+            (Some(value), Some(slot)) => *slot = Some(value.clone()),
+            (Some(_), None) => {}
+            (None, _) => self.failed.push(decl),
+        }
+        value
+    }
+
+    fn eval_length(&mut self, length: Node) {
+        if let Some(Value::Int(value)) = self.eval(length) {
+            if value < 0 {
+                let message = format!("array length must not be negative, found {}", value);
+                self.emit_error(self.tree.token_of(length), Code::C0006, message);
+            }
+        }
+    }
+
+    fn eval(&mut self, node: Node) -> Option<Value> {
+        let value = self.eval_expr(node)?;
+        if let Some(slot) = self.consts.node_values.get_mut(*node) {
+            *slot = Some(value.clone());
+        }
+        Some(value)
+    }
+
+    fn eval_expr(&mut self, node: Node) -> Option<Value> {
+        match self.tree.kind_of(node) {
+            NodeKind::IntLiteral => {
+                literal_value(self.tree.text(self.tokens, node)).map(Value::Int)
+            }
+            NodeKind::RealLiteral => self
+                .tree
+                .text(self.tokens, node)
+                .parse()
+                .ok()
+                .map(Value::Real),
+            NodeKind::BoolLiteral => Some(Value::Bool(self.tree.text(self.tokens, node) == "true")),
+            NodeKind::StringLiteral => {
+                Some(Value::String(unescape(self.tree.text(self.tokens, node))))
+            }
+            NodeKind::ParenExpr => {
+                let inner = self.tree.children(node).nth(1)?;
+                self.eval(inner)
+            }
+            NodeKind::Name => {
+                let decl = self.resolution.decl_of(node)?;
+                if self.resolution.decl(decl).kind == DeclKind::Const {
+                    return self.eval_decl(decl);
+                }
+                let message = format!(
+                    "`{}` is not a constant, only `const`s can be used here",
+                    self.tree.text(self.tokens, node)
+                );
+                self.emit_error(self.tree.token_of(node), Code::C0001, message);
+                None
+            }
+            NodeKind::PrefixOperator => self.eval_prefix(node),
+            NodeKind::InfixOperator => self.eval_infix(node),
+            NodeKind::Call => self.eval_conversion(node),
+            _ => {
+                self.not_constant(node);
+                None
+            }
+        }
+    }
+
+    fn eval_prefix(&mut self, node: Node) -> Option<Value> {
+        let operand = self.tree.children(node).next()?;
+        let op = self.tree.text(self.tokens, node);
+        // `-128` is a literal of its own, which would not fit in `i8` if
+        // `128` was evaluated first.
+        if op == "-" && self.tree.kind_of(operand) == NodeKind::IntLiteral {
+            return literal_value(self.tree.text(self.tokens, operand))
+                .map(|value| Value::Int(-value));
+        }
+        let value = self.eval(operand)?;
+        // Operands the checker rejected, or has not checked yet, have no
+        // value.
+        if !matches!(
+            (op, &value),
+            ("-", Value::Int(_) | Value::Real(_)) | ("!", Value::Bool(_))
+        ) {
+            return None;
+        }
+        let int = self.int_type(node);
+        match apply_prefix(op, &value, int) {
+            Ok(result) => Some(result),
+            Err(fault) => {
+                // `-(-128)`, as `--128` would not read as a negation.
+                let expr = match value {
+                    Value::Int(value) if value < 0 => format!("{}({})", op, value),
+                    _ => format!("{}{}", op, value),
+                };
+                self.report(node, fault, expr, int);
+                None
+            }
+        }
+    }
+
+    fn eval_infix(&mut self, node: Node) -> Option<Value> {
+        let mut children = self.tree.children(node);
+        let (lhs, rhs) = (children.next()?, children.next()?);
+        let op = self.tree.text(self.tokens, node);
+        // Ranges have no `Value`.
+        if op == ".." {
+            self.not_constant(node);
+            return None;
+        }
+        let left = self.eval(lhs)?;
+        match (op, &left) {
+            ("&&", Value::Bool(false)) => return Some(Value::Bool(false)),
+            ("||", Value::Bool(true)) => return Some(Value::Bool(true)),
+            ("&&" | "||", _) => return self.eval(rhs),
+            _ => {}
+        }
+        let right = self.eval(rhs)?;
+        if std::mem::discriminant(&left) != std::mem::discriminant(&right) {
+            return None;
+        }
+        let int = self.int_type(lhs);
+        match apply_binary(op, &left, &right, int) {
+            Ok(result) => Some(result),
+            Err(fault) => {
+                self.report(node, fault, format!("{} {} {}", left, op, right), int);
+                None
+            }
+        }
+    }
+
+    fn eval_conversion(&mut self, node: Node) -> Option<Value> {
+        let mut children = self.tree.children(node);
+        let callee = children.next()?;
+        let is_conversion = self.tree.kind_of(callee) == NodeKind::Name
+            && self
+                .resolution
+                .decl_of(callee)
+                .is_some_and(|decl| self.resolution.decl(decl).kind == DeclKind::BuiltinType);
+        if !is_conversion {
+            self.not_constant(node);
+            return None;
+        }
+        let arg = children.next()?;
+        let value = self.eval(arg)?;
+        let target = self.typing.type_of_node(node)?;
+        match convert(&value, target) {
+            Ok(result) => Some(result),
+            Err(fault) => {
+                let int = self.int_type(node);
+                let expr = format!("{}({})", self.tree.text(self.tokens, callee), value);
+                self.report(callee, fault, expr, int);
+                None
+            }
+        }
+    }
+
+    fn not_constant(&self, node: Node) {
+        let message = "this expression cannot be evaluated at compile time".to_string();
+        self.emit_error(self.tree.token_of(node), Code::C0001, message);
+    }
+
+    /// Reports an operation that has no value, with the values it was
+    /// applied to written out as `expr`.
+    fn report(&self, node: Node, fault: Fault, expr: String, int: Option<IntType>) {
+        let name = int.map_or("Int", IntType::name);
+        let (code, message) = match fault {
+            Fault::Overflow => (Code::C0002, format!("`{}` overflows `{}`", expr, name)),
+            Fault::DivisionByZero => (Code::C0003, format!("`{}` divides by zero", expr)),
+            Fault::ShiftOutOfRange => (
+                Code::C0004,
+                format!(
+                    "`{}` shifts out of range, shifts of `{}` must be by 0 to {}",
+                    expr,
+                    name,
+                    int.map_or(64, IntType::bits) - 1
+                ),
+            ),
+        };
+        self.emit_error(self.tree.token_of(node), code, message);
+    }
+
+    fn emit_error(&self, token: Token, code: Code, message: String) {
+        self.evaluator.diagnostics.borrow_mut().emit(Diagnostic {
+            level: diagnostics::Level::Error,
+            code: Some(code),
+            message,
+            location: self.tokens.location_of(&token).into_owned(),
+            span: self.tokens.span_of(&token),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConstEvaluator, Value};
+    use crate::diagnostics::{Code, Diagnostic};
+    use crate::fixture::Fixture;
+
+    /// The values of the constants in `source`, by name, and what the
+    /// evaluator reported.
+    fn evaluate(source: &str) -> (Vec<(String, Option<Value>)>, Vec<Diagnostic<'static>>) {
+        let fixture = Fixture::parse(source);
+        let (resolution, typing) = fixture.check();
+        fixture.assert_clean();
+        let consts = ConstEvaluator::new()
+            .with_emitter(fixture.emitter.clone())
+            .evaluate(&fixture.tree, &fixture.tokens, &resolution, &typing);
+        let values = resolution
+            .decls()
+            .filter(|(_, decl)| decl.kind == crate::resolve::DeclKind::Const)
+            .map(|(id, decl)| (decl.name.clone(), consts.value_of_decl(id).cloned()))
+            .collect();
+        (values, fixture.take())
+    }
+
+    fn codes(source: &str) -> Vec<Code> {
+        evaluate(source).1.iter().filter_map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_values() {
+        let source = "const SIZE: u8 = BASE * 4 + 1\n\
+                      const BASE: u8 = 1 << 5\n\
+                      const HALF = Real(SIZE) / 2.0\n\
+                      const SIGNED: i8 = -128 >> 2\n\
+                      const WRAPPED: u8 = 255 << 4\n\
+                      const REM = -7 % 3\n\
+                      const BIG = SIZE > 100 || 1 / 0 == 1\n\
+                      const NAME = \"mik\"";
+        let (values, diagnostics) = evaluate(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(
+            values,
+            vec![
+                ("SIZE".to_string(), Some(Value::Int(129))),
+                ("BASE".to_string(), Some(Value::Int(32))),
+                ("HALF".to_string(), Some(Value::Real(64.5))),
+                ("SIGNED".to_string(), Some(Value::Int(-32))),
+                ("WRAPPED".to_string(), Some(Value::Int(240))),
+                ("REM".to_string(), Some(Value::Int(-1))),
+                ("BIG".to_string(), Some(Value::Bool(true))),
+                ("NAME".to_string(), Some(Value::String("mik".to_string()))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let (_, diagnostics) = evaluate("const A: u8 = 200\nconst B = A + 100");
+        assert_eq!(diagnostics[0].message, "`200 + 100` overflows `u8`");
+        assert_eq!(diagnostics[0].location.col, 12);
+        assert_eq!(codes("const A = 1 / (2 - 2)"), vec![Code::C0003]);
+        assert_eq!(codes("const A: i32 = 1 << 32"), vec![Code::C0004]);
+        assert_eq!(codes("const A = 1 >> -1"), vec![Code::C0004]);
+        assert_eq!(codes("const A = u8(256)"), vec![Code::C0002]);
+        assert_eq!(codes("const A: i8 = -(-128)"), vec![Code::C0002]);
+        let (_, diagnostics) = evaluate("const A: i8 = -128\nconst B = -A");
+        assert_eq!(diagnostics[0].message, "`-(-128)` overflows `i8`");
+        assert_eq!(codes("let x = 1\nconst A = x + 1"), vec![Code::C0001]);
+        assert_eq!(codes("const R = 1..3"), vec![Code::C0001]);
+        assert_eq!(
+            codes("func f() -> Int { 1 }\nconst A = f()"),
+            vec![Code::C0001]
+        );
+        // A cycle is reported once, and not again where it is used.
+        assert_eq!(
+            codes("const A = B\nconst B = A + 1\nconst C = A"),
+            vec![Code::C0005]
+        );
+        assert_eq!(codes("var v: [Int; 2 - 3]"), vec![Code::C0006]);
+        assert_eq!(codes("type E = enum { A = 1 << 70 }"), vec![Code::C0004]);
+    }
+}
@@ -0,0 +1,467 @@
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::token::{Token, TokenKind};
+use crate::tokenized_buffer::TokenizedBuffer;
+
+/// A node of the concrete syntax tree.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct CstNode(pub usize);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Element {
+    Node(CstNode),
+    Token(Token),
+}
+
+#[derive(Debug)]
+struct NodeData {
+    // `None` for the root, which stands for the whole file.
+    kind: Option<NodeKind>,
+    parse_node: Option<Node>,
+    parent: Option<CstNode>,
+    children: Vec<Element>,
+}
+
+/// A lossless view of a parse: every token of the file, trivia included,
+/// belongs to exactly one node, so printing the tree reproduces the source.
+///
+/// Tokens that a `ParseTree` node does not reference, such as commas,
+/// whitespace and comments, are placed in the innermost node whose token
+/// range covers them.
+pub struct Cst<'a> {
+    tokens: &'a TokenizedBuffer<'a>,
+    nodes: Vec<NodeData>,
+}
+
+impl<'a> Cst<'a> {
+    pub fn new(tree: &ParseTree, tokens: &'a TokenizedBuffer<'a>) -> Self {
+        let ranges = token_ranges(tree);
+        let mut cst = Self {
+            tokens,
+            nodes: vec![NodeData {
+                kind: None,
+                parse_node: None,
+                parent: None,
+                children: vec![],
+            }],
+        };
+        let root = CstNode(0);
+        let mut next = 0;
+        for node in tree.roots() {
+            cst.build(tree, &ranges, node, root, &mut next);
+        }
+        cst.take_tokens(root, &mut next, tokens.len());
+        cst
+    }
+
+    fn build(
+        &mut self,
+        tree: &ParseTree,
+        ranges: &[Option<(usize, usize)>],
+        node: Node,
+        parent: CstNode,
+        next: &mut usize,
+    ) {
+        let range = ranges[node.0];
+        if let Some((first, _)) = range {
+            self.take_tokens(parent, next, first);
+        }
+        let id = CstNode(self.nodes.len());
+        self.nodes.push(NodeData {
+            kind: Some(tree.kind_of(node)),
+            parse_node: Some(node),
+            parent: Some(parent),
+            children: vec![],
+        });
+        self.nodes[parent.0].children.push(Element::Node(id));
+
+        let Some((_, last)) = range else {
+            return;
+        };
+        for child in tree.children(node) {
+            self.build(tree, ranges, child, id, next);
+        }
+        self.take_tokens(id, next, last + 1);
+    }
+
+    /// Gives `node` the tokens from `next` up to `end`.
+    fn take_tokens(&mut self, node: CstNode, next: &mut usize, end: usize) {
+        while *next < end {
+            self.nodes[node.0]
+                .children
+                .push(Element::Token(Token(*next)));
+            *next += 1;
+        }
+    }
+
+    pub fn root(&self) -> CstNode {
+        CstNode(0)
+    }
+
+    pub fn tokens(&self) -> &'a TokenizedBuffer<'a> {
+        self.tokens
+    }
+
+    /// The kind of `node`, or `None` for the root.
+    pub fn kind_of(&self, node: CstNode) -> Option<NodeKind> {
+        self.nodes[node.0].kind
+    }
+
+    /// The `ParseTree` node `node` was built from.
+    pub fn parse_node_of(&self, node: CstNode) -> Option<Node> {
+        self.nodes[node.0].parse_node
+    }
+
+    pub fn parent(&self, node: CstNode) -> Option<CstNode> {
+        self.nodes[node.0].parent
+    }
+
+    /// The nodes and tokens directly below `node`, in source order.
+    pub fn children(&self, node: CstNode) -> &[Element] {
+        &self.nodes[node.0].children
+    }
+
+    pub fn child_nodes(&self, node: CstNode) -> impl Iterator<Item = CstNode> + '_ {
+        self.children(node).iter().filter_map(|child| match child {
+            Element::Node(node) => Some(*node),
+            Element::Token(_) => None,
+        })
+    }
+
+    /// All tokens below `node`, in source order.
+    pub fn tokens_of(&self, node: CstNode) -> Vec<Token> {
+        let mut tokens = vec![];
+        self.collect_tokens(node, &mut tokens);
+        tokens
+    }
+
+    fn collect_tokens(&self, node: CstNode, tokens: &mut Vec<Token>) {
+        for child in self.children(node) {
+            match *child {
+                Element::Node(node) => self.collect_tokens(node, tokens),
+                Element::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    /// The source text of `node`, exactly as written.
+    pub fn text_of(&self, node: CstNode) -> String {
+        self.tokens_of(node)
+            .iter()
+            .map(|token| self.tokens.str_of(token))
+            .collect()
+    }
+
+    /// The first token below `node` that is not whitespace or a comment.
+    pub fn first_significant_token(&self, node: CstNode) -> Option<Token> {
+        self.tokens_of(node).into_iter().find(|token| {
+            !matches!(
+                self.tokens.kind_of(token),
+                TokenKind::HorizontalWhitespace
+                    | TokenKind::VerticalWhitespace
+                    | TokenKind::Comment
+            )
+        })
+    }
+
+    fn child_of_kind(&self, node: CstNode, kind: NodeKind) -> Option<CstNode> {
+        self.child_nodes(node)
+            .find(|&child| self.kind_of(child) == Some(kind))
+    }
+
+    /// The name declared by the `DeclName` child of `node`, if it parsed.
+    fn decl_name(&self, node: CstNode) -> Option<&'a str> {
+        let name = self.child_of_kind(node, NodeKind::DeclName)?;
+        let token = self.first_significant_token(name)?;
+        Some(self.tokens.str_of(&token))
+    }
+}
+
+impl std::fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text_of(self.root()))
+    }
+}
+
+/// The first and last token each node covers: its own token and those of
+/// its descendants. `Missing` nodes cover nothing, as the token they refer
+/// to belongs to their neighbours.
+fn token_ranges(tree: &ParseTree) -> Vec<Option<(usize, usize)>> {
+    let mut ranges: Vec<Option<(usize, usize)>> = Vec::with_capacity(tree.len());
+    for node in tree.postorder() {
+        if tree.kind_of(node) == NodeKind::Missing {
+            ranges.push(None);
+            continue;
+        }
+        let token = tree.token_of(node).0;
+        let mut range = (token, token);
+        for child in tree.children_rev(node) {
+            if let Some((first, last)) = ranges[child.0] {
+                range = (range.0.min(first), range.1.max(last));
+            }
+        }
+        ranges.push(Some(range));
+    }
+    ranges
+}
+
+/// A typed view of a CST node of one kind.
+pub trait AstNode<'c>: Sized {
+    fn cast(cst: &'c Cst<'c>, node: CstNode) -> Option<Self>;
+    fn syntax(&self) -> CstNode;
+}
+
+macro_rules! ast_node {
+    ($name:ident, $($kind:ident)|+) => {
+        #[derive(Clone, Copy)]
+        pub struct $name<'c> {
+            cst: &'c Cst<'c>,
+            node: CstNode,
+        }
+
+        impl<'c> AstNode<'c> for $name<'c> {
+            fn cast(cst: &'c Cst<'c>, node: CstNode) -> Option<Self> {
+                match cst.kind_of(node)? {
+                    $(NodeKind::$kind)|+ => Some(Self { cst, node }),
+                    _ => None,
+                }
+            }
+
+            fn syntax(&self) -> CstNode {
+                self.node
+            }
+        }
+
+        impl<'c> $name<'c> {
+            pub fn text(&self) -> String {
+                self.cst.text_of(self.node)
+            }
+        }
+    };
+}
+
+ast_node!(FuncDecl, FuncDecl);
+ast_node!(Param, Param);
+ast_node!(TypeParam, TypeParam);
+ast_node!(Block, Block);
+ast_node!(TypeDecl, TypeDecl);
+ast_node!(Binding, LetDecl | VarDecl | ConstDecl);
+ast_node!(Import, Import);
+
+impl<'c> Cst<'c> {
+    fn type_params(&'c self, decl: CstNode) -> impl Iterator<Item = TypeParam<'c>> + 'c {
+        let list = self.child_of_kind(decl, NodeKind::TypeParamList);
+        list.into_iter()
+            .flat_map(|list| self.children_as::<TypeParam>(list))
+    }
+
+    /// The typed nodes of type `T` directly below `node`.
+    pub fn children_as<T: AstNode<'c>>(&'c self, node: CstNode) -> impl Iterator<Item = T> + 'c {
+        self.child_nodes(node)
+            .filter_map(move |child| T::cast(self, child))
+    }
+}
+
+impl<'c> FuncDecl<'c> {
+    pub fn name(&self) -> Option<&'c str> {
+        self.cst.decl_name(self.node)
+    }
+
+    pub fn is_pub(&self) -> bool {
+        self.cst.child_of_kind(self.node, NodeKind::Pub).is_some()
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = Param<'c>> + 'c {
+        let list = self.cst.child_of_kind(self.node, NodeKind::ParamList);
+        list.into_iter()
+            .flat_map(|list| self.cst.children_as::<Param>(list))
+    }
+
+    pub fn type_params(&self) -> impl Iterator<Item = TypeParam<'c>> + 'c {
+        self.cst.type_params(self.node)
+    }
+
+    /// The type after `->`, as written.
+    pub fn return_type(&self) -> Option<String> {
+        let return_type = self.cst.child_of_kind(self.node, NodeKind::ReturnType)?;
+        let ty = self.cst.child_nodes(return_type).next()?;
+        Some(self.cst.text_of(ty).trim().to_string())
+    }
+
+    pub fn body(&self) -> Option<Block<'c>> {
+        self.cst.children_as::<Block>(self.node).next()
+    }
+}
+
+impl<'c> Param<'c> {
+    pub fn name(&self) -> Option<&'c str> {
+        self.cst.decl_name(self.node)
+    }
+
+    /// The parameter type, as written.
+    pub fn ty(&self) -> Option<String> {
+        let ty = self.cst.child_nodes(self.node).find(|&child| {
+            !matches!(
+                self.cst.kind_of(child),
+                Some(NodeKind::DeclName | NodeKind::DefaultValue | NodeKind::Missing)
+            )
+        })?;
+        Some(self.cst.text_of(ty).trim().to_string())
+    }
+}
+
+impl<'c> Block<'c> {
+    /// The statements of the block.
+    pub fn stmts(&self) -> impl Iterator<Item = CstNode> + 'c {
+        let cst = self.cst;
+        cst.child_nodes(self.node)
+            .filter(move |&child| cst.kind_of(child) != Some(NodeKind::BlockStart))
+    }
+}
+
+impl<'c> TypeParam<'c> {
+    pub fn name(&self) -> Option<&'c str> {
+        self.cst.decl_name(self.node)
+    }
+
+    /// The interfaces after `:`, as written.
+    pub fn bounds(&self) -> Vec<String> {
+        let bound = self.cst.child_of_kind(self.node, NodeKind::TypeBound);
+        bound
+            .into_iter()
+            .flat_map(|bound| self.cst.child_nodes(bound))
+            .map(|ty| self.cst.text_of(ty).trim().to_string())
+            .collect()
+    }
+}
+
+impl<'c> TypeDecl<'c> {
+    pub fn name(&self) -> Option<&'c str> {
+        self.cst.decl_name(self.node)
+    }
+
+    pub fn type_params(&self) -> impl Iterator<Item = TypeParam<'c>> + 'c {
+        self.cst.type_params(self.node)
+    }
+}
+
+impl<'c> Binding<'c> {
+    pub fn name(&self) -> Option<&'c str> {
+        self.cst.decl_name(self.node)
+    }
+
+    /// Whether the binding was declared with `var`.
+    pub fn is_mutable(&self) -> bool {
+        self.cst.kind_of(self.node) == Some(NodeKind::VarDecl)
+    }
+
+    /// Whether the binding was declared with `const`.
+    pub fn is_const(&self) -> bool {
+        self.cst.kind_of(self.node) == Some(NodeKind::ConstDecl)
+    }
+}
+
+impl<'c> Import<'c> {
+    /// The imported module, such as `geometry.vec`.
+    pub fn path(&self) -> Option<String> {
+        let path = self.cst.child_of_kind(self.node, NodeKind::ModulePath)?;
+        let segments: Vec<&str> = self
+            .cst
+            .child_nodes(path)
+            .filter_map(|segment| self.cst.first_significant_token(segment))
+            .map(|token| self.cst.tokens.str_of(&token))
+            .collect();
+        Some(segments.join("."))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    fn parse(source: &str) -> (TokenizedBuffer<'_>, ParseTree) {
+        let Fixture { tokens, tree, .. } = Fixture::parse(source);
+        (tokens, tree)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for source in [
+            "",
+            "  // only a comment\n",
+            "func f(a: Int, b: Real = 1.0) -> Real {\n    a + b // sum\n}\n",
+            "let x = (1 +\n  2) * f(3,)\n",
+            "import a.{b as c}; pub type T = struct { x: [Int; 4], }",
+            "func (a: ) {\n  1 +\n}\n}\nlet = $ 2",
+        ] {
+            let (tokens, tree) = parse(source);
+            assert_eq!(Cst::new(&tree, &tokens).to_string(), source);
+        }
+    }
+
+    #[test]
+    fn test_trivia_placement() {
+        let source = "func f() {\n  // comment\n  g()\n}\n";
+        let (tokens, tree) = parse(source);
+        let cst = Cst::new(&tree, &tokens);
+        let func = cst.children_as::<FuncDecl>(cst.root()).next().unwrap();
+        assert_eq!(func.text(), "func f() {\n  // comment\n  g()\n}");
+        let body = func.body().unwrap();
+        let stmt = body.stmts().next().unwrap();
+        assert_eq!(cst.text_of(stmt), "g()");
+        assert_eq!(cst.parent(stmt), Some(body.syntax()));
+    }
+
+    #[test]
+    fn test_typed_nodes() {
+        let source = "import geometry.vec as v\n\
+                      pub func area(w: Real, h: *Real = 1.0) -> Real {\n  var a = w\n  a\n}\n\
+                      type Meters = Real\n\
+                      type Pair[A: Eq + Show, B] = (A, B)\n";
+        let (tokens, tree) = parse(source);
+        let cst = Cst::new(&tree, &tokens);
+        let root = cst.root();
+
+        let import = cst.children_as::<Import>(root).next().unwrap();
+        assert_eq!(import.path().as_deref(), Some("geometry.vec"));
+
+        let func = cst.children_as::<FuncDecl>(root).next().unwrap();
+        assert_eq!(func.name(), Some("area"));
+        assert!(func.is_pub());
+        let params: Vec<(Option<&str>, Option<String>)> = func
+            .params()
+            .map(|param| (param.name(), param.ty()))
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                (Some("w"), Some("Real".to_string())),
+                (Some("h"), Some("*Real".to_string()))
+            ]
+        );
+        assert_eq!(func.return_type().as_deref(), Some("Real"));
+        let body = func.body().unwrap();
+        let binding = cst.children_as::<Binding>(body.syntax()).next().unwrap();
+        assert_eq!(binding.name(), Some("a"));
+        assert!(binding.is_mutable());
+        assert_eq!(body.stmts().count(), 2);
+
+        let ty = cst.children_as::<TypeDecl>(root).next().unwrap();
+        assert_eq!(ty.name(), Some("Meters"));
+        assert!(FuncDecl::cast(&cst, ty.syntax()).is_none());
+        assert_eq!(func.type_params().count(), 0);
+
+        let pair = cst.children_as::<TypeDecl>(root).nth(1).unwrap();
+        let params: Vec<(Option<&str>, Vec<String>)> = pair
+            .type_params()
+            .map(|param| (param.name(), param.bounds()))
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                (Some("A"), vec!["Eq".to_string(), "Show".to_string()]),
+                (Some("B"), vec![])
+            ]
+        );
+    }
+}
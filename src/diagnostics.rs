@@ -1,17 +1,180 @@
-use crate::token::SourceLocation;
+use crate::token::{SourceLocation, Span};
 
+#[derive(Debug, Clone)]
 pub struct Diagnostic<'a> {
     pub level: Level,
-    pub message: &'a str,
+    pub code: Option<Code>,
+    pub message: String,
     pub location: SourceLocation<'a>,
+    /// The bytes of the source it points at, which start at `location`.
+    pub span: Span,
 }
 
+impl Diagnostic<'_> {
+    pub fn into_owned(self) -> Diagnostic<'static> {
+        Diagnostic {
+            level: self.level,
+            code: self.code,
+            message: self.message,
+            location: self.location.into_owned(),
+            span: self.span,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Level {
     Error,
     Warning,
     Info,
 }
 
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Error => write!(f, "ERROR"),
+            Level::Warning => write!(f, "WARN"),
+            Level::Info => write!(f, "INFO"),
+        }
+    }
+}
+
+/// Stable identifiers for diagnostics, so tests and users can refer to a
+/// class of error without matching on its message. The letter names the
+/// phase that reports it (`L`exer, `P`arser, `A`ttributes,
+/// `R`esolution, `T`ype checking, `F`low analysis, `C`onstant evaluation,
+/// `M`odule loading, lint `W`arnings, ...).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Code {
+    /// A character that does not start any token.
+    L0001,
+    /// A string literal without its closing quote on the same line.
+    L0002,
+    /// A backslash escape that strings do not support.
+    L0003,
+    /// A specific token, such as a closing delimiter, is missing.
+    P0001,
+    /// Tokens left over after a complete construct.
+    P0002,
+    /// An expression is required but the next token cannot start one.
+    P0003,
+    /// Non-associative operators, such as comparisons, used in a chain.
+    P0004,
+    /// A type is required but the next token cannot start one.
+    P0005,
+    /// A parameter without a default value follows one with a default.
+    P0006,
+    /// `pub` or attributes not followed by a declaration they can apply to.
+    P0007,
+    /// A pattern is required but the next token cannot start one.
+    P0008,
+    /// An attribute that no one registered.
+    A0001,
+    /// An attribute on a kind of declaration it does not apply to.
+    A0002,
+    /// Attribute arguments that do not match what the attribute takes.
+    A0003,
+    /// The same attribute given twice on one declaration.
+    A0004,
+    /// A name that no enclosing scope declares.
+    R0001,
+    /// A name declared twice where names must be unique.
+    R0002,
+    /// A name used as a type that does not name one.
+    R0003,
+    /// A nested function referring to a local of the function around it.
+    R0004,
+    /// A `self` parameter outside of a method, or not first.
+    R0005,
+    /// An operator applied to operands of types it does not take.
+    T0001,
+    /// A value whose type differs from the one its context requires.
+    T0002,
+    /// A call of something that is not a function.
+    T0003,
+    /// A call with too few or too many arguments.
+    T0004,
+    /// A conversion from something other than a single number.
+    T0005,
+    /// An integer literal outside the range of its type.
+    T0006,
+    /// A declaration whose type nothing determines.
+    T0007,
+    /// A field, method or variant that the type does not have.
+    T0008,
+    /// A struct literal or pattern that leaves out fields.
+    T0009,
+    /// A struct literal that gives a field twice.
+    T0010,
+    /// A struct literal or pattern of a type that is not a struct.
+    T0011,
+    /// A `match` that leaves out values.
+    T0012,
+    /// A `match` arm that cannot match anything the arms before it do not.
+    T0013,
+    /// An interface where a type is expected, or the other way around.
+    T0014,
+    /// A type that does not implement an interface it has to, or an `impl`
+    /// that leaves out methods of its interface.
+    T0015,
+    /// Two implementations of the same interface for the same type.
+    T0016,
+    /// An index known at compile time that is out of the bounds of an
+    /// array.
+    T0017,
+    /// A type the checker does not support yet.
+    T0018,
+    /// A type alias that refers to itself.
+    T0019,
+    /// A struct or enum that contains itself without indirection, which
+    /// has no size.
+    T0020,
+    /// An assignment to something other than a `var`, or to a `let` that
+    /// may already have a value.
+    F0001,
+    /// A local read on a path where it is not assigned.
+    F0002,
+    /// A function returning a value that can reach its end without one.
+    F0003,
+    /// A constant expression that refers to something other than a constant,
+    /// or does something that cannot be done at compile time.
+    C0001,
+    /// Integer overflow in a constant expression.
+    C0002,
+    /// Division or remainder by zero in a constant expression.
+    C0003,
+    /// A shift by a negative amount or by the width of the type or more.
+    C0004,
+    /// A constant whose value depends on itself.
+    C0005,
+    /// An array length below zero.
+    C0006,
+    /// An imported module has no file below the module root.
+    M0001,
+    /// Modules that import each other, directly or through others.
+    M0002,
+    /// A module header that does not match the path of its file.
+    M0003,
+    /// A local or parameter that is never read (`unused_variables`).
+    W0001,
+    /// An import that nothing refers to (`unused_imports`).
+    W0002,
+    /// A private function that is never called (`unused_functions`).
+    W0003,
+    /// A statement that cannot run (`unreachable_code`).
+    W0004,
+    /// Parentheses that change nothing (`redundant_parens`).
+    W0005,
+    /// A local that hides another of the same name (`shadowed_bindings`).
+    W0006,
+}
+
+impl std::fmt::Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 pub trait DiagnosticEmitter {
     fn emit(&mut self, diagnostic: Diagnostic);
 }
@@ -21,20 +184,46 @@ pub struct DefaultEmitter;
 impl DiagnosticEmitter for DefaultEmitter {
     fn emit(&mut self, diagnostic: Diagnostic) {
         let location = diagnostic.location;
-        let file = location.file.unwrap_or("unknown file");
-        match diagnostic.level {
-            Level::Error => eprintln!(
-                "[ERROR]: {}:{}:{}  --> {}",
-                file, location.row, location.col, diagnostic.message
-            ),
-            Level::Warning => eprintln!(
-                "[WARN]: {}:{}:{}  --> {}",
-                file, location.row, location.col, diagnostic.message
-            ),
-            Level::Info => eprintln!(
-                "[INFO]: {}:{}:{}  --> {}",
-                file, location.row, location.col, diagnostic.message
-            ),
-        }
+        let file = location.file.as_deref().unwrap_or("unknown file");
+        let level = match diagnostic.code {
+            Some(code) => format!("{}({})", diagnostic.level, code),
+            None => diagnostic.level.to_string(),
+        };
+        // Locations are 0-based; editors and `mik parse` count from 1.
+        eprintln!(
+            "[{}]: {}:{}:{}  --> {}",
+            level,
+            file,
+            location.row + 1,
+            location.col + 1,
+            diagnostic.message
+        );
+    }
+}
+
+/// Keeps every diagnostic instead of printing it, for callers that want to
+/// inspect what was reported (the test runner, tests).
+#[derive(Default)]
+pub struct CollectingEmitter {
+    diagnostics: Vec<Diagnostic<'static>>,
+}
+
+impl CollectingEmitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic<'static>] {
+        &self.diagnostics
+    }
+
+    pub fn take(&mut self) -> Vec<Diagnostic<'static>> {
+        std::mem::take(&mut self.diagnostics)
+    }
+}
+
+impl DiagnosticEmitter for CollectingEmitter {
+    fn emit(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic.into_owned());
     }
 }
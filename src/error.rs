@@ -1,36 +1,135 @@
 use derive_more::{Display, From};
 
+use crate::diagnostics::{Code, Diagnostic, Level};
+use crate::token::{SourceLocation, Span};
+
 pub type Result<T> = ::core::result::Result<T, Error>;
 
 #[derive(Debug, Display, From)]
 pub enum Error {
-    #[display(fmt = "Unexpected token: expected {}, found {}", expected, found)]
-    UnexpectedToken { expected: String, found: String },
-
     #[from]
-    Custom(String),
+    Compile(CompileError),
+    #[display(fmt = "I/O error: {}", _0)]
     #[from]
     Io(std::io::Error),
+    #[display(fmt = "internal error: {}", _0)]
+    #[from]
+    Internal(String),
 }
 
 impl Error {
-    pub fn custom(error: impl Into<String>) -> Self {
-        Error::Custom(error.into())
+    pub fn internal(error: impl Into<String>) -> Self {
+        Error::Internal(error.into())
+    }
+
+    pub fn into_diagnostic(self) -> Option<Diagnostic<'static>> {
+        match self {
+            Error::Compile(error) => Some(error.into()),
+            Error::Io(_) | Error::Internal(_) => None,
+        }
     }
 }
 
 impl From<&str> for Error {
     fn from(s: &str) -> Self {
-        Error::Custom(s.to_string())
+        Error::Internal(s.to_string())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::Compile(_) | Error::Internal(_) => None,
+        }
+    }
+}
+
+/// An error in the program being compiled, as opposed to a failure of the
+/// compiler itself. It displays its location 1-based, like the emitters.
+#[derive(Debug, Display, Clone, PartialEq)]
+#[display(
+    fmt = "{}:{}:{}: {}",
+    "location.file.as_deref().unwrap_or(\"unknown file\")",
+    "location.row + 1",
+    "location.col + 1",
+    message
+)]
+pub struct CompileError {
+    pub code: Option<Code>,
+    pub message: String,
+    pub span: Span,
+    pub location: SourceLocation<'static>,
+}
+
+impl CompileError {
+    pub fn new(
+        code: Code,
+        message: impl Into<String>,
+        span: Span,
+        location: SourceLocation<'_>,
+    ) -> Self {
+        Self {
+            code: Some(code),
+            message: message.into(),
+            span,
+            location: location.into_owned(),
+        }
+    }
+}
+
+impl From<CompileError> for Diagnostic<'static> {
+    fn from(error: CompileError) -> Self {
+        Diagnostic {
+            level: Level::Error,
+            code: error.code,
+            message: error.message,
+            location: error.location,
+            span: error.span,
+        }
+    }
+}
+
+impl From<Diagnostic<'static>> for CompileError {
+    fn from(diagnostic: Diagnostic<'static>) -> Self {
+        Self {
+            code: diagnostic.code,
+            message: diagnostic.message,
+            span: diagnostic.span,
+            location: diagnostic.location,
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use super::*;
+
     #[test]
     fn test_error() {
-        use super::*;
-        let error = Error::custom("Custom error");
-        assert_eq!(error.to_string(), "Custom error");
+        let error = Error::internal("Custom error");
+        assert_eq!(error.to_string(), "internal error: Custom error");
+        assert!(error.into_diagnostic().is_none());
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let location = SourceLocation::new(Some("h1.mik"), 0, 4);
+        let error: Error = CompileError::new(
+            Code::L0001,
+            "invalid character `$`",
+            Span::new(4, 5),
+            location,
+        )
+        .into();
+        assert_eq!(error.to_string(), "h1.mik:1:5: invalid character `$`");
+        assert!(matches!(&error, Error::Compile(e) if e.code == Some(Code::L0001)));
+
+        let diagnostic = error.into_diagnostic().unwrap();
+        assert_eq!(diagnostic.level, Level::Error);
+        assert_eq!(diagnostic.location.col, 4);
+        assert_eq!(diagnostic.span, Span::new(4, 5));
+        let error = CompileError::from(diagnostic);
+        assert_eq!(error.span, Span::new(4, 5));
     }
 }
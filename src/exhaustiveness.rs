@@ -0,0 +1,409 @@
+use crate::check::Typing;
+use crate::resolve::Resolution;
+use crate::types::{Type, TypeId};
+
+/// A pattern reduced to what matters for coverage: bindings are wildcards
+/// and literals are constructors without fields.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+    Or(Vec<Pat>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Ctor {
+    /// The integers from the first to the second, both included. `false`
+    /// and `true` are 0 and 1.
+    Range(i128, i128),
+    /// The variant at this index.
+    Variant(usize),
+    /// The only constructor of tuples, structs and `()`.
+    Single,
+    /// A literal of a type with too many values to list, like `Real`.
+    Literal(String),
+}
+
+/// What checking the arms of a `match` found.
+#[derive(Debug, PartialEq)]
+pub struct Coverage {
+    /// A value no arm matches, written as a pattern.
+    pub missing: Option<String>,
+    /// The arms that only match values earlier arms already do.
+    pub unreachable: Vec<usize>,
+}
+
+/// Checks the arms of a `match` on a value of type `ty`, each with whether
+/// it has a guard. Guarded arms can fail, so they cover nothing.
+///
+/// An arm is reachable if its pattern is useful with respect to the arms
+/// before it, and the arms are exhaustive if `_` is not useful after all of
+/// them, as in "Warnings for pattern matching" by Luc Maranget.
+pub fn check_arms(
+    typing: &Typing,
+    resolution: &Resolution,
+    ty: TypeId,
+    arms: &[(Pat, bool)],
+) -> Coverage {
+    let checker = Checker { typing, resolution };
+    let mut rows = vec![];
+    let mut unreachable = vec![];
+    for (index, (pat, guarded)) in arms.iter().enumerate() {
+        if checker
+            .useful(&rows, std::slice::from_ref(pat), &[ty])
+            .is_none()
+        {
+            unreachable.push(index);
+        }
+        if !guarded {
+            rows.push(vec![pat.clone()]);
+        }
+    }
+    let missing = checker
+        .useful(&rows, &[Pat::Wild], &[ty])
+        .map(|witness| checker.display(&witness[0], ty));
+    Coverage {
+        missing,
+        unreachable,
+    }
+}
+
+struct Checker<'a> {
+    typing: &'a Typing,
+    resolution: &'a Resolution,
+}
+
+impl Checker<'_> {
+    /// Whether a value matching `row` can match none of `rows`, and if so
+    /// such a value. All rows are as long as `row`, whose columns have the
+    /// types `tys`.
+    fn useful(&self, rows: &[Vec<Pat>], row: &[Pat], tys: &[TypeId]) -> Option<Vec<Pat>> {
+        let Some(head) = row.first() else {
+            return rows.is_empty().then(Vec::new);
+        };
+        let rows = expand_or(rows);
+        let heads: Vec<&Ctor> = rows
+            .iter()
+            .filter_map(|row| match &row[0] {
+                Pat::Ctor(ctor, _) => Some(ctor),
+                _ => None,
+            })
+            .collect();
+
+        match head {
+            Pat::Or(alternatives) => alternatives.iter().find_map(|alternative| {
+                let row = [std::slice::from_ref(alternative), &row[1..]].concat();
+                self.useful(&rows, &row, tys)
+            }),
+            Pat::Ctor(ctor, fields) => {
+                let ctors = match ctor {
+                    Ctor::Range(lo, hi) => split(&heads, *lo, *hi),
+                    _ => vec![ctor.clone()],
+                };
+                ctors.into_iter().find_map(|ctor| {
+                    let row = [fields.as_slice(), &row[1..]].concat();
+                    self.useful_ctor(&rows, &row, tys, ctor)
+                })
+            }
+            Pat::Wild => {
+                let all = self.ctors(tys[0], &heads);
+                let missing: Option<Vec<Ctor>> = all.as_ref().map(|all| {
+                    all.iter()
+                        .filter(|ctor| !heads.iter().any(|head| covers(head, ctor)))
+                        .cloned()
+                        .collect()
+                });
+                if let (Some(all), Some(true)) = (all, missing.as_ref().map(Vec::is_empty)) {
+                    // Every constructor appears, so the wildcard is useful
+                    // only if it is for one of them.
+                    return all.into_iter().find_map(|ctor| {
+                        let arity = self.fields(tys[0], &ctor).len();
+                        let row = [vec![Pat::Wild; arity].as_slice(), &row[1..]].concat();
+                        self.useful_ctor(&rows, &row, tys, ctor)
+                    });
+                }
+
+                let rest: Vec<Vec<Pat>> = rows
+                    .iter()
+                    .filter(|row| row[0] == Pat::Wild)
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                let mut witness = self.useful(&rest, &row[1..], &tys[1..])?;
+                // An example of a constructor no row starts with, unless no
+                // row starts with any and `_` says as much.
+                let head = match missing.and_then(|missing| missing.into_iter().next()) {
+                    Some(ctor) if !heads.is_empty() => {
+                        let arity = self.fields(tys[0], &ctor).len();
+                        Pat::Ctor(ctor, vec![Pat::Wild; arity])
+                    }
+                    _ => Pat::Wild,
+                };
+                witness.insert(0, head);
+                Some(witness)
+            }
+        }
+    }
+
+    /// `useful` for the rows starting with `ctor`, where `row` already has
+    /// the fields of `ctor` in place of its first column.
+    fn useful_ctor(
+        &self,
+        rows: &[Vec<Pat>],
+        row: &[Pat],
+        tys: &[TypeId],
+        ctor: Ctor,
+    ) -> Option<Vec<Pat>> {
+        let fields = self.fields(tys[0], &ctor);
+        let arity = fields.len();
+        let specialized: Vec<Vec<Pat>> = rows
+            .iter()
+            .filter_map(|other| match &other[0] {
+                Pat::Wild => Some([vec![Pat::Wild; arity].as_slice(), &other[1..]].concat()),
+                Pat::Ctor(head, args) if covers(head, &ctor) => {
+                    Some([args.as_slice(), &other[1..]].concat())
+                }
+                _ => None,
+            })
+            .collect();
+        let tys = [fields.as_slice(), &tys[1..]].concat();
+        let mut witness = self.useful(&specialized, row, &tys)?;
+        let args = witness.drain(..arity).collect();
+        witness.insert(0, Pat::Ctor(ctor, args));
+        Some(witness)
+    }
+
+    /// Every constructor of `ty`, with integer ranges split at the bounds
+    /// of `heads`, or `None` if there are too many to list.
+    fn ctors(&self, ty: TypeId, heads: &[&Ctor]) -> Option<Vec<Ctor>> {
+        let ctors = match self.typing.types.get(ty) {
+            Type::Bool => split(heads, 0, 1),
+            Type::Int(int) => split(heads, int.min(), int.max()),
+            Type::Named(..) => match self.typing.variants_of(ty) {
+                Some(variants) => (0..variants.len()).map(Ctor::Variant).collect(),
+                None => vec![Ctor::Single],
+            },
+            Type::Unit | Type::Tuple(_) => vec![Ctor::Single],
+            _ => return None,
+        };
+        Some(ctors)
+    }
+
+    /// The types of the fields of `ctor`.
+    fn fields(&self, ty: TypeId, ctor: &Ctor) -> Vec<TypeId> {
+        match (self.typing.types.get(ty), ctor) {
+            (Type::Tuple(tys), Ctor::Single) => tys.clone(),
+            (Type::Named(..), Ctor::Single) => self
+                .typing
+                .fields_of(ty)
+                .map(|fields| fields.iter().map(|field| field.ty).collect())
+                .unwrap_or_default(),
+            (Type::Named(..), Ctor::Variant(index)) => self
+                .typing
+                .variants_of(ty)
+                .and_then(|variants| variants.get(*index))
+                .map(|variant| variant.payload.clone())
+                .unwrap_or_default(),
+            _ => vec![],
+        }
+    }
+
+    /// The pattern as it is written in source.
+    fn display(&self, pat: &Pat, ty: TypeId) -> String {
+        let (ctor, args) = match pat {
+            Pat::Wild => return "_".to_string(),
+            Pat::Or(alternatives) => return self.display(&alternatives[0], ty),
+            Pat::Ctor(ctor, args) => (ctor, args),
+        };
+        let fields = self.fields(ty, ctor);
+        let list = |separator: &str| {
+            let args: Vec<String> = args
+                .iter()
+                .zip(&fields)
+                .map(|(arg, &ty)| self.display(arg, ty))
+                .collect();
+            args.join(separator)
+        };
+        match (self.typing.types.get(ty), ctor) {
+            (Type::Bool, Ctor::Range(0, 0)) => "false".to_string(),
+            (Type::Bool, Ctor::Range(1, 1)) => "true".to_string(),
+            (_, Ctor::Range(lo, hi)) if lo == hi => lo.to_string(),
+            (_, Ctor::Range(lo, hi)) => format!("{}..{}", lo, hi + 1),
+            // Patterns name generic types without their type arguments.
+            (Type::Named(decl, _), Ctor::Variant(index)) => {
+                let name = &self.typing.variants_of(ty).unwrap()[*index].name;
+                let owner = &self.resolution.decl(*decl).name;
+                match args.is_empty() {
+                    true => format!("{}.{}", owner, name),
+                    false => format!("{}.{}({})", owner, name, list(", ")),
+                }
+            }
+            (Type::Named(decl, _), Ctor::Single) => {
+                let fields: Vec<String> = self
+                    .typing
+                    .fields_of(ty)
+                    .unwrap_or_default()
+                    .iter()
+                    .zip(args)
+                    .zip(&fields)
+                    .map(|((field, arg), &ty)| format!("{}: {}", field.name, self.display(arg, ty)))
+                    .collect();
+                let owner = &self.resolution.decl(*decl).name;
+                format!("{} {{ {} }}", owner, fields.join(", "))
+            }
+            (Type::Tuple(tys), Ctor::Single) if tys.len() == 1 => format!("({},)", list("")),
+            (_, Ctor::Single) => format!("({})", list(", ")),
+            (_, Ctor::Literal(literal)) => literal.clone(),
+            (_, Ctor::Variant(_)) => "_".to_string(),
+        }
+    }
+}
+
+/// The rows with the alternatives of a leading `a | b` as rows of their
+/// own.
+fn expand_or(rows: &[Vec<Pat>]) -> Vec<Vec<Pat>> {
+    let mut expanded = vec![];
+    for row in rows {
+        match &row[0] {
+            Pat::Or(alternatives) => {
+                let alternatives: Vec<Vec<Pat>> = alternatives
+                    .iter()
+                    .map(|alternative| [std::slice::from_ref(alternative), &row[1..]].concat())
+                    .collect();
+                expanded.extend(expand_or(&alternatives));
+            }
+            _ => expanded.push(row.clone()),
+        }
+    }
+    expanded
+}
+
+/// Splits `lo..=hi` where the ranges in `heads` start or end, so that each
+/// part is either inside or outside of each of them.
+fn split(heads: &[&Ctor], lo: i128, hi: i128) -> Vec<Ctor> {
+    if lo > hi {
+        return vec![];
+    }
+    let mut bounds = vec![lo, hi + 1];
+    for head in heads {
+        if let Ctor::Range(start, end) = head {
+            if start <= end {
+                bounds.extend([*start, end + 1].into_iter().filter(|&b| lo < b && b <= hi));
+            }
+        }
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+    bounds
+        .windows(2)
+        .map(|pair| Ctor::Range(pair[0], pair[1] - 1))
+        .collect()
+}
+
+/// Whether every value `ctor` stands for is one `head` matches, given that
+/// ranges were split so that they do not partly overlap.
+fn covers(head: &Ctor, ctor: &Ctor) -> bool {
+    match (head, ctor) {
+        (Ctor::Range(start, end), Ctor::Range(lo, hi)) => start <= lo && hi <= end,
+        _ => head == ctor,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fixture::Fixture;
+
+    /// What checking `func f(v: ty) { body }` reports, after `decls`.
+    fn report(decls: &str, ty: &str, body: &str) -> Vec<String> {
+        let source = format!("{}\nfunc f(v: {}) -> Int {{\n{}\n}}", decls, ty, body);
+        let fixture = Fixture::parse(&source);
+        fixture.check();
+        fixture.take().into_iter().map(|d| d.message).collect()
+    }
+
+    const SHAPE: &str = "type Shape = enum {\nCircle(Real)\nRect(Real, Real)\nEmpty\n}";
+
+    #[test]
+    fn test_missing() {
+        assert_eq!(
+            report(
+                SHAPE,
+                "Shape",
+                "match v { Shape.Circle(_) => 1, Empty => 2 }"
+            ),
+            vec!["non-exhaustive match: `Shape.Rect(_, _)` is not covered"]
+        );
+        assert_eq!(
+            report(
+                "",
+                "(Bool, Bool)",
+                "match v { (true, _) => 1, (_, true) => 2 }"
+            ),
+            vec!["non-exhaustive match: `(false, false)` is not covered"]
+        );
+        assert_eq!(
+            report("", "u8", "match v { 0..100 => 1, 101..256 => 2 }"),
+            vec!["non-exhaustive match: `100` is not covered"]
+        );
+        assert_eq!(
+            report("", "i8", "match v { 0 => 1 }"),
+            vec!["non-exhaustive match: `-128..0` is not covered"]
+        );
+        assert_eq!(
+            report("", "Real", "match v { 1.0 => 1 }"),
+            vec!["non-exhaustive match: `_` is not covered"]
+        );
+        assert_eq!(
+            report(
+                "type P = struct { x: Bool, y: Int }",
+                "P",
+                "match v { P { x: true, .. } => 1 }"
+            ),
+            vec!["non-exhaustive match: `P { x: false, y: _ }` is not covered"]
+        );
+        // A guard can fail, so its arm covers nothing.
+        assert_eq!(
+            report("", "Bool", "match v { true if v => 1, false => 2 }"),
+            vec!["non-exhaustive match: `true` is not covered"]
+        );
+    }
+
+    #[test]
+    fn test_nested() {
+        let decls = format!("{}\ntype Option = enum {{\nSome(Shape)\nNone\n}}", SHAPE);
+        let body = "match v {\n\
+                    Option.Some(Shape.Circle(_)) | None => 1\n\
+                    Some(Shape.Rect(_, _)) => 2\n\
+                    }";
+        assert_eq!(
+            report(&decls, "Option", body),
+            vec!["non-exhaustive match: `Option.Some(Shape.Empty)` is not covered"]
+        );
+        let body = "match v {\n\
+                    Some(Shape.Empty) => 1\n\
+                    Some(_) => 2\n\
+                    None => 3\n\
+                    }";
+        assert!(report(&decls, "Option", body).is_empty());
+    }
+
+    #[test]
+    fn test_unreachable() {
+        assert_eq!(
+            report("", "u8", "match v { 0..10 => 1, 5 => 2, _ => 3 }"),
+            vec!["unreachable pattern"]
+        );
+        assert_eq!(
+            report(SHAPE, "Shape", "match v { _ => 1, Empty => 2 }"),
+            vec!["unreachable pattern"]
+        );
+        assert_eq!(
+            report(
+                "",
+                "(Bool, u8)",
+                "match v { (true, _) => 1, (false, n) => 2, (_, 3) => 3 }"
+            ),
+            vec!["unreachable pattern"]
+        );
+        assert!(report("", "Bool", "match v { true => 1, false => 2 }").is_empty());
+    }
+}
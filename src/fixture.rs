@@ -0,0 +1,70 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::check::{TypeChecker, Typing};
+use crate::diagnostics::{Code, CollectingEmitter, Diagnostic};
+use crate::lexer::Lexer;
+use crate::parse_tree::ParseTree;
+use crate::parser::Parser;
+use crate::resolve::{Resolution, Resolver};
+use crate::tokenized_buffer::TokenizedBuffer;
+
+/// A source lexed and parsed for unit tests, which run the later phases
+/// they need on it. Every phase reports to `emitter`.
+pub(crate) struct Fixture<'s> {
+    pub tokens: TokenizedBuffer<'s>,
+    pub tree: ParseTree,
+    pub emitter: Rc<RefCell<CollectingEmitter>>,
+}
+
+impl<'s> Fixture<'s> {
+    pub fn parse(source: &'s str) -> Self {
+        let emitter = Rc::new(RefCell::new(CollectingEmitter::new()));
+        let tokens = Lexer::new(source).with_emitter(emitter.clone()).tokenize();
+        let tree = Parser::new(&tokens).with_emitter(emitter.clone()).parse();
+        Self {
+            tokens,
+            tree,
+            emitter,
+        }
+    }
+
+    pub fn resolve(&self) -> Resolution {
+        Resolver::new()
+            .with_emitter(self.emitter.clone())
+            .resolve(&self.tree, &self.tokens)
+    }
+
+    /// Resolves and type checks the source.
+    pub fn check(&self) -> (Resolution, Typing) {
+        let resolution = self.resolve();
+        let typing = self.check_resolved(&resolution);
+        (resolution, typing)
+    }
+
+    pub fn check_resolved(&self, resolution: &Resolution) -> Typing {
+        TypeChecker::new().with_emitter(self.emitter.clone()).check(
+            &self.tree,
+            &self.tokens,
+            resolution,
+        )
+    }
+
+    /// Fails the test if anything was reported so far.
+    pub fn assert_clean(&self) {
+        let emitter = self.emitter.borrow();
+        assert!(
+            emitter.diagnostics().is_empty(),
+            "{:?}",
+            emitter.diagnostics()
+        );
+    }
+
+    pub fn take(&self) -> Vec<Diagnostic<'static>> {
+        self.emitter.borrow_mut().take()
+    }
+
+    /// The codes of what was reported since the last `take`.
+    pub fn codes(&self) -> Vec<Code> {
+        self.take().iter().filter_map(|d| d.code).collect()
+    }
+}
@@ -0,0 +1,879 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::check::Typing;
+use crate::diagnostics::{self, Code, Diagnostic, DiagnosticEmitter};
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::resolve::{DeclId, DeclKind, Resolution};
+use crate::token::Token;
+use crate::tokenized_buffer::TokenizedBuffer;
+use crate::types::{Type, TypeId, Types};
+
+/// What a basic block does to locals, in order.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Event {
+    /// A local read at the node.
+    Read(DeclId, Node),
+    /// A local coming into scope without a value, as in `let x: Int`.
+    Declare(DeclId),
+    /// A local declared with a value.
+    Init(DeclId),
+    /// An assignment to `target`, the whole of a local or one of its
+    /// fields or elements. `decl` is `None` when the target is not a place
+    /// at all.
+    Assign {
+        target: Node,
+        decl: Option<DeclId>,
+        field: bool,
+    },
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct BasicBlock {
+    pub events: Vec<Event>,
+    pub successors: Vec<usize>,
+    /// The statements that start in this block.
+    pub stmts: Vec<Node>,
+}
+
+/// The control-flow graph of a function body. Block 0 is the entry and
+/// block 1 the exit, which every `return` and the end of the body lead to.
+/// Of expressions, only `match` branches here: elsewhere they cannot
+/// assign, so the order their parts run in does not matter.
+#[derive(Debug, Default)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+    /// The block that runs off the end of the body without giving a value.
+    pub end: usize,
+}
+
+pub const ENTRY: usize = 0;
+pub const EXIT: usize = 1;
+
+impl Cfg {
+    /// The graph of `body`, the block or expression of a function or
+    /// closure, or a sequence of module-level statements.
+    pub fn build(
+        tree: &ParseTree,
+        tokens: &TokenizedBuffer,
+        resolution: &Resolution,
+        body: &[Node],
+    ) -> Self {
+        let mut builder = Builder {
+            tree,
+            tokens,
+            resolution,
+            cfg: Cfg {
+                blocks: vec![BasicBlock::default(), BasicBlock::default()],
+                end: ENTRY,
+            },
+            current: ENTRY,
+            loops: vec![],
+        };
+        match body {
+            [block] if tree.kind_of(*block) == NodeKind::Block => builder.block(*block, true),
+            [expr] if !is_stmt(tree.kind_of(*expr)) => {
+                builder.expr(*expr);
+                builder.give_value();
+            }
+            stmts => {
+                for &stmt in stmts {
+                    builder.stmt(stmt);
+                }
+            }
+        }
+        builder.cfg.end = builder.current;
+        builder.edge(builder.current, EXIT);
+        builder.cfg
+    }
+
+    /// Which blocks can run, following edges from the entry.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut stack = vec![ENTRY];
+        while let Some(block) = stack.pop() {
+            if std::mem::replace(&mut reachable[block], true) {
+                continue;
+            }
+            stack.extend(&self.blocks[block].successors);
+        }
+        reachable
+    }
+}
+
+fn is_stmt(kind: NodeKind) -> bool {
+    matches!(
+        kind,
+        NodeKind::ExprStmt
+            | NodeKind::Assign
+            | NodeKind::LetDecl
+            | NodeKind::VarDecl
+            | NodeKind::ConstDecl
+            | NodeKind::If
+            | NodeKind::While
+            | NodeKind::For
+            | NodeKind::Break
+            | NodeKind::Continue
+            | NodeKind::Return
+            | NodeKind::Block
+    )
+}
+
+struct Builder<'t> {
+    tree: &'t ParseTree,
+    tokens: &'t TokenizedBuffer<'t>,
+    resolution: &'t Resolution,
+    cfg: Cfg,
+    current: usize,
+    /// The blocks `continue` and `break` go to, for each enclosing loop.
+    loops: Vec<(usize, usize)>,
+}
+
+impl Builder<'_> {
+    fn new_block(&mut self) -> usize {
+        self.cfg.blocks.push(BasicBlock::default());
+        self.cfg.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        self.cfg.blocks[from].successors.push(to);
+    }
+
+    fn push(&mut self, event: Event) {
+        self.cfg.blocks[self.current].events.push(event);
+    }
+
+    /// Leaves the current block for `to`. What follows is only reachable
+    /// from edges added later.
+    fn jump(&mut self, to: usize) {
+        self.edge(self.current, to);
+        self.current = self.new_block();
+    }
+
+    /// The value of the body has been computed, so the function returns.
+    fn give_value(&mut self) {
+        self.jump(EXIT);
+    }
+
+    /// Lowers a block. In `tail` position, its last expression is the value
+    /// of the function.
+    fn block(&mut self, block: Node, tail: bool) {
+        let stmts: Vec<Node> = self
+            .tree
+            .children(block)
+            .filter(|&child| self.tree.kind_of(child) != NodeKind::BlockStart)
+            .collect();
+        let Some((&last, rest)) = stmts.split_last() else {
+            return;
+        };
+        for &stmt in rest {
+            self.stmt(stmt);
+        }
+        match self.tree.kind_of(last) {
+            NodeKind::ExprStmt if tail => {
+                self.stmt(last);
+                self.give_value();
+            }
+            NodeKind::If if tail => {
+                self.cfg.blocks[self.current].stmts.push(last);
+                self.if_(last, true);
+            }
+            _ => self.stmt(last),
+        }
+    }
+
+    fn stmt(&mut self, node: Node) {
+        self.cfg.blocks[self.current].stmts.push(node);
+        match self.tree.kind_of(node) {
+            NodeKind::ExprStmt => {
+                if let Some(expr) = self.tree.children(node).next() {
+                    self.expr(expr);
+                }
+            }
+            NodeKind::Assign => self.assign(node),
+            NodeKind::LetDecl | NodeKind::VarDecl => {
+                let value = self
+                    .tree
+                    .child(node, NodeKind::Initializer)
+                    .and_then(|initializer| self.tree.children(initializer).next());
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                let decl = self
+                    .tree
+                    .child(node, NodeKind::DeclName)
+                    .and_then(|name| self.resolution.decl_of(name));
+                match (decl, value) {
+                    (Some(decl), Some(_)) => self.push(Event::Init(decl)),
+                    (Some(decl), None) => self.push(Event::Declare(decl)),
+                    (None, _) => {}
+                }
+            }
+            NodeKind::If => self.if_(node, false),
+            NodeKind::While => {
+                let mut children = self.tree.children(node);
+                let head = self.new_block();
+                self.edge(self.current, head);
+                self.current = head;
+                let forever = match children.next() {
+                    Some(condition) => {
+                        self.expr(condition);
+                        self.tree.kind_of(condition) == NodeKind::BoolLiteral
+                            && self.tree.text(self.tokens, condition) == "true"
+                    }
+                    None => false,
+                };
+                self.body(head, forever, children);
+            }
+            NodeKind::For => {
+                let name = self.tree.child(node, NodeKind::DeclName);
+                let mut children = self
+                    .tree
+                    .children(node)
+                    .filter(|&child| Some(child) != name);
+                if let Some(iterable) = children.next() {
+                    self.expr(iterable);
+                }
+                let head = self.new_block();
+                self.edge(self.current, head);
+                self.current = head;
+                self.body(head, false, children);
+            }
+            NodeKind::Break => {
+                if let Some(&(_, after)) = self.loops.last() {
+                    self.jump(after);
+                }
+            }
+            NodeKind::Continue => {
+                if let Some(&(head, _)) = self.loops.last() {
+                    self.jump(head);
+                }
+            }
+            NodeKind::Return => {
+                if let Some(value) = self.tree.children(node).next() {
+                    self.expr(value);
+                }
+                self.jump(EXIT);
+            }
+            NodeKind::Block => self.block(node, false),
+            _ => {}
+        }
+    }
+
+    /// The body of a loop whose condition was evaluated in `head`. Unless
+    /// the loop runs `forever`, it can leave after each check.
+    fn body(&mut self, head: usize, forever: bool, children: impl Iterator<Item = Node>) {
+        let after = self.new_block();
+        if !forever {
+            self.edge(head, after);
+        }
+        let body = self.new_block();
+        self.edge(head, body);
+        self.current = body;
+        self.loops.push((head, after));
+        for child in children {
+            self.stmt(child);
+        }
+        self.loops.pop();
+        self.edge(self.current, head);
+        self.current = after;
+    }
+
+    /// Lowers an `if`. Only with an `else` can it give the function's value.
+    fn if_(&mut self, node: Node, tail: bool) {
+        let mut children = self.tree.children(node);
+        if let Some(condition) = children.next() {
+            self.expr(condition);
+        }
+        let has_else = self
+            .tree
+            .children(node)
+            .any(|child| self.tree.kind_of(child) == NodeKind::Else);
+        let tail = tail && has_else;
+        let branch = self.current;
+        let after = self.new_block();
+        for child in children {
+            let start = self.new_block();
+            self.edge(branch, start);
+            self.current = start;
+            match self.tree.kind_of(child) {
+                NodeKind::Else => match self.tree.children(child).next() {
+                    Some(nested) if self.tree.kind_of(nested) == NodeKind::If => {
+                        self.if_(nested, tail)
+                    }
+                    Some(block) => self.block(block, tail),
+                    None => {}
+                },
+                _ => self.block(child, tail),
+            }
+            self.edge(self.current, after);
+        }
+        if !has_else {
+            self.edge(branch, after);
+        }
+        self.current = after;
+    }
+
+    /// Lowers a `match`, each arm a branch of its own. The type checker
+    /// makes sure the arms cover every value.
+    fn match_(&mut self, node: Node) {
+        let mut children = self.tree.children(node);
+        if let Some(scrutinee) = children.next() {
+            self.expr(scrutinee);
+        }
+        let arms: Vec<Node> = children
+            .filter(|&child| self.tree.kind_of(child) == NodeKind::MatchArmList)
+            .flat_map(|list| self.tree.children(list))
+            .filter(|&arm| self.tree.kind_of(arm) == NodeKind::MatchArm)
+            .collect();
+        let branch = self.current;
+        let after = self.new_block();
+        for arm in arms {
+            let start = self.new_block();
+            self.edge(branch, start);
+            self.current = start;
+            // The pattern binds names, which are always assigned.
+            for part in self.tree.children(arm).skip(1) {
+                match self.tree.kind_of(part) {
+                    NodeKind::Block => self.block(part, false),
+                    _ => self.expr(part),
+                }
+            }
+            self.edge(self.current, after);
+        }
+        self.current = after;
+    }
+
+    fn assign(&mut self, node: Node) {
+        let mut children = self.tree.children(node);
+        let (Some(target), Some(value)) = (children.next(), children.next()) else {
+            return;
+        };
+        self.expr(value);
+        let compound = self.tree.text(self.tokens, node) != "=";
+        let mut place = target;
+        let mut field = false;
+        loop {
+            match self.tree.kind_of(place) {
+                NodeKind::ParenExpr => match self.tree.children(place).nth(1) {
+                    Some(inner) => place = inner,
+                    None => break,
+                },
+                NodeKind::FieldAccess => match self.tree.children(place).next() {
+                    Some(object) => {
+                        place = object;
+                        field = true;
+                    }
+                    None => break,
+                },
+                NodeKind::IndexExpr => {
+                    let mut children = self.tree.children(place);
+                    let (Some(object), Some(index)) = (children.next(), children.next()) else {
+                        break;
+                    };
+                    self.expr(index);
+                    place = object;
+                    field = true;
+                }
+                _ => break,
+            }
+        }
+        let decl = match self.tree.kind_of(place) {
+            NodeKind::Name => self.resolution.decl_of(place),
+            _ => None,
+        };
+        match decl {
+            // Assigning to a field or element, or with an operator, reads
+            // the rest.
+            Some(decl) if field || compound => self.push(Event::Read(decl, place)),
+            Some(_) => {}
+            None => self.expr(place),
+        }
+        self.push(Event::Assign {
+            target,
+            decl,
+            field,
+        });
+    }
+
+    /// Whether the closure or function expression `node` reads `decl`,
+    /// rather than only assigning all of it.
+    fn reads(&self, node: Node, decl: DeclId) -> bool {
+        let mut assigned = vec![];
+        for inner in self.tree.postorder_of(node) {
+            if self.tree.kind_of(inner) != NodeKind::Assign
+                || self.tree.text(self.tokens, inner) != "="
+            {
+                continue;
+            }
+            let mut target = self.tree.children(inner).next();
+            while let Some(paren) = target.filter(|&t| self.tree.kind_of(t) == NodeKind::ParenExpr)
+            {
+                target = self.tree.children(paren).nth(1);
+            }
+            assigned.extend(target);
+        }
+        self.tree.postorder_of(node).any(|inner| {
+            self.tree.kind_of(inner) == NodeKind::Name
+                && self.resolution.decl_of(inner) == Some(decl)
+                && !assigned.contains(&inner)
+        })
+    }
+
+    /// Records the locals an expression reads. A closure reads the captures
+    /// its body reads where it is created; its body is a graph of its own.
+    fn expr(&mut self, node: Node) {
+        match self.tree.kind_of(node) {
+            NodeKind::MatchExpr => self.match_(node),
+            NodeKind::Name => {
+                if let Some(decl) = self.resolution.decl_of(node) {
+                    self.push(Event::Read(decl, node));
+                }
+            }
+            NodeKind::Return => self.stmt(node),
+            NodeKind::ClosureExpr | NodeKind::FuncExpr => {
+                for &decl in self.resolution.captures_of(node) {
+                    if self.reads(node, decl) {
+                        self.push(Event::Read(decl, node));
+                    }
+                }
+            }
+            _ => {
+                for child in self.tree.children(node) {
+                    self.expr(child);
+                }
+            }
+        }
+    }
+}
+
+/// Checks each function body on its control-flow graph: that locals are
+/// assigned before they are read, that only `var`s are assigned again, and
+/// that functions returning a value do so on every path.
+pub struct FlowChecker {
+    diagnostics: Rc<RefCell<dyn DiagnosticEmitter>>,
+}
+
+impl Default for FlowChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlowChecker {
+    pub fn new() -> Self {
+        Self {
+            diagnostics: Rc::new(RefCell::new(diagnostics::DefaultEmitter)),
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
+        self
+    }
+
+    pub fn check(
+        &self,
+        tree: &ParseTree,
+        tokens: &TokenizedBuffer,
+        resolution: &Resolution,
+        typing: &Typing,
+    ) {
+        let context = Context {
+            checker: self,
+            tree,
+            tokens,
+            resolution,
+            typing,
+        };
+        for (node, body) in bodies(tree) {
+            let Some(node) = node else {
+                context.check_body(&body, None);
+                continue;
+            };
+            let (ty, what) = match tree.kind_of(node) {
+                NodeKind::FuncDecl => {
+                    let name = tree.child(node, NodeKind::DeclName);
+                    let ty = name
+                        .and_then(|name| resolution.decl_of(name))
+                        .and_then(|decl| typing.type_of_decl(decl));
+                    let what = match name {
+                        Some(name) => format!("`{}`", tree.text(tokens, name)),
+                        None => "this function".to_string(),
+                    };
+                    (ty, what)
+                }
+                NodeKind::ClosureExpr => (typing.type_of(node), "this closure".to_string()),
+                _ => (typing.type_of(node), "this function".to_string()),
+            };
+            context.check_body(&body, ty.map(|ty| (ty, what)));
+        }
+    }
+}
+
+/// The bodies that get a graph of their own: the statements of the module,
+/// then those of each function, closure and function expression, with the
+/// node they belong to.
+pub fn bodies(tree: &ParseTree) -> Vec<(Option<Node>, Vec<Node>)> {
+    let stmts: Vec<Node> = tree
+        .roots()
+        .filter(|&root| is_stmt(tree.kind_of(root)))
+        .collect();
+    let mut bodies = vec![(None, stmts)];
+    for node in tree.postorder() {
+        let body = match tree.kind_of(node) {
+            NodeKind::FuncDecl => tree.child(node, NodeKind::Block),
+            NodeKind::ClosureExpr | NodeKind::FuncExpr => tree.children(node).last(),
+            _ => None,
+        };
+        if let Some(body) = body {
+            bodies.push((Some(node), vec![body]));
+        }
+    }
+    bodies
+}
+
+/// Which tracked locals are assigned on every path to a point, and which
+/// on some path.
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    assigned: Vec<bool>,
+    maybe: Vec<bool>,
+}
+
+impl State {
+    fn merge(&mut self, other: &State) -> bool {
+        let before = self.clone();
+        for (a, b) in self.assigned.iter_mut().zip(&other.assigned) {
+            *a &= b;
+        }
+        for (a, b) in self.maybe.iter_mut().zip(&other.maybe) {
+            *a |= b;
+        }
+        *self != before
+    }
+}
+
+struct Context<'c, 't> {
+    checker: &'c FlowChecker,
+    tree: &'t ParseTree,
+    tokens: &'t TokenizedBuffer<'t>,
+    resolution: &'t Resolution,
+    typing: &'t Typing,
+}
+
+impl Context<'_, '_> {
+    /// Checks a body, and with the function's type and how to name it,
+    /// that it returns a value on every path.
+    fn check_body(&self, body: &[Node], func: Option<(TypeId, String)>) {
+        let cfg = Cfg::build(self.tree, self.tokens, self.resolution, body);
+        let reachable = cfg.reachable();
+        // The locals declared in this body. Others, from module scope or
+        // around a closure, are assigned by the time it runs.
+        let mut tracked: Vec<DeclId> = vec![];
+        for block in &cfg.blocks {
+            for event in &block.events {
+                if let Event::Declare(decl) | Event::Init(decl) = *event {
+                    tracked.push(decl);
+                }
+            }
+        }
+
+        let unassigned = State {
+            assigned: vec![false; tracked.len()],
+            maybe: vec![false; tracked.len()],
+        };
+        let mut states: Vec<Option<State>> = vec![None; cfg.blocks.len()];
+        states[ENTRY] = Some(unassigned);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, block) in cfg.blocks.iter().enumerate() {
+                let Some(mut state) = states[index].clone() else {
+                    continue;
+                };
+                for event in &block.events {
+                    self.apply(&tracked, &mut state, event, false);
+                }
+                for &successor in &block.successors {
+                    match &mut states[successor] {
+                        Some(next) => changed |= next.merge(&state),
+                        next @ None => {
+                            *next = Some(state.clone());
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (index, block) in cfg.blocks.iter().enumerate() {
+            if let Some(mut state) = states[index].clone() {
+                for event in &block.events {
+                    self.apply(&tracked, &mut state, event, true);
+                }
+            }
+        }
+
+        let Some((ty, what)) = func else {
+            return;
+        };
+        let ret = match self.typing.types.get(ty) {
+            Type::Func { ret, .. } => *ret,
+            _ => return,
+        };
+        if matches!(ret, Types::UNIT | Types::ERROR) || !reachable[cfg.end] {
+            return;
+        }
+        let Some(&body) = body.first() else {
+            return;
+        };
+        let message = format!(
+            "not every path through {} returns a value of type `{}`",
+            what,
+            self.typing.types.display(ret, self.resolution)
+        );
+        self.emit_error(self.tree.token_of(body), Code::F0003, message);
+    }
+
+    /// Updates `state` for an event, reporting what it does wrong when
+    /// `report` is set.
+    fn apply(&self, tracked: &[DeclId], state: &mut State, event: &Event, report: bool) {
+        let index = |decl| tracked.iter().position(|&other| other == decl);
+        match *event {
+            Event::Read(decl, node) => {
+                let Some(index) = index(decl) else {
+                    return;
+                };
+                if report && !state.assigned[index] {
+                    let name = &self.resolution.decl(decl).name;
+                    let message = match state.maybe[index] {
+                        true => format!("`{}` is used where it may not be assigned", name),
+                        false => format!("`{}` is used before it is assigned", name),
+                    };
+                    self.emit_error(self.tree.token_of(node), Code::F0002, message);
+                }
+            }
+            Event::Declare(decl) => {
+                if let Some(index) = index(decl) {
+                    state.assigned[index] = false;
+                    state.maybe[index] = false;
+                }
+            }
+            Event::Init(decl) => {
+                if let Some(index) = index(decl) {
+                    state.assigned[index] = true;
+                    state.maybe[index] = true;
+                }
+            }
+            Event::Assign {
+                target,
+                decl,
+                field,
+            } => {
+                let token = match self.tree.kind_of(target) {
+                    NodeKind::IndexExpr => self.first_token(target),
+                    _ => self.tree.token_of(target),
+                };
+                let Some(decl) = decl else {
+                    if report {
+                        let message = "cannot assign to this expression".to_string();
+                        self.emit_error(token, Code::F0001, message);
+                    }
+                    return;
+                };
+                let index = index(decl);
+                let kind = self.resolution.decl(decl).kind;
+                // A `let` without a value can be assigned once.
+                let deferred = kind == DeclKind::Local { mutable: false }
+                    && !field
+                    && index.is_some_and(|index| !state.maybe[index]);
+                if report && !deferred {
+                    if let Some(reason) = self.immutable(decl) {
+                        let element = self.tree.kind_of(target) == NodeKind::IndexExpr;
+                        let to = match (field, index.is_some()) {
+                            (true, _) if element => "to an element of",
+                            (true, _) => "to a field of",
+                            // Only `let`s declared here are tracked.
+                            (false, true) => "twice to",
+                            (false, _) => "to",
+                        };
+                        let message = format!(
+                            "cannot assign {} `{}`, which is {}",
+                            to,
+                            self.resolution.decl(decl).name,
+                            reason
+                        );
+                        self.emit_error(token, Code::F0001, message);
+                    }
+                }
+                if let Some(index) = index.filter(|_| !field) {
+                    state.assigned[index] = true;
+                    state.maybe[index] = true;
+                }
+            }
+        }
+    }
+
+    /// Why a declaration cannot be assigned, if it cannot.
+    fn immutable(&self, decl: DeclId) -> Option<&'static str> {
+        let reason = match self.resolution.decl(decl).kind {
+            DeclKind::Local { mutable: true } => return None,
+            DeclKind::Local { mutable: false } => {
+                let parent = self
+                    .resolution
+                    .decl(decl)
+                    .node
+                    .and_then(|name| self.tree.parent(name));
+                match parent.map(|parent| self.tree.kind_of(parent)) {
+                    Some(NodeKind::For) => "a `for` loop variable",
+                    _ => "declared with `let`",
+                }
+            }
+            DeclKind::Param => "a parameter",
+            DeclKind::PatternBinding => "bound by a pattern",
+            _ => "not a variable",
+        };
+        Some(reason)
+    }
+
+    /// The token an expression starts at.
+    fn first_token(&self, node: Node) -> Token {
+        let start = *node + 1 - self.tree.subtree_size_of(node);
+        (start..=*node)
+            .map(|index| self.tree.token_of(Node(index)))
+            .min_by_key(|token| token.0)
+            .unwrap_or(self.tree.token_of(node))
+    }
+
+    fn emit_error(&self, token: Token, code: Code, message: String) {
+        self.checker.diagnostics.borrow_mut().emit(Diagnostic {
+            level: diagnostics::Level::Error,
+            code: Some(code),
+            message,
+            location: self.tokens.location_of(&token).into_owned(),
+            span: self.tokens.span_of(&token),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Cfg, FlowChecker};
+    use crate::diagnostics::Code;
+    use crate::fixture::Fixture;
+    use crate::parse_tree::NodeKind;
+
+    fn codes(source: &str) -> Vec<Code> {
+        let fixture = Fixture::parse(source);
+        let (resolution, typing) = fixture.check();
+        fixture.assert_clean();
+        FlowChecker::new()
+            .with_emitter(fixture.emitter.clone())
+            .check(&fixture.tree, &fixture.tokens, &resolution, &typing);
+        fixture.codes()
+    }
+
+    /// Whether the end of the first function's body can be reached.
+    fn falls_through(source: &str) -> bool {
+        let fixture = Fixture::parse(source);
+        let resolution = fixture.resolve();
+        let tree = &fixture.tree;
+        let body = tree
+            .roots()
+            .find(|&root| tree.kind_of(root) == NodeKind::FuncDecl)
+            .and_then(|func| tree.children(func).last())
+            .unwrap();
+        let cfg = Cfg::build(tree, &fixture.tokens, &resolution, &[body]);
+        cfg.reachable()[cfg.end]
+    }
+
+    #[test]
+    fn test_cfg() {
+        assert!(!falls_through("func f() -> Int { 1 }"));
+        assert!(!falls_through("func f() -> Int { return 1 }"));
+        assert!(falls_through(
+            "func f(c: Bool) -> Int { if c { return 1 } }"
+        ));
+        assert!(!falls_through(
+            "func f(c: Bool) -> Int { if c { return 1 } else { 2 } }"
+        ));
+        assert!(!falls_through("func f() { while true {} }"));
+        assert!(falls_through("func f() { while true { break } }"));
+        assert!(falls_through("func f(c: Bool) { while c {} }"));
+    }
+
+    #[test]
+    fn test_assignment() {
+        assert_eq!(codes("func f() { var x = 1\nx = 2 }"), vec![]);
+        assert_eq!(codes("func f() { let x = 1\nx = 2 }"), vec![Code::F0001]);
+        assert_eq!(codes("func f() { let x: Int\nx = 2 }"), vec![]);
+        assert_eq!(codes("func f(x: Int) { x = 2 }"), vec![Code::F0001]);
+        assert_eq!(
+            codes("func f() { let a = [1]\na[0] = 2 }"),
+            vec![Code::F0001]
+        );
+        assert_eq!(codes("func f() { var a = [1]\na[0] = 2 }"), vec![]);
+        assert_eq!(
+            codes("func f() { var a: [Int; 1]\na[0] = 2 }"),
+            vec![Code::F0002]
+        );
+        assert_eq!(
+            codes("func f() { let x = 1\nlet g = func() { x = 2 } }"),
+            vec![Code::F0001]
+        );
+        assert_eq!(
+            codes("func f() { var x = 1\nlet g = func() { x = 2 } }"),
+            vec![]
+        );
+        // A closure that only assigns a local does not read it.
+        assert_eq!(
+            codes("func f() { var b: Int\nlet g = || { b = 3 } }"),
+            vec![]
+        );
+        // It still cannot assign a `let`, as it may run more than once.
+        assert_eq!(
+            codes("func f() { let b: Int\nlet g = || { b = 3 } }"),
+            vec![Code::F0001]
+        );
+        assert_eq!(
+            codes("func f() { var b: Int\nlet g = || { (b) = b + 1 } }"),
+            vec![Code::F0002]
+        );
+        assert_eq!(
+            codes("func f() { var b: Int\nlet g = || { b += 1 } }"),
+            vec![Code::F0002]
+        );
+    }
+
+    #[test]
+    fn test_definite_assignment() {
+        assert_eq!(
+            codes("func f() -> Int { let x: Int\nx }"),
+            vec![Code::F0002]
+        );
+        assert_eq!(
+            codes("func f(c: Bool) -> Int { let x: Int\nif c { x = 1 } else { x = 2 }\nx }"),
+            vec![]
+        );
+        assert_eq!(
+            codes("func f(c: Bool) -> Int { let x: Int\nwhile c { x = 1 }\nx }"),
+            vec![Code::F0002, Code::F0001]
+        );
+        assert_eq!(
+            codes("func f(c: Bool) -> Int { if c { return 1 } }"),
+            vec![Code::F0003]
+        );
+        // An arm that returns assigns nothing, but nothing after it runs.
+        assert_eq!(
+            codes(
+                "func f(c: Bool) -> Int { let x: Int\n\
+                 match c { true => { x = 1 }, false => return 2 }\nx }"
+            ),
+            vec![]
+        );
+        assert_eq!(
+            codes("func f(c: Bool) -> Int { match c { true => return 1, false => return 2 } }"),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use crate::check::Typing;
+use crate::types::{Type, TypeId};
+
+/// How values of a type are laid out in memory, for a 64-bit target.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Layout {
+    pub size: u64,
+    pub align: u64,
+    /// Where each field or element starts, for structs and tuples. Enums
+    /// have their tag at 0, and each variant is laid out as the tag
+    /// followed by its payload. Array elements follow each other without
+    /// padding, as sizes are multiples of alignments.
+    pub offsets: Vec<u64>,
+}
+
+impl Layout {
+    fn scalar(size: u64) -> Self {
+        Self {
+            size,
+            align: size.max(1),
+            offsets: vec![],
+        }
+    }
+
+    /// Fields in order, each at the next offset its alignment allows, and
+    /// the whole padded to a multiple of the largest alignment. `None` if
+    /// that does not fit in the address space.
+    fn aggregate(fields: &[Layout]) -> Option<Self> {
+        let mut size: u64 = 0;
+        let mut align = 1;
+        let mut offsets = vec![];
+        for field in fields {
+            size = size.checked_next_multiple_of(field.align)?;
+            offsets.push(size);
+            size = size.checked_add(field.size)?;
+            align = align.max(field.align);
+        }
+        Some(Self {
+            size: size.checked_next_multiple_of(align)?,
+            align,
+            offsets,
+        })
+    }
+}
+
+/// The layout of `ty`, or `None` for types without one: errors, type
+/// parameters, structs and enums that contain themselves, and arrays too
+/// large to address.
+pub fn layout_of(typing: &Typing, ty: TypeId) -> Option<Layout> {
+    layout_with(typing, ty, &mut vec![])
+}
+
+fn layout_with(typing: &Typing, ty: TypeId, open: &mut Vec<TypeId>) -> Option<Layout> {
+    let layout = match typing.types.get(ty) {
+        Type::Unit => Layout::scalar(0),
+        Type::Bool => Layout::scalar(1),
+        Type::Int(int) => Layout::scalar(u64::from(int.bits() / 8)),
+        Type::Real => Layout::scalar(8),
+        // A pointer and a length.
+        Type::String => Layout::aggregate(&[Layout::scalar(8), Layout::scalar(8)])?,
+        // The code and the captured environment.
+        Type::Func { .. } => Layout::aggregate(&[Layout::scalar(8), Layout::scalar(8)])?,
+        Type::Range(element) => {
+            let element = layout_with(typing, *element, open)?;
+            Layout::aggregate(&[element.clone(), element])?
+        }
+        Type::Array(element, len) => {
+            let element = layout_with(typing, *element, open)?;
+            Layout {
+                size: element.size.checked_mul(*len)?,
+                align: element.align,
+                offsets: vec![],
+            }
+        }
+        // A pointer and a length, like a string.
+        Type::Slice(_) => Layout::aggregate(&[Layout::scalar(8), Layout::scalar(8)])?,
+        Type::Tuple(elements) => {
+            let elements = elements
+                .iter()
+                .map(|&element| layout_with(typing, element, open))
+                .collect::<Option<Vec<_>>>()?;
+            Layout::aggregate(&elements)?
+        }
+        Type::Named(..) if open.contains(&ty) => return None,
+        Type::Named(..) => {
+            open.push(ty);
+            let layout = match (typing.fields_of(ty), typing.variants_of(ty)) {
+                (Some(fields), _) => fields
+                    .iter()
+                    .map(|field| layout_with(typing, field.ty, open))
+                    .collect::<Option<Vec<_>>>()
+                    .and_then(|fields| Layout::aggregate(&fields)),
+                (_, Some(variants)) => (0..variants.len())
+                    .map(|index| variant_with(typing, ty, index, open))
+                    .collect::<Option<Vec<_>>>()
+                    .map(|variants| Layout {
+                        size: variants.iter().map(|v| v.size).max().unwrap_or(0),
+                        align: variants.iter().map(|v| v.align).max().unwrap_or(1),
+                        offsets: vec![0],
+                    }),
+                _ => None,
+            };
+            open.pop();
+            layout?
+        }
+        Type::Error | Type::Param(_) | Type::Var(_) | Type::IntVar(_) => return None,
+    };
+    Some(layout)
+}
+
+/// The layout of the variant at `index` of the enum `ty`: its tag, then
+/// the elements of its payload.
+pub fn variant_layout(typing: &Typing, ty: TypeId, index: usize) -> Option<Layout> {
+    variant_with(typing, ty, index, &mut vec![])
+}
+
+fn variant_with(
+    typing: &Typing,
+    ty: TypeId,
+    index: usize,
+    open: &mut Vec<TypeId>,
+) -> Option<Layout> {
+    let variants = typing.variants_of(ty)?;
+    // The tag numbers the variants, whatever their discriminants.
+    let tag = match variants.len() {
+        0..=0x100 => 1,
+        0x101..=0x10000 => 2,
+        _ => 4,
+    };
+    let mut elements = vec![Layout::scalar(tag)];
+    for &element in &variants.get(index)?.payload {
+        elements.push(layout_with(typing, element, open)?);
+    }
+    Layout::aggregate(&elements)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{layout_of, variant_layout, Layout};
+    use crate::check::Typing;
+    use crate::fixture::Fixture;
+    use crate::parse_tree::NodeKind;
+    use crate::types::TypeId;
+
+    /// The layouts of the types of the top-level `let`s.
+    fn layouts(
+        source: &str,
+        layout_of: impl Fn(&Typing, TypeId) -> Option<Layout>,
+    ) -> Vec<Option<Layout>> {
+        let fixture = Fixture::parse(source);
+        let (resolution, typing) = fixture.check();
+        fixture.assert_clean();
+        let tree = &fixture.tree;
+        tree.roots()
+            .filter(|&root| tree.kind_of(root) == NodeKind::LetDecl)
+            .map(|root| {
+                let name = tree.children(root).next().unwrap();
+                let decl = resolution.decl_of(name).unwrap();
+                layout_of(&typing, typing.type_of_decl(decl).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_layout() {
+        let source = "type Pair = struct { tag: u8, value: Real, flag: Bool }\n\
+                      type Outer = struct { a: u16, pair: Pair, b: u8 }\n\
+                      let pair: Pair = Pair { tag: u8(1), value: 2.0, flag: true }\n\
+                      let outer: Outer = Outer { a: u16(1), pair, b: u8(2) }\n\
+                      let range = i32(0)..i32(4)\n\
+                      let name: String = \"mik\"\n\
+                      let bytes = [u8(1); 3]\n\
+                      let pairs: [Pair] = [pair][0..1]\n\
+                      type Huge = struct { a: [u8; 9223372036854775807], \
+                      b: [u8; 9223372036854775807], c: [u8; 9223372036854775807] }\n\
+                      let huge: Huge";
+        assert_eq!(
+            layouts(source, layout_of),
+            vec![
+                Some(Layout {
+                    size: 24,
+                    align: 8,
+                    offsets: vec![0, 8, 16]
+                }),
+                Some(Layout {
+                    size: 40,
+                    align: 8,
+                    offsets: vec![0, 8, 32]
+                }),
+                Some(Layout {
+                    size: 8,
+                    align: 4,
+                    offsets: vec![0, 4]
+                }),
+                Some(Layout {
+                    size: 16,
+                    align: 8,
+                    offsets: vec![0, 8]
+                }),
+                Some(Layout {
+                    size: 3,
+                    align: 1,
+                    offsets: vec![]
+                }),
+                Some(Layout {
+                    size: 16,
+                    align: 8,
+                    offsets: vec![0, 8]
+                }),
+                // Too large to address.
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recursive() {
+        // A type can only refer to itself through a slice or function.
+        let source = "type List = struct { head: Int, tail: [List] }\n\
+                      let list: List\n\
+                      let unit: () = ()";
+        assert_eq!(
+            layouts(source, layout_of),
+            vec![
+                Some(Layout {
+                    size: 24,
+                    align: 8,
+                    offsets: vec![0, 8]
+                }),
+                Some(Layout {
+                    size: 0,
+                    align: 1,
+                    offsets: vec![]
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enums() {
+        let source = "type Shape = enum {\n Circle(Real)\n Rect(u16, u16)\n Empty\n}\n\
+                      let shape = Shape.Empty";
+        assert_eq!(
+            layouts(source, layout_of),
+            vec![Some(Layout {
+                size: 16,
+                align: 8,
+                offsets: vec![0]
+            })]
+        );
+        assert_eq!(
+            layouts(source, |typing, ty| variant_layout(typing, ty, 1)),
+            vec![Some(Layout {
+                size: 6,
+                align: 2,
+                offsets: vec![0, 2, 4]
+            })]
+        );
+    }
+
+    #[test]
+    fn test_generic() {
+        // `W` holds ever larger instances of itself, behind slices.
+        let source = "type Pair[A, B] = struct { first: A, second: B }\n\
+                      type W[T] = struct { value: T, w: [W[(T, T)]] }\n\
+                      let small: Pair[u8, u8]\n\
+                      let wide: Pair[u8, Pair[Real, Bool]]\n\
+                      let w: W[Int]";
+        assert_eq!(
+            layouts(source, layout_of),
+            vec![
+                Some(Layout {
+                    size: 2,
+                    align: 1,
+                    offsets: vec![0, 1]
+                }),
+                Some(Layout {
+                    size: 24,
+                    align: 8,
+                    offsets: vec![0, 8]
+                }),
+                Some(Layout {
+                    size: 24,
+                    align: 8,
+                    offsets: vec![0, 8]
+                })
+            ]
+        );
+    }
+}
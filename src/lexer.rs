@@ -1,7 +1,6 @@
 use std::{cell::RefCell, rc::Rc};
 
-use crate::diagnostics::{self, Diagnostic, DiagnosticEmitter};
-use crate::fifo::Fifo;
+use crate::diagnostics::{self, Code, Diagnostic, DiagnosticEmitter};
 
 use super::{
     token::{self, TokenInfo},
@@ -13,15 +12,20 @@ pub struct Lexer<'a> {
     file_name: Option<&'a str>,
     source: &'a [u8],
     context: Context,
-    lookahead: Option<Context>,
     tokens: TokenizedBuffer<'a>,
+    token_start: (usize, usize),
 }
 
 pub struct Context {
     row: usize,
     col: usize,
     pos: usize,
-    pending: Fifo<u8>,
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Context {
@@ -30,7 +34,6 @@ impl Context {
             row: 0,
             col: 0,
             pos: 0,
-            pending: Fifo::new(),
         }
     }
 }
@@ -43,8 +46,8 @@ impl<'a> Lexer<'a> {
             file_name: None,
             source: bytes,
             context: Context::new(),
-            lookahead: None,
             tokens: TokenizedBuffer::new(bytes),
+            token_start: (0, 0),
         }
     }
 
@@ -55,24 +58,43 @@ impl<'a> Lexer<'a> {
             file_name: Some(file_name),
             source: bytes,
             context: Context::new(),
-            lookahead: None,
-            tokens: TokenizedBuffer::new(bytes),
+            tokens: TokenizedBuffer::new(bytes).with_file(Some(file_name)),
+            token_start: (0, 0),
         }
     }
 
     pub fn with_file_name(mut self, file_name: &'a str) -> Self {
         self.file_name = Some(file_name);
+        self.tokens = self.tokens.with_file(Some(file_name));
+        self
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
         self
     }
 
     pub fn tokenize(mut self) -> TokenizedBuffer<'a> {
         while let Some(byte) = self.peek() {
+            self.token_start = (self.context.row, self.context.col);
             match byte {
                 b' ' | b'\t' => self.visit_horizontal_whitespace(),
                 b'\r' | b'\n' => self.visit_vertical_whitespace(),
-                // b'a'..=b'z' | b'A'..=b'Z' | b'_' => self.visit_identifier(),
+                b'a'..=b'z' | b'A'..=b'Z' | b'_' => self.visit_identifier(),
                 b'0'..=b'9' => self.visit_number(),
-                b'+' | b'-' | b'*' | b'/' => self.visit_operator(),
+                b'/' if self.peek_next() == Some(b'/') => self.visit_comment(),
+                b'+' | b'-' | b'*' | b'/' | b'%' | b'=' | b'!' | b'<' | b'>' | b'&' | b'|'
+                | b':' | b',' | b';' | b'.' => self.visit_operator(),
+                b'(' | b')' | b'{' | b'}' | b'[' | b']' => self.visit_delimiter(),
+                b'"' => self.visit_string(),
+                b'@' => {
+                    let start = self.context.pos;
+                    self.take();
+                    self.push(
+                        token::TokenKind::At,
+                        token::Span::new(start, self.context.pos),
+                    );
+                }
                 b'\0' => {
                     self.take();
                     self.push(
@@ -81,34 +103,220 @@ impl<'a> Lexer<'a> {
                     );
                     break;
                 }
-                _ => {
+                _ => self.visit_invalid(),
+            };
+        }
+        if self.tokens.is_empty()
+            || self.tokens.kind_of(&self.tokens.last()) != token::TokenKind::Eof
+        {
+            self.token_start = (self.context.row, self.context.col);
+            self.push(
+                token::TokenKind::Eof,
+                token::Span::new(self.context.pos, self.context.pos),
+            );
+        }
+        self.tokens
+    }
+
+    fn visit_invalid(&mut self) {
+        let start = self.context.pos;
+        let (row, col) = (self.context.row, self.context.col);
+        self.take();
+        // Consume the rest of a multi-byte character so the error names it
+        // rather than its individual bytes.
+        while let Some(byte) = self.peek() {
+            match byte {
+                0x80..=0xBF => self.take(),
+                _ => break,
+            };
+        }
+        let text = String::from_utf8_lossy(&self.source[start..self.context.pos]).into_owned();
+        self.emit_error_at(
+            Code::L0001,
+            format!("invalid character `{}`", text.escape_debug()),
+            row,
+            col,
+            token::Span::new(start, self.context.pos),
+        );
+        self.push(
+            token::TokenKind::Invalid,
+            token::Span::new(start, self.context.pos),
+        );
+    }
+
+    /// `"text"` on a single line, with `\\n`, `\\t`, `\\r`, `\\0`, `\\\\` and `\\"`
+    /// escapes. An unterminated string still becomes a token, ending at the
+    /// end of its line.
+    fn visit_string(&mut self) {
+        let start = self.context.pos;
+        let (row, col) = (self.context.row, self.context.col);
+        self.take();
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.take();
+                    break;
+                }
+                Some(b'\\') => {
+                    let (row, col) = (self.context.row, self.context.col);
                     self.take();
-                    self.push(
-                        token::TokenKind::Invalid,
-                        token::Span::new(self.context.pos, self.context.pos),
+                    match self.peek() {
+                        Some(b'n' | b't' | b'r' | b'0' | b'\\' | b'"') => {
+                            self.take();
+                        }
+                        Some(b'\r' | b'\n') | None => {}
+                        Some(_) => {
+                            let escape_start = self.context.pos;
+                            self.take();
+                            while let Some(0x80..=0xBF) = self.peek() {
+                                self.take();
+                            }
+                            let text = String::from_utf8_lossy(
+                                &self.source[escape_start..self.context.pos],
+                            )
+                            .into_owned();
+                            self.emit_error_at(
+                                Code::L0003,
+                                format!("unknown escape sequence `\\{}`", text.escape_debug()),
+                                row,
+                                col,
+                                token::Span::new(escape_start - 1, self.context.pos),
+                            );
+                        }
+                    }
+                }
+                Some(b'\r' | b'\n') | None => {
+                    self.emit_error_at(
+                        Code::L0002,
+                        "unterminated string literal".to_string(),
+                        row,
+                        col,
+                        token::Span::new(start, self.context.pos),
                     );
+                    break;
                 }
+                Some(_) => {
+                    self.take();
+                }
+            }
+        }
+        self.push(
+            token::TokenKind::StringLiteral,
+            token::Span::new(start, self.context.pos),
+        );
+    }
+
+    fn visit_comment(&mut self) {
+        let start = self.context.pos;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'\r' | b'\n' => break,
+                _ => self.take(),
             };
         }
-        return self.tokens;
+        self.push(
+            token::TokenKind::Comment,
+            token::Span::new(start, self.context.pos),
+        );
     }
 
     fn visit_operator(&mut self) {
         let start = self.context.pos;
         let op = self.take();
         assert!(op.is_some());
+        let kind = match op.unwrap() {
+            b'+' if self.take_if(b'=') => token::TokenKind::PlusEqual,
+            b'+' => token::TokenKind::Plus,
+            b'-' if self.take_if(b'>') => token::TokenKind::Arrow,
+            b'-' if self.take_if(b'=') => token::TokenKind::MinusEqual,
+            b'-' => token::TokenKind::Minus,
+            b'*' if self.take_if(b'=') => token::TokenKind::StarEqual,
+            b'*' => token::TokenKind::Star,
+            b'/' if self.take_if(b'=') => token::TokenKind::SlashEqual,
+            b'/' => token::TokenKind::Slash,
+            b'%' if self.take_if(b'=') => token::TokenKind::PercentEqual,
+            b'%' => token::TokenKind::Percent,
+            b'=' if self.take_if(b'=') => token::TokenKind::EqualEqual,
+            b'=' if self.take_if(b'>') => token::TokenKind::FatArrow,
+            b'=' => token::TokenKind::Equal,
+            b'!' if self.take_if(b'=') => token::TokenKind::BangEqual,
+            b'!' => token::TokenKind::Bang,
+            b'<' if self.take_if(b'<') => token::TokenKind::LessLess,
+            b'<' if self.take_if(b'=') => token::TokenKind::LessEqual,
+            b'<' => token::TokenKind::Less,
+            b'>' if self.take_if(b'>') => token::TokenKind::GreaterGreater,
+            b'>' if self.take_if(b'=') => token::TokenKind::GreaterEqual,
+            b'>' => token::TokenKind::Greater,
+            b'&' if self.take_if(b'&') => token::TokenKind::AmpAmp,
+            b'&' => token::TokenKind::Amp,
+            b'|' if self.take_if(b'|') => token::TokenKind::PipePipe,
+            b'|' => token::TokenKind::Pipe,
+            b':' => token::TokenKind::Colon,
+            b',' => token::TokenKind::Comma,
+            b';' => token::TokenKind::Semi,
+            b'.' if self.take_if(b'.') => token::TokenKind::DotDot,
+            b'.' => token::TokenKind::Dot,
+            _ => unreachable!(),
+        };
+        self.push(kind, token::Span::new(start, self.context.pos));
+    }
+
+    fn visit_delimiter(&mut self) {
+        let start = self.context.pos;
+        let delimiter = self.take();
+        assert!(delimiter.is_some());
         self.push(
-            match op.unwrap() {
-                b'+' => token::TokenKind::Plus,
-                b'-' => token::TokenKind::Minus,
-                b'*' => token::TokenKind::Star,
-                b'/' => token::TokenKind::Slash,
+            match delimiter.unwrap() {
+                b'(' => token::TokenKind::OpenParen,
+                b')' => token::TokenKind::CloseParen,
+                b'{' => token::TokenKind::OpenBrace,
+                b'}' => token::TokenKind::CloseBrace,
+                b'[' => token::TokenKind::OpenBracket,
+                b']' => token::TokenKind::CloseBracket,
                 _ => unreachable!(),
             },
             token::Span::new(start, self.context.pos),
         );
     }
 
+    fn visit_identifier(&mut self) {
+        let start = self.context.pos;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' => self.take(),
+                _ => break,
+            };
+        }
+        let kind = match &self.source[start..self.context.pos] {
+            b"func" => token::TokenKind::FuncKeyword,
+            b"type" => token::TokenKind::TypeKeyword,
+            b"module" => token::TokenKind::ModuleKeyword,
+            b"struct" => token::TokenKind::StructKeyword,
+            b"enum" => token::TokenKind::EnumKeyword,
+            b"import" => token::TokenKind::ImportKeyword,
+            b"as" => token::TokenKind::AsKeyword,
+            b"pub" => token::TokenKind::PubKeyword,
+            b"let" => token::TokenKind::LetKeyword,
+            b"var" => token::TokenKind::VarKeyword,
+            b"const" => token::TokenKind::ConstKeyword,
+            b"if" => token::TokenKind::IfKeyword,
+            b"else" => token::TokenKind::ElseKeyword,
+            b"while" => token::TokenKind::WhileKeyword,
+            b"for" => token::TokenKind::ForKeyword,
+            b"in" => token::TokenKind::InKeyword,
+            b"break" => token::TokenKind::BreakKeyword,
+            b"continue" => token::TokenKind::ContinueKeyword,
+            b"return" => token::TokenKind::ReturnKeyword,
+            b"match" => token::TokenKind::MatchKeyword,
+            b"impl" => token::TokenKind::ImplKeyword,
+            b"interface" => token::TokenKind::InterfaceKeyword,
+            b"true" => token::TokenKind::TrueKeyword,
+            b"false" => token::TokenKind::FalseKeyword,
+            _ => token::TokenKind::Ident,
+        };
+        self.push(kind, token::Span::new(start, self.context.pos));
+    }
+
     fn visit_number(&mut self) {
         let initial_pos = self.context.pos;
         let mut is_float = false;
@@ -119,23 +327,25 @@ impl<'a> Lexer<'a> {
             };
         }
 
-        match self.peek() {
-            Some(b'.') => {
-                self.take();
-                while let Some(byte) = self.peek() {
-                    match byte {
-                        b'0'..=b'9' => self.take(),
-                        _ => break,
-                    };
-                }
-                is_float = true;
+        // `0..10` is a range, so a dot only continues the number when a
+        // digit follows it.
+        if self.peek() == Some(b'.') && self.peek_next().is_some_and(|b| b.is_ascii_digit()) {
+            self.take();
+            while let Some(byte) = self.peek() {
+                match byte {
+                    b'0'..=b'9' => self.take(),
+                    _ => break,
+                };
             }
-            _ => {}
+            is_float = true;
         }
 
+        // A number with an exponent is real, even without a dot: `1e5`
+        // as much as `1e-5`.
         match self.peek() {
             Some(b'e') | Some(b'E') => {
                 self.take();
+                is_float = true;
                 match self.peek() {
                     Some(b'+') | Some(b'-') => {
                         self.take();
@@ -194,8 +404,8 @@ impl<'a> Lexer<'a> {
         self.tokens.push(
             kind,
             TokenInfo {
-                row: self.context.row,
-                col: self.context.col,
+                row: self.token_start.0,
+                col: self.token_start.1,
                 start: span.start,
                 end: span.end,
             },
@@ -206,6 +416,18 @@ impl<'a> Lexer<'a> {
         self.source.get(self.context.pos).copied()
     }
 
+    fn peek_next(&self) -> Option<u8> {
+        self.source.get(self.context.pos + 1).copied()
+    }
+
+    fn take_if(&mut self, byte: u8) -> bool {
+        let matches = self.peek() == Some(byte);
+        if matches {
+            self.take();
+        }
+        matches
+    }
+
     fn take(&mut self) -> Option<u8> {
         let byte = self.peek();
         if byte.is_some() {
@@ -224,15 +446,48 @@ impl<'a> Lexer<'a> {
         self.diagnostics.borrow_mut().emit(diagnostic);
     }
 
-    fn emit_error(&mut self, message: &str) {
+    fn emit_error_at(
+        &mut self,
+        code: Code,
+        message: String,
+        row: usize,
+        col: usize,
+        span: token::Span,
+    ) {
         self.emit(Diagnostic {
             message,
+            code: Some(code),
             level: diagnostics::Level::Error,
-            location: token::SourceLocation::new(
-                self.file_name,
-                self.context.row,
-                self.context.col,
-            ),
+            location: token::SourceLocation::new(self.file_name, row, col),
+            span,
         });
     }
 }
+
+/// The value of a string literal token, with its quotes removed and its
+/// escapes replaced. Unknown escapes, already reported, are kept as written.
+pub fn unescape(literal: &str) -> String {
+    let inner = literal.strip_prefix('"').unwrap_or(literal);
+    let inner = inner.strip_suffix('"').unwrap_or(inner);
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('0') => value.push('\0'),
+            Some(c @ ('\\' | '"')) => value.push(c),
+            Some(c) => {
+                value.push('\\');
+                value.push(c);
+            }
+            None => value.push('\\'),
+        }
+    }
+    value
+}
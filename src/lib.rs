@@ -1,12 +1,27 @@
+pub mod attributes;
+pub mod bounds;
+pub mod check;
+pub mod consteval;
+pub mod cst;
 pub mod diagnostics;
+pub mod error;
+pub mod exhaustiveness;
+pub mod flow;
+pub mod layout;
 pub mod lexer;
+pub mod lint;
+pub mod modules;
 pub mod parse_tree;
 pub mod parser;
+pub mod resolve;
+pub mod test_runner;
 pub mod token;
 pub mod tokenized_buffer;
+pub mod types;
 
 pub(crate) mod fifo;
+#[cfg(test)]
+pub(crate) mod fixture;
 pub(crate) mod smallvec;
 
-pub type Result<T> = ::core::result::Result<T, Error>;
-pub type Error = Box<dyn std::error::Error>;
+pub use error::{CompileError, Error, Result};
@@ -0,0 +1,387 @@
+use std::cell::{OnceCell, RefCell};
+use std::rc::Rc;
+
+use crate::attributes::{Attribute, AttributeArg};
+use crate::check::Typing;
+use crate::diagnostics::{self, Code, Diagnostic, DiagnosticEmitter};
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::resolve::{DeclId, Resolution};
+use crate::token::Token;
+use crate::tokenized_buffer::TokenizedBuffer;
+
+mod style;
+mod unreachable;
+mod unused;
+
+pub use style::{RedundantParens, ShadowedBindings, REDUNDANT_PARENS, SHADOWED_BINDINGS};
+pub use unreachable::{UnreachableCode, UNREACHABLE_CODE};
+pub use unused::{
+    UnusedFunctions, UnusedImports, UnusedVariables, UNUSED_FUNCTIONS, UNUSED_IMPORTS,
+    UNUSED_VARIABLES,
+};
+
+/// What becomes of what a lint finds.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    /// The level set by the attribute `name`, as in `@allow(...)`.
+    fn of_attribute(name: &str) -> Option<LintLevel> {
+        match name {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Lint {
+    pub name: &'static str,
+    pub code: Code,
+    pub default: LintLevel,
+    pub description: &'static str,
+}
+
+/// A check of a program that passed type checking. What it finds is not
+/// wrong, only suspicious, and reported at the level of its lint.
+pub trait LintPass {
+    fn lint(&self) -> &'static Lint;
+    fn check(&self, cx: &LintContext);
+}
+
+/// The lints the compiler runs, with the levels given on the command line.
+/// Attributes on declarations override those for the code inside them.
+pub struct LintRegistry {
+    passes: Vec<Box<dyn LintPass>>,
+    levels: Vec<(&'static str, LintLevel)>,
+    diagnostics: Rc<RefCell<dyn DiagnosticEmitter>>,
+}
+
+impl Default for LintRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LintRegistry {
+    /// A registry holding the builtin lints.
+    pub fn new() -> Self {
+        let passes: Vec<Box<dyn LintPass>> = vec![
+            Box::new(UnusedVariables),
+            Box::new(UnusedImports),
+            Box::new(UnusedFunctions),
+            Box::new(UnreachableCode),
+            Box::new(RedundantParens),
+            Box::new(ShadowedBindings),
+        ];
+        Self {
+            passes,
+            levels: vec![],
+            diagnostics: Rc::new(RefCell::new(diagnostics::DefaultEmitter)),
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
+        self
+    }
+
+    /// Adds `pass`, replacing a lint of the same name.
+    pub fn register(&mut self, pass: Box<dyn LintPass>) {
+        let name = pass.lint().name;
+        self.passes.retain(|known| known.lint().name != name);
+        self.passes.push(pass);
+    }
+
+    pub fn lint(&self, name: &str) -> Option<&'static Lint> {
+        self.passes
+            .iter()
+            .map(|pass| pass.lint())
+            .find(|lint| lint.name == name)
+    }
+
+    pub fn lints(&self) -> impl Iterator<Item = &'static Lint> + '_ {
+        self.passes.iter().map(|pass| pass.lint())
+    }
+
+    /// Sets the level of the lint `name` for the whole program, as the
+    /// command line does.
+    pub fn set_level(&mut self, name: &str, level: LintLevel) -> Result<(), String> {
+        let lint = self
+            .lint(name)
+            .ok_or_else(|| format!("unknown lint `{}`", name))?;
+        self.levels.retain(|&(known, _)| known != lint.name);
+        self.levels.push((lint.name, level));
+        Ok(())
+    }
+
+    /// Runs every lint. `attributes` are the validated attributes of the
+    /// tree, whose lint names `AttributeRegistry` has already checked.
+    pub fn check(
+        &self,
+        tree: &ParseTree,
+        tokens: &TokenizedBuffer,
+        resolution: &Resolution,
+        typing: &Typing,
+        attributes: &[Attribute],
+    ) {
+        let parents = tree.parents();
+        let references = OnceCell::new();
+        for pass in &self.passes {
+            if self.is_allowed_everywhere(pass.lint(), attributes) {
+                continue;
+            }
+            let cx = LintContext {
+                tree,
+                tokens,
+                resolution,
+                typing,
+                attributes,
+                registry: self,
+                lint: pass.lint(),
+                parents: &parents,
+                references: &references,
+            };
+            pass.check(&cx);
+        }
+    }
+
+    /// The level of `lint` outside of attributes.
+    fn level(&self, lint: &Lint) -> LintLevel {
+        self.levels
+            .iter()
+            .find(|&&(name, _)| name == lint.name)
+            .map_or(lint.default, |&(_, level)| level)
+    }
+
+    /// Whether `lint` would report nothing wherever it looked, so that it
+    /// need not run.
+    fn is_allowed_everywhere(&self, lint: &Lint, attributes: &[Attribute]) -> bool {
+        self.level(lint) == LintLevel::Allow
+            && attributes.iter().all(|attribute| {
+                LintLevel::of_attribute(&attribute.name)
+                    .is_none_or(|level| level == LintLevel::Allow || !names(attribute, lint))
+            })
+    }
+
+    fn emit(
+        &self,
+        tokens: &TokenizedBuffer,
+        token: Token,
+        level: diagnostics::Level,
+        code: Code,
+        message: String,
+    ) {
+        self.diagnostics.borrow_mut().emit(Diagnostic {
+            level,
+            code: Some(code),
+            message,
+            location: tokens.location_of(&token).into_owned(),
+            span: tokens.span_of(&token),
+        });
+    }
+}
+
+/// Whether `attribute` names `lint`, as `@allow(lint)` does.
+fn names(attribute: &Attribute, lint: &Lint) -> bool {
+    attribute.args.iter().any(|arg| match arg {
+        AttributeArg::Name(name) => name == lint.name,
+        _ => false,
+    })
+}
+
+/// What a lint pass sees of the program, and where it reports.
+pub struct LintContext<'a> {
+    pub tree: &'a ParseTree,
+    pub tokens: &'a TokenizedBuffer<'a>,
+    pub resolution: &'a Resolution,
+    pub typing: &'a Typing,
+    pub attributes: &'a [Attribute],
+    registry: &'a LintRegistry,
+    lint: &'static Lint,
+    parents: &'a [Option<Node>],
+    /// Shared by the passes, and only found for those that ask.
+    references: &'a OnceCell<Vec<Vec<Node>>>,
+}
+
+impl LintContext<'_> {
+    /// Like `ParseTree::parent`, without scanning the tree.
+    pub fn parent(&self, node: Node) -> Option<Node> {
+        self.parents[*node]
+    }
+
+    /// The nodes that use each declaration, indexed by `DeclId`.
+    pub fn references(&self) -> &[Vec<Node>] {
+        self.references.get_or_init(|| {
+            let mut references = vec![vec![]; self.resolution.decls().count()];
+            for node in self.tree.postorder() {
+                if let Some(decl) = self.resolution.decl_of(node) {
+                    if self.uses(node, decl) {
+                        references[decl.0].push(node);
+                    }
+                }
+            }
+            references
+        })
+    }
+
+    /// Whether `node` uses `decl`, rather than declaring it. Assigning to
+    /// the whole of a variable does not use it.
+    fn uses(&self, node: Node, decl: DeclId) -> bool {
+        if self.resolution.decl(decl).node == Some(node) {
+            return false;
+        }
+        match self.tree.kind_of(node) {
+            NodeKind::DeclName | NodeKind::BindingPattern | NodeKind::FieldPattern => false,
+            _ => match self.parent(node) {
+                Some(parent) if self.tree.kind_of(parent) == NodeKind::Assign => {
+                    self.tree.children(parent).next() != Some(node)
+                        || self.tree.text(self.tokens, parent) != "="
+                }
+                _ => true,
+            },
+        }
+    }
+
+    /// The level of the lint at `node`: that of the innermost declaration
+    /// around it with an attribute naming the lint, else the one from the
+    /// command line, else its default. An attribute on the module header
+    /// covers the whole file.
+    pub fn level_at(&self, node: Node) -> LintLevel {
+        let mut innermost: Option<(usize, LintLevel)> = None;
+        for attribute in self.attributes {
+            let Some(level) = LintLevel::of_attribute(&attribute.name) else {
+                continue;
+            };
+            if !names(attribute, self.lint) {
+                continue;
+            }
+            let decl = attribute.decl;
+            let size = match self.tree.kind_of(decl) {
+                NodeKind::ModuleDecl => usize::MAX,
+                _ => self.tree.subtree_size_of(decl),
+            };
+            let covers = size == usize::MAX || (*decl + 1 - size..=*decl).contains(&*node);
+            if covers && innermost.is_none_or(|(other, _)| size < other) {
+                innermost = Some((size, level));
+            }
+        }
+        match innermost {
+            Some((_, level)) => level,
+            None => self.registry.level(self.lint),
+        }
+    }
+
+    /// Reports a finding at `node`, unless the lint is allowed there.
+    pub fn report(&self, node: Node, message: String) {
+        let level = match self.level_at(node) {
+            LintLevel::Allow => return,
+            LintLevel::Warn => diagnostics::Level::Warning,
+            LintLevel::Deny => diagnostics::Level::Error,
+        };
+        self.registry.emit(
+            self.tokens,
+            self.tree.token_of(node),
+            level,
+            self.lint.code,
+            message,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Lint, LintContext, LintLevel, LintPass, LintRegistry};
+    use crate::attributes::AttributeRegistry;
+    use crate::diagnostics::{Code, Level};
+    use crate::fixture::Fixture;
+
+    /// What `registry` reports for `source`, by code and level.
+    fn lint(registry: LintRegistry, source: &str) -> Vec<(Code, Level)> {
+        let fixture = Fixture::parse(source);
+        let attributes = AttributeRegistry::new()
+            .with_lints(&registry)
+            .with_emitter(fixture.emitter.clone())
+            .check(&fixture.tree, &fixture.tokens);
+        let (resolution, typing) = fixture.check();
+        fixture.assert_clean();
+        registry.with_emitter(fixture.emitter.clone()).check(
+            &fixture.tree,
+            &fixture.tokens,
+            &resolution,
+            &typing,
+            &attributes,
+        );
+        fixture
+            .take()
+            .iter()
+            .map(|d| (d.code.unwrap(), d.level))
+            .collect()
+    }
+
+    #[test]
+    fn test_levels() {
+        let source = "func main() {\nlet a = (1)\n}";
+        assert_eq!(
+            lint(LintRegistry::new(), source),
+            vec![(Code::W0001, Level::Warning), (Code::W0005, Level::Warning)]
+        );
+
+        let mut registry = LintRegistry::new();
+        registry
+            .set_level("unused_variables", LintLevel::Allow)
+            .unwrap();
+        registry
+            .set_level("redundant_parens", LintLevel::Deny)
+            .unwrap();
+        assert!(registry.set_level("unused", LintLevel::Deny).is_err());
+        assert_eq!(lint(registry, source), vec![(Code::W0005, Level::Error)]);
+
+        // Attributes win over the command line, the innermost first.
+        let source = "@allow(redundant_parens)\nfunc main() {\n\
+                      @warn(redundant_parens) let a = (1)\nlet _b = (2)\n}";
+        let mut registry = LintRegistry::new();
+        registry
+            .set_level("redundant_parens", LintLevel::Deny)
+            .unwrap();
+        assert_eq!(
+            lint(registry, source),
+            vec![(Code::W0001, Level::Warning), (Code::W0005, Level::Warning)]
+        );
+    }
+
+    static NEVER: Lint = Lint {
+        name: "never",
+        code: Code::W0006,
+        default: LintLevel::Allow,
+        description: "a lint that must not run",
+    };
+
+    struct Never;
+
+    impl LintPass for Never {
+        fn lint(&self) -> &'static Lint {
+            &NEVER
+        }
+
+        fn check(&self, _: &LintContext) {
+            panic!("`never` ran while allowed everywhere");
+        }
+    }
+
+    #[test]
+    fn test_allowed_everywhere() {
+        let mut registry = LintRegistry::new();
+        registry.register(Box::new(Never));
+        assert_eq!(
+            lint(registry, "func main() {}\n@allow(never) let _a = 1"),
+            vec![]
+        );
+    }
+}
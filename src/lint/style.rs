@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use crate::check::Member;
+use crate::diagnostics::Code;
+use crate::parse_tree::{Node, NodeKind};
+use crate::resolve::DeclKind;
+
+use super::{Lint, LintContext, LintLevel, LintPass};
+
+pub static REDUNDANT_PARENS: Lint = Lint {
+    name: "redundant_parens",
+    code: Code::W0005,
+    default: LintLevel::Warn,
+    description: "parentheses that do not change how an expression parses",
+};
+
+pub static SHADOWED_BINDINGS: Lint = Lint {
+    name: "shadowed_bindings",
+    code: Code::W0006,
+    default: LintLevel::Allow,
+    description: "locals that hide another of the same name in scope",
+};
+
+pub struct RedundantParens;
+
+impl LintPass for RedundantParens {
+    fn lint(&self) -> &'static Lint {
+        &REDUNDANT_PARENS
+    }
+
+    fn check(&self, cx: &LintContext) {
+        for node in cx.tree.postorder() {
+            if cx.tree.kind_of(node) != NodeKind::ParenExpr {
+                continue;
+            }
+            let mut children = cx.tree.children(node);
+            let (Some(open), Some(inner)) = (children.next(), children.next()) else {
+                continue;
+            };
+            if is_atomic(cx, inner) || stands_alone(cx, node) {
+                cx.report(open, "unnecessary parentheses".to_string());
+            }
+        }
+    }
+}
+
+/// Whether an expression binds tighter than any operator around it.
+fn is_atomic(cx: &LintContext, node: Node) -> bool {
+    matches!(
+        cx.tree.kind_of(node),
+        NodeKind::IntLiteral
+            | NodeKind::RealLiteral
+            | NodeKind::StringLiteral
+            | NodeKind::BoolLiteral
+            | NodeKind::Name
+            | NodeKind::GenericName
+            | NodeKind::Call
+            | NodeKind::FieldAccess
+            | NodeKind::IndexExpr
+            | NodeKind::ArrayLiteral
+            | NodeKind::ArrayRepeat
+            | NodeKind::TupleLiteral
+            | NodeKind::ParenExpr
+    )
+}
+
+/// Whether the parenthesized `node` is a whole expression on its own,
+/// where nothing around it could take its operands.
+fn stands_alone(cx: &LintContext, node: Node) -> bool {
+    let Some(parent) = cx.parent(node) else {
+        return false;
+    };
+    let first = cx.tree.children(parent).next() == Some(node);
+    match cx.tree.kind_of(parent) {
+        NodeKind::Initializer
+        | NodeKind::ExprStmt
+        | NodeKind::Return
+        | NodeKind::FieldInit
+        | NodeKind::MatchGuard
+        | NodeKind::MatchArm
+        | NodeKind::DefaultValue => true,
+        NodeKind::Assign
+        | NodeKind::Call
+        | NodeKind::IndexExpr
+        | NodeKind::ArrayLiteral
+        | NodeKind::ArrayRepeat
+        | NodeKind::TupleLiteral => !first,
+        // A struct literal there needs its parentheses, or its `{` would
+        // start the block.
+        NodeKind::If | NodeKind::While | NodeKind::MatchExpr => {
+            first
+                && !cx
+                    .tree
+                    .postorder_of(node)
+                    .any(|part| cx.tree.kind_of(part) == NodeKind::StructLiteral)
+        }
+        _ => false,
+    }
+}
+
+pub struct ShadowedBindings;
+
+impl LintPass for ShadowedBindings {
+    fn lint(&self) -> &'static Lint {
+        &SHADOWED_BINDINGS
+    }
+
+    fn check(&self, cx: &LintContext) {
+        // Each local, and the locals by name and the function they are
+        // declared in, with the node their scope ends at.
+        let mut locals = vec![];
+        let mut by_name = HashMap::<_, Vec<_>>::new();
+        for (_, decl) in cx.resolution.decls() {
+            let Some(node) = decl.node else {
+                continue;
+            };
+            let local = match decl.kind {
+                DeclKind::Local { .. } | DeclKind::Param => decl.name != "self",
+                DeclKind::PatternBinding => {
+                    !matches!(cx.typing.member_of(node), Some(Member::Variant(_)))
+                }
+                _ => false,
+            };
+            if local && !decl.name.starts_with('_') {
+                let key = (decl.name.as_str(), function(cx, node));
+                locals.push((key, node));
+                by_name
+                    .entry(key)
+                    .or_default()
+                    .push((node, scope(cx, node)));
+            }
+        }
+        for &(key, node) in &locals {
+            let (name, _) = key;
+            let shadows = by_name[&key].iter().any(|&(earlier, scope)| {
+                earlier < node
+                    && scope.is_some_and(|scope| {
+                        *scope + 1 - cx.tree.subtree_size_of(scope) <= *node && node < scope
+                    })
+            });
+            if shadows {
+                cx.report(node, format!("`{}` shadows an earlier binding", name));
+            }
+        }
+    }
+}
+
+/// The node whose subtree a local declared at `node` is visible in.
+fn scope(cx: &LintContext, node: Node) -> Option<Node> {
+    let mut current = cx.parent(node)?;
+    loop {
+        match cx.tree.kind_of(current) {
+            NodeKind::LetDecl | NodeKind::VarDecl => return cx.parent(current),
+            NodeKind::For
+            | NodeKind::MatchArm
+            | NodeKind::FuncDecl
+            | NodeKind::ClosureExpr
+            | NodeKind::FuncExpr => return Some(current),
+            _ => current = cx.parent(current)?,
+        }
+    }
+}
+
+/// The function declaration `node` is in. Closures share the locals of
+/// the function around them.
+fn function(cx: &LintContext, node: Node) -> Option<Node> {
+    let mut current = cx.parent(node)?;
+    while cx.tree.kind_of(current) != NodeKind::FuncDecl {
+        current = cx.parent(current)?;
+    }
+    Some(current)
+}
@@ -0,0 +1,49 @@
+use crate::diagnostics::Code;
+use crate::flow::{bodies, Cfg};
+use crate::parse_tree::NodeKind;
+
+use super::{Lint, LintContext, LintLevel, LintPass};
+
+pub static UNREACHABLE_CODE: Lint = Lint {
+    name: "unreachable_code",
+    code: Code::W0004,
+    default: LintLevel::Warn,
+    description: "statements after `return`, `break`, `continue` or an endless loop",
+};
+
+/// Reports the first statement of each run that cannot be reached, on the
+/// control-flow graph of its body.
+pub struct UnreachableCode;
+
+impl LintPass for UnreachableCode {
+    fn lint(&self) -> &'static Lint {
+        &UNREACHABLE_CODE
+    }
+
+    fn check(&self, cx: &LintContext) {
+        let mut reachable = vec![None; cx.tree.len()];
+        for (_, body) in bodies(cx.tree) {
+            let cfg = Cfg::build(cx.tree, cx.tokens, cx.resolution, &body);
+            for (block, live) in cfg.blocks.iter().zip(cfg.reachable()) {
+                for &stmt in &block.stmts {
+                    reachable[*stmt] = Some(live);
+                }
+            }
+        }
+        for block in cx.tree.postorder() {
+            if cx.tree.kind_of(block) != NodeKind::Block {
+                continue;
+            }
+            let mut previous = true;
+            for stmt in cx.tree.children(block) {
+                let Some(live) = reachable[*stmt] else {
+                    continue;
+                };
+                if previous && !live {
+                    cx.report(stmt, "unreachable code".to_string());
+                }
+                previous = live;
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::check::Member;
+use crate::diagnostics::Code;
+use crate::parse_tree::{Node, NodeKind};
+use crate::resolve::DeclKind;
+
+use super::{Lint, LintContext, LintLevel, LintPass};
+
+pub static UNUSED_VARIABLES: Lint = Lint {
+    name: "unused_variables",
+    code: Code::W0001,
+    default: LintLevel::Warn,
+    description: "locals and parameters that are never read",
+};
+
+pub static UNUSED_IMPORTS: Lint = Lint {
+    name: "unused_imports",
+    code: Code::W0002,
+    default: LintLevel::Warn,
+    description: "imports that nothing refers to",
+};
+
+pub static UNUSED_FUNCTIONS: Lint = Lint {
+    name: "unused_functions",
+    code: Code::W0003,
+    default: LintLevel::Warn,
+    description: "private functions that are never called outside themselves",
+};
+
+pub struct UnusedVariables;
+
+impl LintPass for UnusedVariables {
+    fn lint(&self) -> &'static Lint {
+        &UNUSED_VARIABLES
+    }
+
+    fn check(&self, cx: &LintContext) {
+        let references = cx.references();
+        for (id, decl) in cx.resolution.decls() {
+            let Some(node) = decl.node else {
+                continue;
+            };
+            let variable = match decl.kind {
+                DeclKind::Local { .. } => true,
+                DeclKind::Param => decl.name != "self" && has_body(cx, node),
+                // A name in a pattern can stand for a variant instead.
+                DeclKind::PatternBinding => {
+                    !matches!(cx.typing.member_of(node), Some(Member::Variant(_)))
+                }
+                _ => false,
+            };
+            if variable && !decl.name.starts_with('_') && references[id.0].is_empty() {
+                let message = format!(
+                    "unused variable `{}`, name it `_{}` if that is intended",
+                    decl.name, decl.name
+                );
+                cx.report(node, message);
+            }
+        }
+    }
+}
+
+/// Whether the parameter declared at `name` belongs to a function with a
+/// body, unlike the methods of an interface.
+fn has_body(cx: &LintContext, name: Node) -> bool {
+    let func = cx
+        .parent(name)
+        .and_then(|param| cx.parent(param))
+        .and_then(|list| cx.parent(list));
+    match func {
+        Some(func) if cx.tree.kind_of(func) == NodeKind::FuncDecl => {
+            cx.tree.child(func, NodeKind::Block).is_some()
+        }
+        _ => true,
+    }
+}
+
+pub struct UnusedImports;
+
+impl LintPass for UnusedImports {
+    fn lint(&self) -> &'static Lint {
+        &UNUSED_IMPORTS
+    }
+
+    fn check(&self, cx: &LintContext) {
+        let references = cx.references();
+        for (id, decl) in cx.resolution.decls() {
+            let Some(node) = decl.node else {
+                continue;
+            };
+            if decl.kind == DeclKind::Import && references[id.0].is_empty() {
+                cx.report(node, format!("unused import `{}`", decl.name));
+            }
+        }
+    }
+}
+
+pub struct UnusedFunctions;
+
+impl LintPass for UnusedFunctions {
+    fn lint(&self) -> &'static Lint {
+        &UNUSED_FUNCTIONS
+    }
+
+    fn check(&self, cx: &LintContext) {
+        let references = cx.references();
+        for (id, decl) in cx.resolution.decls() {
+            let Some(name) = decl.node else {
+                continue;
+            };
+            let Some(func) = cx.parent(name) else {
+                continue;
+            };
+            if decl.kind != DeclKind::Func || decl.name == "main" || !is_private(cx, func) {
+                continue;
+            }
+            // Calls from inside the function itself do not use it.
+            let start = *func + 1 - cx.tree.subtree_size_of(func);
+            let used = references[id.0]
+                .iter()
+                .any(|&node| !(start..=*func).contains(&*node));
+            if !used {
+                cx.report(name, format!("function `{}` is never used", decl.name));
+            }
+        }
+    }
+}
+
+/// Whether a function is only visible in its module, and only called from
+/// it: not `pub`, not a method, and not marked `@test` or `@extern`.
+fn is_private(cx: &LintContext, func: Node) -> bool {
+    let method = cx.parent(func).is_some_and(|parent| {
+        matches!(
+            cx.tree.kind_of(parent),
+            NodeKind::ImplDecl | NodeKind::InterfaceDecl
+        )
+    });
+    let entry = cx.attributes.iter().any(|attribute| {
+        attribute.decl == func && matches!(attribute.name.as_str(), "test" | "extern")
+    });
+    !method && !entry && cx.tree.child(func, NodeKind::Pub).is_none()
+}
@@ -1,5 +1,11 @@
+use std::path::PathBuf;
+
 use clap::Parser;
+use mik::diagnostics::{DefaultEmitter, DiagnosticEmitter, Level};
 use mik::lexer::Lexer;
+use mik::lint::{LintLevel, LintRegistry};
+use mik::parser::Parser as MikParser;
+use mik::test_runner;
 
 #[derive(clap::Parser)]
 struct App {
@@ -14,6 +20,55 @@ enum Cmd {
         #[clap(value_parser)]
         file: String,
     },
+    /// Print the parse tree of a file.
+    #[clap(name = "parse", alias = "p")]
+    Parse {
+        #[clap(value_parser)]
+        file: String,
+        #[clap(long, value_enum, default_value = "sexpr")]
+        format: TreeFormat,
+    },
+    /// Compile a file and print what the compiler reports.
+    #[clap(name = "check", alias = "c")]
+    Check {
+        #[clap(value_parser)]
+        file: String,
+        /// Lints to leave unreported.
+        #[clap(short = 'A', long = "allow", value_name = "LINT")]
+        allow: Vec<String>,
+        /// Lints to report as warnings.
+        #[clap(short = 'W', long = "warn", value_name = "LINT")]
+        warn: Vec<String>,
+        /// Lints to report as errors.
+        #[clap(short = 'D', long = "deny", value_name = "LINT")]
+        deny: Vec<String>,
+        /// Leave out the runtime bounds checks of indices, as for release
+        /// builds.
+        #[clap(long)]
+        no_bounds_checks: bool,
+        /// Print where the program checks indices when it runs.
+        #[clap(long)]
+        traps: bool,
+    },
+    /// List the lints and their default levels.
+    #[clap(name = "lints")]
+    Lints,
+    #[clap(name = "test", alias = "t")]
+    Test {
+        #[clap(value_parser, default_value = "tests/ui")]
+        path: PathBuf,
+        /// Rewrite the annotations of failing files to match the output.
+        #[clap(long)]
+        bless: bool,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+enum TreeFormat {
+    /// An indented S-expression.
+    Sexpr,
+    /// A Graphviz graph.
+    Dot,
 }
 
 fn main() {
@@ -33,5 +88,94 @@ fn main() {
                 )
             }
         }
+        Cmd::Parse { file, format } => {
+            let source = std::fs::read_to_string(&file).expect("failed to read file");
+            let tokens = Lexer::new_file(&file, &source).tokenize();
+            let tree = MikParser::new(&tokens).parse();
+            match format {
+                TreeFormat::Sexpr => print!("{}", tree.dump(&tokens)),
+                TreeFormat::Dot => print!("{}", tree.dump_dot(&tokens)),
+            }
+        }
+        Cmd::Check {
+            file,
+            allow,
+            warn,
+            deny,
+            no_bounds_checks,
+            traps,
+        } => {
+            let source = std::fs::read_to_string(&file).expect("failed to read file");
+            let mut lints = LintRegistry::new();
+            let levels = [
+                (allow, LintLevel::Allow),
+                (warn, LintLevel::Warn),
+                (deny, LintLevel::Deny),
+            ];
+            for (names, level) in levels {
+                for name in names {
+                    if let Err(message) = lints.set_level(&name, level) {
+                        eprintln!("{}", message);
+                        std::process::exit(2);
+                    }
+                }
+            }
+            let compiled = test_runner::compile_with(&file, &source, lints, !no_bounds_checks);
+            let failed = compiled
+                .diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.level == Level::Error);
+            for diagnostic in compiled.diagnostics {
+                DefaultEmitter.emit(diagnostic);
+            }
+            if traps {
+                for trap in &compiled.traps {
+                    println!("{}", trap.message());
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        Cmd::Lints => {
+            for lint in LintRegistry::new().lints() {
+                let level = match lint.default {
+                    LintLevel::Allow => "allow",
+                    LintLevel::Warn => "warn",
+                    LintLevel::Deny => "deny",
+                };
+                println!("{:<20} {:<6} {}", lint.name, level, lint.description);
+            }
+        }
+        Cmd::Test { path, bless } => {
+            let reports = test_runner::run(&path, bless).expect("failed to run tests");
+            let mut failed = 0;
+            for report in &reports {
+                let status = if report.blessed {
+                    "blessed"
+                } else if !report.passed() {
+                    "FAILED"
+                } else if report.output_unchecked {
+                    "ok (output unchecked)"
+                } else {
+                    "ok"
+                };
+                println!(
+                    "[TEST] {:.<50} {}",
+                    report.path.display().to_string(),
+                    status
+                );
+                for mismatch in &report.mismatches {
+                    println!("    {}", mismatch);
+                }
+                if !report.passed() {
+                    failed += 1;
+                }
+            }
+            println!("{} passed, {} failed", reports.len() - failed, failed);
+            if failed > 0 {
+                std::process::exit(1);
+            }
+        }
     }
 }
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{cell::RefCell, rc::Rc};
+
+use crate::diagnostics::{self, Code, CollectingEmitter, Diagnostic, DiagnosticEmitter};
+use crate::lexer::Lexer;
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::parser::Parser;
+use crate::token::{SourceLocation, Span};
+use crate::tokenized_buffer::TokenizedBuffer;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct ModuleId(pub usize);
+
+#[derive(Debug)]
+pub struct Module {
+    /// The dotted name the module is imported by, such as `geometry.vec`.
+    pub name: String,
+    pub path: PathBuf,
+    pub source: String,
+    pub tree: ParseTree,
+    pub imports: Vec<Import>,
+}
+
+impl Module {
+    /// The tokens the tree refers to. Lexing is deterministic, so lexing the
+    /// source again yields the same buffer the module was parsed from.
+    pub fn tokens(&self) -> TokenizedBuffer<'_> {
+        let silent = Rc::new(RefCell::new(CollectingEmitter::new()));
+        Lexer::new(&self.source)
+            .with_emitter(silent)
+            .tokenize()
+            .with_file(self.path.to_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: String,
+    pub location: SourceLocation<'static>,
+    pub span: Span,
+    /// `None` when no file provides the module.
+    pub target: Option<ModuleId>,
+}
+
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+    modules: Vec<Module>,
+    order: Vec<ModuleId>,
+}
+
+impl ModuleGraph {
+    pub fn module(&self, id: ModuleId) -> &Module {
+        &self.modules[id.0]
+    }
+
+    pub fn modules(&self) -> impl Iterator<Item = (ModuleId, &Module)> {
+        self.modules
+            .iter()
+            .enumerate()
+            .map(|(id, module)| (ModuleId(id), module))
+    }
+
+    /// Every module after the modules it imports. Modules in a cycle are
+    /// ordered by when they were first reached.
+    pub fn order(&self) -> &[ModuleId] {
+        &self.order
+    }
+
+    pub fn find(&self, name: &str) -> Option<ModuleId> {
+        self.modules
+            .iter()
+            .position(|module| module.name == name)
+            .map(ModuleId)
+    }
+}
+
+/// Loads a module and everything it imports. The module `a.b` lives in
+/// `a/b.mik` below the root.
+pub struct ModuleLoader {
+    root: PathBuf,
+    diagnostics: Rc<RefCell<dyn DiagnosticEmitter>>,
+}
+
+impl ModuleLoader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            diagnostics: Rc::new(RefCell::new(diagnostics::DefaultEmitter)),
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
+        self
+    }
+
+    pub fn module_path(&self, name: &str) -> PathBuf {
+        let mut path = self.root.clone();
+        path.extend(name.split('.'));
+        path.set_extension("mik");
+        path
+    }
+
+    /// The module name a file below the root is expected to declare.
+    fn module_name(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?.with_extension("");
+        let segments: Option<Vec<&str>> = relative.iter().map(|s| s.to_str()).collect();
+        Some(segments?.join("."))
+    }
+
+    /// Loads `entry` and, transitively, its imports. Missing modules and
+    /// import cycles are reported as diagnostics; only failing to read a
+    /// file that exists is an error.
+    pub fn load(&self, entry: &Path) -> crate::Result<ModuleGraph> {
+        let mut graph = ModuleGraph::default();
+        let mut ids: HashMap<String, ModuleId> = HashMap::new();
+
+        let name = self.module_name(entry).unwrap_or_else(|| {
+            let stem = entry.file_stem().unwrap_or_default();
+            stem.to_string_lossy().into_owned()
+        });
+        let source = std::fs::read_to_string(entry)?;
+        ids.insert(name.clone(), ModuleId(0));
+        graph
+            .modules
+            .push(self.parse(name, entry.to_path_buf(), source));
+
+        let mut next = 0;
+        while next < graph.modules.len() {
+            for i in 0..graph.modules[next].imports.len() {
+                let import = graph.modules[next].imports[i].clone();
+                let target = match ids.get(&import.path) {
+                    Some(&id) => Some(id),
+                    None => self.load_import(&import, &mut graph)?,
+                };
+                if let Some(id) = target {
+                    ids.insert(graph.modules[id.0].name.clone(), id);
+                }
+                graph.modules[next].imports[i].target = target;
+            }
+            next += 1;
+        }
+
+        graph.order = self.order(&graph);
+        Ok(graph)
+    }
+
+    fn load_import(
+        &self,
+        import: &Import,
+        graph: &mut ModuleGraph,
+    ) -> crate::Result<Option<ModuleId>> {
+        let path = self.module_path(&import.path);
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                let message = format!(
+                    "module `{}` not found, expected it at `{}`",
+                    import.path,
+                    path.display()
+                );
+                self.emit_error(import.location.clone(), import.span, Code::M0001, message);
+                return Ok(None);
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let id = ModuleId(graph.modules.len());
+        graph
+            .modules
+            .push(self.parse(import.path.clone(), path, source));
+        Ok(Some(id))
+    }
+
+    fn parse(&self, name: String, path: PathBuf, source: String) -> Module {
+        let file = path.to_string_lossy().into_owned();
+        let tokens = Lexer::new_file(&file, &source)
+            .with_emitter(self.diagnostics.clone())
+            .tokenize();
+        let tree = Parser::new(&tokens)
+            .with_emitter(self.diagnostics.clone())
+            .parse();
+
+        let mut imports = vec![];
+        for root in tree.roots() {
+            match tree.kind_of(root) {
+                NodeKind::ModuleDecl => {
+                    let Some((header, location, span)) = module_path(&tree, &tokens, root) else {
+                        continue;
+                    };
+                    if header != name {
+                        let message = format!(
+                            "module `{}` is declared in the file for module `{}`",
+                            header, name
+                        );
+                        self.emit_error(location, span, Code::M0003, message);
+                    }
+                }
+                NodeKind::Import => {
+                    if let Some((path, location, span)) = module_path(&tree, &tokens, root) {
+                        imports.push(Import {
+                            path,
+                            location,
+                            span,
+                            target: None,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Module {
+            name,
+            path,
+            source,
+            tree,
+            imports,
+        }
+    }
+
+    /// Orders modules dependencies first, reporting each import that closes
+    /// a cycle.
+    fn order(&self, graph: &ModuleGraph) -> Vec<ModuleId> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            Visiting,
+            Done,
+        }
+
+        fn visit(
+            loader: &ModuleLoader,
+            graph: &ModuleGraph,
+            id: ModuleId,
+            states: &mut [State],
+            stack: &mut Vec<ModuleId>,
+            order: &mut Vec<ModuleId>,
+        ) {
+            states[id.0] = State::Visiting;
+            stack.push(id);
+            for import in &graph.module(id).imports {
+                let Some(target) = import.target else {
+                    continue;
+                };
+                match states[target.0] {
+                    State::New => visit(loader, graph, target, states, stack, order),
+                    State::Visiting => {
+                        let from = stack.iter().position(|&m| m == target).unwrap_or(0);
+                        let mut names: Vec<&str> = stack[from..]
+                            .iter()
+                            .map(|&m| graph.module(m).name.as_str())
+                            .collect();
+                        names.push(&graph.module(target).name);
+                        let message = format!("import cycle: {}", names.join(" -> "));
+                        loader.emit_error(
+                            import.location.clone(),
+                            import.span,
+                            Code::M0002,
+                            message,
+                        );
+                    }
+                    State::Done => {}
+                }
+            }
+            stack.pop();
+            states[id.0] = State::Done;
+            order.push(id);
+        }
+
+        let mut states = vec![State::New; graph.modules.len()];
+        let mut order = vec![];
+        for (id, _) in graph.modules() {
+            if states[id.0] == State::New {
+                visit(self, graph, id, &mut states, &mut vec![], &mut order);
+            }
+        }
+        order
+    }
+
+    fn emit_error(
+        &self,
+        location: SourceLocation<'static>,
+        span: Span,
+        code: Code,
+        message: String,
+    ) {
+        self.diagnostics.borrow_mut().emit(Diagnostic {
+            level: diagnostics::Level::Error,
+            code: Some(code),
+            message,
+            location,
+            span,
+        });
+    }
+}
+
+/// The dotted path of a module header or import, located at its first
+/// segment and spanning all of them. `None` if the path did not parse.
+fn module_path(
+    tree: &ParseTree,
+    tokens: &TokenizedBuffer,
+    node: Node,
+) -> Option<(String, SourceLocation<'static>, Span)> {
+    let path = tree.child(node, NodeKind::ModulePath)?;
+    if tree.has_error(path) {
+        return None;
+    }
+    let segments: Vec<Node> = tree.children(path).collect();
+    let first = tree.token_of(*segments.first()?);
+    let last = tree.token_of(*segments.last()?);
+    let span = Span::new(tokens.span_of(&first).start, tokens.span_of(&last).end);
+    let name: Vec<&str> = segments
+        .iter()
+        .map(|&segment| tree.text(tokens, segment))
+        .collect();
+    Some((
+        name.join("."),
+        tokens.location_of(&first).into_owned(),
+        span,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::ModuleLoader;
+    use crate::diagnostics::{Code, CollectingEmitter, Diagnostic};
+
+    /// A fresh directory holding `files`, given as `(path, source)` pairs.
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("mik-modules-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for (path, source) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        root
+    }
+
+    fn load(root: &PathBuf) -> (super::ModuleGraph, Vec<Diagnostic<'static>>) {
+        let emitter = Rc::new(RefCell::new(CollectingEmitter::new()));
+        let graph = ModuleLoader::new(root)
+            .with_emitter(emitter.clone())
+            .load(&root.join("main.mik"))
+            .unwrap();
+        let diagnostics = emitter.borrow_mut().take();
+        (graph, diagnostics)
+    }
+
+    #[test]
+    fn test_load() {
+        let root = tree(
+            "load",
+            &[
+                ("main.mik", "import geometry.vec.{Vec2}\nimport util as u\n"),
+                (
+                    "geometry/vec.mik",
+                    "module geometry.vec\nimport util\npub type Vec2 = (Real, Real)\n",
+                ),
+                ("util.mik", "pub func id(x: Int) -> Int { x }\n"),
+            ],
+        );
+        let (graph, diagnostics) = load(&root);
+        assert!(diagnostics.is_empty());
+        let names: Vec<&str> = graph
+            .order()
+            .iter()
+            .map(|&id| graph.module(id).name.as_str())
+            .collect();
+        assert_eq!(names, vec!["util", "geometry.vec", "main"]);
+        let main = graph.module(graph.find("main").unwrap());
+        assert_eq!(main.imports[1].target, graph.find("util"));
+        assert_eq!(main.tokens().file(), main.path.to_str());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let root = tree(
+            "errors",
+            &[
+                ("main.mik", "import a\nimport missing.mod\n"),
+                ("a.mik", "module a\nimport b\n"),
+                ("b.mik", "module c\nimport a\n"),
+            ],
+        );
+        let (graph, diagnostics) = load(&root);
+        let codes: Vec<Code> = diagnostics.iter().filter_map(|d| d.code).collect();
+        assert_eq!(codes, vec![Code::M0001, Code::M0003, Code::M0002]);
+        assert_eq!(diagnostics[1].location.row, 0);
+        assert_eq!(diagnostics[2].message, "import cycle: a -> b -> a");
+        assert_eq!(
+            graph.module(graph.find("main").unwrap()).imports[1].target,
+            None
+        );
+        assert_eq!(graph.order().len(), 3);
+
+        let loader = ModuleLoader::new(&root);
+        assert!(loader.load(&root.join("none.mik")).is_err());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
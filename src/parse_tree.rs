@@ -0,0 +1,590 @@
+use std::fmt::Write;
+use std::ops::Deref;
+
+use crate::token::Token;
+use crate::tokenized_buffer::TokenizedBuffer;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Node(pub usize);
+
+impl Deref for Node {
+    type Target = usize;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NodeKind {
+    IntLiteral,
+    RealLiteral,
+    StringLiteral,
+    BoolLiteral,
+    Name,
+    ParenExprStart,
+    ParenExpr,
+    TupleLiteral,
+    PrefixOperator,
+    InfixOperator,
+    Call,
+    IndexExpr,
+    GenericName,
+    FieldAccess,
+    StructLiteralStart,
+    StructLiteral,
+    FieldInit,
+    ClosureExpr,
+    ClosureParams,
+    FuncExpr,
+    ArrayLiteralStart,
+    ArrayLiteral,
+    ArrayRepeat,
+
+    MatchExpr,
+    MatchArmListStart,
+    MatchArmList,
+    MatchArm,
+    MatchGuard,
+    WildcardPattern,
+    BindingPattern,
+    LiteralPattern,
+    RangePattern,
+    TuplePatternStart,
+    TuplePattern,
+    PatternPath,
+    VariantPattern,
+    StructPatternStart,
+    StructPattern,
+    FieldPattern,
+    RestPattern,
+    AltPattern,
+
+    BlockStart,
+    Block,
+    ExprStmt,
+    Assign,
+    LetDecl,
+    VarDecl,
+    ConstDecl,
+    TypeAnnotation,
+    Initializer,
+    If,
+    Else,
+    While,
+    For,
+    Break,
+    Continue,
+    Return,
+
+    Pub,
+    Attribute,
+    AttributeName,
+    AttributeArgsStart,
+    AttributeArgs,
+    ModuleDecl,
+    Import,
+    ModulePath,
+    PathSegment,
+    ImportAlias,
+    ImportList,
+    ImportItem,
+
+    FuncDecl,
+    DeclName,
+    TypeParamListStart,
+    TypeParamList,
+    TypeParam,
+    TypeBound,
+    ParamListStart,
+    ParamList,
+    Param,
+    DefaultValue,
+    ReturnType,
+
+    TypeDecl,
+    StructTypeStart,
+    StructType,
+    Field,
+    EnumTypeStart,
+    EnumType,
+    Variant,
+    VariantPayload,
+    Discriminant,
+    ImplDecl,
+    ImplBodyStart,
+    ImplInterface,
+    InterfaceDecl,
+    InterfaceBodyStart,
+
+    TypeName,
+    GenericType,
+    TypeArgsStart,
+    TypeArgs,
+    PointerType,
+    ReferenceType,
+    ArrayTypeStart,
+    ArrayType,
+    SliceType,
+    FuncType,
+    FuncTypeParams,
+    TupleTypeStart,
+    TupleType,
+
+    /// A required node that is absent.
+    Missing,
+    /// Tokens skipped while recovering from an error.
+    Error,
+}
+
+impl std::fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The parser's output. Nodes are stored in postorder: every node comes
+/// right after its last child, and its subtree is the `subtree_size` nodes
+/// ending at it. Like `TokenizedBuffer`, each property lives in its own
+/// array indexed by the node.
+#[derive(Debug, Default)]
+pub struct ParseTree {
+    kinds: Vec<NodeKind>,
+    tokens: Vec<Token>,
+    subtree_sizes: Vec<u32>,
+    errors: Vec<bool>,
+    has_errors: bool,
+}
+
+impl ParseTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a node whose subtree starts at `subtree_start`, the number of
+    /// nodes in the tree before its first descendant was pushed.
+    pub fn push(
+        &mut self,
+        kind: NodeKind,
+        token: Token,
+        subtree_start: usize,
+        has_error: bool,
+    ) -> Node {
+        debug_assert!(subtree_start <= self.len());
+        let node = Node(self.len());
+        self.kinds.push(kind);
+        self.tokens.push(token);
+        self.subtree_sizes.push((node.0 - subtree_start + 1) as u32);
+        self.errors.push(has_error);
+        self.has_errors |= has_error;
+        node
+    }
+
+    pub fn len(&self) -> usize {
+        self.kinds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.has_errors
+    }
+
+    pub fn kind_of(&self, node: Node) -> NodeKind {
+        self.kinds[node.0]
+    }
+
+    pub fn token_of(&self, node: Node) -> Token {
+        self.tokens[node.0]
+    }
+
+    pub fn subtree_size_of(&self, node: Node) -> usize {
+        self.subtree_sizes[node.0] as usize
+    }
+
+    pub fn has_error(&self, node: Node) -> bool {
+        self.errors[node.0]
+    }
+
+    pub fn postorder(&self) -> impl DoubleEndedIterator<Item = Node> {
+        (0..self.len()).map(Node)
+    }
+
+    /// The subtree of `node` in postorder, ending with `node` itself.
+    pub fn postorder_of(&self, node: Node) -> impl DoubleEndedIterator<Item = Node> {
+        (node.0 + 1 - self.subtree_size_of(node)..=node.0).map(Node)
+    }
+
+    pub fn preorder(&self) -> Preorder<'_> {
+        Preorder {
+            tree: self,
+            stack: self.roots().rev().collect(),
+        }
+    }
+
+    pub fn preorder_of(&self, node: Node) -> Preorder<'_> {
+        Preorder {
+            tree: self,
+            stack: vec![node],
+        }
+    }
+
+    /// The nodes without a parent, in source order.
+    pub fn roots(&self) -> std::vec::IntoIter<Node> {
+        let mut roots: Vec<Node> = Siblings::new(self, self.len()).collect();
+        roots.reverse();
+        roots.into_iter()
+    }
+
+    /// The direct children of `node`, in source order.
+    pub fn children(&self, node: Node) -> Children<'_> {
+        Children {
+            tree: self,
+            start: node.0 + 1 - self.subtree_size_of(node),
+            end: node.0,
+            ahead: [Node(0); CHILDREN_AHEAD],
+            ahead_len: 0,
+        }
+    }
+
+    /// The direct children of `node`, last one first, as the postorder
+    /// layout gives them.
+    pub fn children_rev(&self, node: Node) -> Siblings<'_> {
+        let mut siblings = Siblings::new(self, node.0);
+        siblings.end = node.0 + 1 - self.subtree_size_of(node);
+        siblings
+    }
+
+    /// The first child of `node` of the given kind.
+    pub fn child(&self, node: Node, kind: NodeKind) -> Option<Node> {
+        self.children(node)
+            .find(|&child| self.kind_of(child) == kind)
+    }
+
+    /// The source text of the token of `node`.
+    pub fn text<'a>(&self, tokens: &TokenizedBuffer<'a>, node: Node) -> &'a str {
+        tokens.str_of(&self.token_of(node))
+    }
+
+    /// Finds the parent of `node` by walking down from its root, stepping
+    /// over the subtrees of siblings that do not contain it.
+    pub fn parent(&self, node: Node) -> Option<Node> {
+        let mut parent = None;
+        let mut siblings = Siblings::new(self, self.len());
+        loop {
+            // Siblings come last one first, so the first that starts at or
+            // before `node` is the one that contains it.
+            let ancestor = siblings
+                .find(|&sibling| sibling.0 + 1 - self.subtree_size_of(sibling) <= node.0)?;
+            if ancestor == node {
+                return parent;
+            }
+            parent = Some(ancestor);
+            siblings = self.children_rev(ancestor);
+        }
+    }
+
+    /// The parent of every node, indexed by node. For passes that climb the
+    /// tree often, where `parent` would walk down to each node.
+    pub fn parents(&self) -> Vec<Option<Node>> {
+        let mut parents = vec![None; self.len()];
+        for node in self.postorder() {
+            for child in self.children_rev(node) {
+                parents[child.0] = Some(node);
+            }
+        }
+        parents
+    }
+}
+
+impl ParseTree {
+    /// The tree as an indented S-expression, one node per line with its
+    /// token, 1-based position and byte span.
+    pub fn dump(&self, tokens: &TokenizedBuffer) -> String {
+        let mut out = String::new();
+        for root in self.roots() {
+            self.dump_node(tokens, root, 0, &mut out);
+            out.push('\n');
+        }
+        out
+    }
+
+    fn dump_node(&self, tokens: &TokenizedBuffer, node: Node, depth: usize, out: &mut String) {
+        let _ = write!(
+            out,
+            "{:indent$}({} {}",
+            "",
+            self.kind_of(node),
+            self.describe(tokens, node),
+            indent = depth * 2
+        );
+        for child in self.children(node) {
+            out.push('\n');
+            self.dump_node(tokens, child, depth + 1, out);
+        }
+        out.push(')');
+    }
+
+    /// The tree as a Graphviz graph, with nodes that have errors in red.
+    pub fn dump_dot(&self, tokens: &TokenizedBuffer) -> String {
+        let mut out =
+            String::from("digraph ParseTree {\n    node [shape=box, fontname=monospace];\n");
+        for node in self.postorder() {
+            // `\n` is a line break inside Graphviz labels.
+            let description = self.describe(tokens, node);
+            let label = format!(
+                "{}\\n{}",
+                self.kind_of(node),
+                description.replace('\\', "\\\\").replace('"', "\\\"")
+            );
+            let color = if self.has_error(node) {
+                ", color=red"
+            } else {
+                ""
+            };
+            let _ = writeln!(out, "    n{} [label=\"{}\"{}];", node.0, label, color);
+            for child in self.children(node) {
+                let _ = writeln!(out, "    n{} -> n{};", node.0, child.0);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn describe(&self, tokens: &TokenizedBuffer, node: Node) -> String {
+        let token = self.token_of(node);
+        let location = tokens.location_of(&token);
+        let span = tokens.span_of(&token);
+        let error = if self.has_error(node) { " error" } else { "" };
+        format!(
+            "{:?} {}:{} {}..{}{}",
+            tokens.str_of(&token),
+            location.row + 1,
+            location.col + 1,
+            span.start,
+            span.end,
+            error
+        )
+    }
+}
+
+/// Walks a run of siblings backwards, jumping over each one's subtree.
+/// How many children `Children` reads ahead at a time.
+const CHILDREN_AHEAD: usize = 16;
+
+/// The children of a node in source order. The layout only leads from a
+/// child to the one before it, so this walks back from the last child and
+/// keeps the first few it has not returned yet, without allocating.
+#[derive(Clone)]
+pub struct Children<'a> {
+    tree: &'a ParseTree,
+    /// Where the subtrees of the children not read ahead yet start.
+    start: usize,
+    /// One past the last child.
+    end: usize,
+    /// The children read ahead, the next one last.
+    ahead: [Node; CHILDREN_AHEAD],
+    ahead_len: usize,
+}
+
+impl Children<'_> {
+    fn read_ahead(&mut self) {
+        let mut siblings = Siblings::new(self.tree, self.end);
+        siblings.end = self.start;
+        // The last ones the walk reaches are the first in source order.
+        let mut seen = 0;
+        let mut ring = [Node(0); CHILDREN_AHEAD];
+        for sibling in siblings {
+            ring[seen % CHILDREN_AHEAD] = sibling;
+            seen += 1;
+        }
+        self.ahead_len = seen.min(CHILDREN_AHEAD);
+        for i in 0..self.ahead_len {
+            self.ahead[i] = ring[(seen - self.ahead_len + i) % CHILDREN_AHEAD];
+        }
+        if self.ahead_len > 0 {
+            self.start = self.ahead[0].0 + 1;
+        }
+    }
+}
+
+impl Iterator for Children<'_> {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ahead_len == 0 {
+            if self.start >= self.end {
+                return None;
+            }
+            self.read_ahead();
+        }
+        self.ahead_len -= 1;
+        Some(self.ahead[self.ahead_len])
+    }
+
+    fn last(self) -> Option<Self::Item> {
+        if self.start < self.end {
+            Some(Node(self.end - 1))
+        } else {
+            self.ahead[..self.ahead_len].first().copied()
+        }
+    }
+}
+
+pub struct Siblings<'a> {
+    tree: &'a ParseTree,
+    next: usize,
+    end: usize,
+}
+
+impl<'a> Siblings<'a> {
+    fn new(tree: &'a ParseTree, after_last: usize) -> Self {
+        Self {
+            tree,
+            next: after_last,
+            end: 0,
+        }
+    }
+}
+
+impl Iterator for Siblings<'_> {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next <= self.end {
+            return None;
+        }
+        let node = Node(self.next - 1);
+        self.next -= self.tree.subtree_size_of(node);
+        Some(node)
+    }
+}
+
+pub struct Preorder<'a> {
+    tree: &'a ParseTree,
+    stack: Vec<Node>,
+}
+
+impl Iterator for Preorder<'_> {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack.extend(self.tree.children_rev(node));
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 1 + 2 - 3.0 + 4, with tokens numbered by position.
+    fn tree() -> ParseTree {
+        let mut tree = ParseTree::new();
+        tree.push(NodeKind::IntLiteral, Token(0), 0, false);
+        tree.push(NodeKind::IntLiteral, Token(2), 1, false);
+        tree.push(NodeKind::InfixOperator, Token(1), 0, false);
+        tree.push(NodeKind::RealLiteral, Token(4), 3, false);
+        tree.push(NodeKind::InfixOperator, Token(3), 0, false);
+        tree.push(NodeKind::IntLiteral, Token(6), 5, true);
+        tree.push(NodeKind::InfixOperator, Token(5), 0, false);
+        tree
+    }
+
+    #[test]
+    fn test_children() {
+        let tree = tree();
+        assert_eq!(
+            tree.children(Node(6)).collect::<Vec<_>>(),
+            vec![Node(4), Node(5)]
+        );
+        assert_eq!(
+            tree.children(Node(4)).collect::<Vec<_>>(),
+            vec![Node(2), Node(3)]
+        );
+        assert_eq!(
+            tree.children_rev(Node(2)).collect::<Vec<_>>(),
+            vec![Node(1), Node(0)]
+        );
+        assert_eq!(tree.children(Node(0)).count(), 0);
+        assert_eq!(tree.roots().collect::<Vec<_>>(), vec![Node(6)]);
+        assert_eq!(tree.child(Node(4), NodeKind::RealLiteral), Some(Node(3)));
+        assert_eq!(tree.child(Node(6), NodeKind::RealLiteral), None);
+    }
+
+    #[test]
+    fn test_walks() {
+        let tree = tree();
+        let preorder: Vec<usize> = tree.preorder().map(|node| node.0).collect();
+        assert_eq!(preorder, vec![6, 4, 2, 0, 1, 3, 5]);
+        let subtree: Vec<usize> = tree.postorder_of(Node(4)).map(|node| node.0).collect();
+        assert_eq!(subtree, vec![0, 1, 2, 3, 4]);
+        assert_eq!(tree.subtree_size_of(Node(6)), 7);
+    }
+
+    #[test]
+    fn test_parent() {
+        let tree = tree();
+        assert_eq!(tree.parent(Node(0)), Some(Node(2)));
+        assert_eq!(tree.parent(Node(3)), Some(Node(4)));
+        assert_eq!(tree.parent(Node(5)), Some(Node(6)));
+        assert_eq!(tree.parent(Node(6)), None);
+        let parents = tree.parents();
+        for node in tree.postorder() {
+            assert_eq!(parents[node.0], tree.parent(node));
+        }
+        assert!(tree.has_error(Node(5)));
+        assert!(tree.has_errors());
+    }
+
+    #[test]
+    fn test_many_children() {
+        // A block of 40 pairs, each an operator over a literal, then a
+        // second root.
+        let mut tree = ParseTree::new();
+        for i in 0..40 {
+            tree.push(NodeKind::IntLiteral, Token(i), 2 * i, false);
+            tree.push(NodeKind::PrefixOperator, Token(i), 2 * i, false);
+        }
+        let block = tree.push(NodeKind::Block, Token(40), 0, false);
+        let root = tree.push(NodeKind::IntLiteral, Token(41), 81, false);
+        let pairs: Vec<Node> = (0..40).map(|i| Node(2 * i + 1)).collect();
+        assert_eq!(tree.children(block).collect::<Vec<_>>(), pairs);
+        assert_eq!(tree.children(block).last(), Some(Node(79)));
+        assert_eq!(tree.children(block).nth(20), Some(Node(41)));
+        assert_eq!(tree.children(block).skip(39).last(), Some(Node(79)));
+        assert_eq!(tree.children(root).last(), None);
+        assert_eq!(tree.parent(Node(40)), Some(Node(41)));
+        assert_eq!(tree.parent(Node(41)), Some(block));
+        assert_eq!(tree.parent(root), None);
+        assert_eq!(tree.parents()[40], Some(Node(41)));
+    }
+
+    #[test]
+    fn test_dump() {
+        let crate::fixture::Fixture { tokens, tree, .. } = crate::fixture::Fixture::parse("f(a $");
+        assert!(tree
+            .dump(&tokens)
+            .contains("\n    (Error \"$\" 1:5 4..5 error))"));
+
+        let tokens = crate::lexer::Lexer::new("x = 1").tokenize();
+        let tree = crate::parser::Parser::new(&tokens).parse();
+        assert_eq!(
+            tree.dump(&tokens),
+            "(Assign \"=\" 1:3 2..3\n  (Name \"x\" 1:1 0..1)\n  (IntLiteral \"1\" 1:5 4..5))\n"
+        );
+        assert_eq!(tree.text(&tokens, Node(0)), "x");
+        assert_eq!(tree.text(&tokens, Node(2)), "=");
+        assert_eq!(
+            tree.dump_dot(&tokens),
+            "digraph ParseTree {\n    node [shape=box, fontname=monospace];\n\
+             \x20   n0 [label=\"Name\\n\\\"x\\\" 1:1 0..1\"];\n\
+             \x20   n1 [label=\"IntLiteral\\n\\\"1\\\" 1:5 4..5\"];\n\
+             \x20   n2 [label=\"Assign\\n\\\"=\\\" 1:3 2..3\"];\n\
+             \x20   n2 -> n0;\n    n2 -> n1;\n}\n"
+        );
+    }
+}
@@ -1,11 +1,432 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::diagnostics::{self, Code, Diagnostic, DiagnosticEmitter};
+use crate::parse_tree::{NodeKind, ParseTree};
+use crate::token::{Token, TokenKind};
 use crate::tokenized_buffer::TokenizedBuffer;
 
+mod decl;
+mod expr;
+mod pattern;
+mod stmt;
+mod types;
+
 pub struct Parser<'a> {
+    diagnostics: Rc<RefCell<dyn DiagnosticEmitter>>,
     buf: &'a TokenizedBuffer<'a>,
+    tree: ParseTree,
+    pos: usize,
+    last: Option<Token>,
+    // Newlines end expressions only outside of parentheses.
+    nesting: usize,
+    // Set once the current statement has reported an error. Later errors in
+    // the same statement are almost always caused by the first, so they are
+    // not reported.
+    errored: bool,
+    // Set while parsing the expression before the block of an `if`,
+    // `while`, `for` or `match`, where `x {` starts the block rather than a
+    // struct literal.
+    no_struct_literal: bool,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(buf: &'a TokenizedBuffer<'a>) -> Self {
-        Self { buf }
+        Self {
+            diagnostics: Rc::new(RefCell::new(diagnostics::DefaultEmitter)),
+            buf,
+            tree: ParseTree::new(),
+            pos: 0,
+            last: None,
+            nesting: 0,
+            errored: false,
+            no_struct_literal: false,
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
+        self
+    }
+
+    pub fn parse(mut self) -> ParseTree {
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                TokenKind::Eof => break,
+                TokenKind::CloseBrace => {
+                    self.errored = false;
+                    let start = self.tree.len();
+                    let token = self.take();
+                    self.emit_error(token, Code::P0002, "unexpected `}`".to_string());
+                    self.tree.push(NodeKind::Error, token, start, true);
+                }
+                _ => self.parse_stmt(),
+            }
+        }
+        self.tree
+    }
+
+    /// Skips to the end of the current statement: the end of the line, a
+    /// declaration keyword, or a `;` or `}`. Braces opened along the way are
+    /// skipped up to their closing brace, so that a broken statement takes
+    /// its block with it, and a `;` inside brackets does not end it. The
+    /// skipped tokens become an `Error` node.
+    fn skip_line(&mut self) {
+        let start = self.tree.len();
+        let first = self.current();
+        let mut braces = 0;
+        let mut brackets = 0;
+        loop {
+            match self.peek() {
+                TokenKind::Eof => break,
+                TokenKind::VerticalWhitespace | TokenKind::CloseBrace if braces == 0 => break,
+                TokenKind::Semi if braces == 0 && brackets == 0 => break,
+                _ if braces == 0 && first != self.current() && self.at_decl_start() => break,
+                TokenKind::OpenBrace => braces += 1,
+                TokenKind::CloseBrace => braces -= 1,
+                TokenKind::OpenParen | TokenKind::OpenBracket => brackets += 1,
+                TokenKind::CloseParen | TokenKind::CloseBracket => {
+                    brackets = usize::saturating_sub(brackets, 1)
+                }
+                _ => {}
+            }
+            self.take();
+        }
+        self.push_error(start, first);
+    }
+
+    /// Skips tokens, newlines included, until one of `kinds` or the end of
+    /// the file. The skipped tokens become an `Error` node.
+    fn skip_until(&mut self, kinds: &[TokenKind]) {
+        let start = self.tree.len();
+        let first = self.current();
+        loop {
+            let kind = self.peek();
+            if kind == TokenKind::Eof || kinds.contains(&kind) {
+                break;
+            }
+            self.take();
+        }
+        self.push_error(start, first);
+    }
+
+    /// Records the tokens skipped since `first`, if any, so that the tree
+    /// still accounts for them.
+    fn push_error(&mut self, start: usize, first: Token) {
+        if self.current() != first {
+            self.tree.push(NodeKind::Error, first, start, true);
+        }
+    }
+
+    /// Stands in for a required node that is absent, so its parent keeps
+    /// the shape later passes expect. It takes the preceding token so that
+    /// it stays inside its parent's token range.
+    fn push_missing(&mut self) {
+        let token = self.last.unwrap_or_else(|| self.current());
+        let start = self.tree.len();
+        self.tree.push(NodeKind::Missing, token, start, true);
+    }
+
+    fn expect(&mut self, kind: TokenKind, expected: &str) -> Option<Token> {
+        if self.peek() == kind {
+            return Some(self.take());
+        }
+        let token = self.current();
+        self.emit_error(
+            token,
+            Code::P0001,
+            format!("expected {}, found {}", expected, self.describe(token)),
+        );
+        None
+    }
+
+    fn is_trivia(&self, kind: TokenKind) -> bool {
+        match kind {
+            TokenKind::HorizontalWhitespace | TokenKind::Comment => true,
+            TokenKind::VerticalWhitespace => self.nesting > 0,
+            _ => false,
+        }
+    }
+
+    fn skip_trivia(&mut self) {
+        while self.pos < self.buf.len() - 1 && self.is_trivia(self.buf.kind_of(&Token(self.pos))) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        loop {
+            self.skip_trivia();
+            if self.buf.kind_of(&Token(self.pos)) != TokenKind::VerticalWhitespace {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// The kind of the next token that is not a newline, without moving.
+    fn peek_past_newlines(&mut self) -> TokenKind {
+        let pos = self.pos;
+        self.skip_newlines();
+        let kind = self.peek();
+        self.pos = pos;
+        kind
+    }
+
+    /// Whether the next tokens start a declaration, where recovery can
+    /// resume. `func` and `type` also appear inside types and expressions,
+    /// so they only count when a name follows.
+    fn at_decl_start(&mut self) -> bool {
+        match self.peek() {
+            TokenKind::PubKeyword => true,
+            TokenKind::FuncKeyword
+            | TokenKind::TypeKeyword
+            | TokenKind::ImplKeyword
+            | TokenKind::InterfaceKeyword
+            | TokenKind::ImportKeyword
+            | TokenKind::ModuleKeyword => {
+                let pos = self.pos;
+                self.pos += 1;
+                let kind = self.peek();
+                self.pos = pos;
+                kind == TokenKind::Ident
+            }
+            _ => false,
+        }
+    }
+
+    fn current(&mut self) -> Token {
+        self.skip_trivia();
+        Token(self.pos)
+    }
+
+    fn peek(&mut self) -> TokenKind {
+        let token = self.current();
+        self.buf.kind_of(&token)
+    }
+
+    fn take(&mut self) -> Token {
+        let token = self.current();
+        if self.buf.kind_of(&token) != TokenKind::Eof {
+            self.pos += 1;
+        }
+        self.last = Some(token);
+        token
+    }
+
+    fn describe(&self, token: Token) -> String {
+        match self.buf.kind_of(&token) {
+            TokenKind::Eof => "end of file".to_string(),
+            TokenKind::VerticalWhitespace => "end of line".to_string(),
+            _ => format!("`{}`", self.buf.str_of(&token).escape_debug()),
+        }
+    }
+
+    fn emit_error(&mut self, token: Token, code: Code, message: String) {
+        if self.errored {
+            return;
+        }
+        self.errored = true;
+        // Invalid tokens were already reported by the lexer.
+        if self.buf.kind_of(&token) == TokenKind::Invalid {
+            return;
+        }
+        self.diagnostics.borrow_mut().emit(Diagnostic {
+            level: diagnostics::Level::Error,
+            code: Some(code),
+            message,
+            location: self.buf.location_of(&token),
+            span: self.buf.span_of(&token),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::Code;
+    use crate::fixture::Fixture;
+    use crate::lexer::Lexer;
+    use crate::parse_tree::{Node, ParseTree};
+
+    /// The tree as nested node kinds, for grammar tests where the token
+    /// text alone would be ambiguous.
+    pub(super) fn kinds(source: &str) -> (String, Vec<Code>) {
+        let fixture = Fixture::parse(source);
+        let codes = fixture.codes();
+        let Fixture { tree, .. } = fixture;
+        let roots: Vec<String> = tree.roots().map(|root| kind_sexpr(&tree, root)).collect();
+        (roots.join(" "), codes)
+    }
+
+    fn kind_sexpr(tree: &ParseTree, node: Node) -> String {
+        let children: Vec<String> = tree
+            .children(node)
+            .map(|child| kind_sexpr(tree, child))
+            .collect();
+        let kind = tree.kind_of(node).to_string();
+        if children.is_empty() {
+            kind
+        } else {
+            format!("({} {})", kind, children.join(" "))
+        }
+    }
+
+    fn parse(source: &str) -> (String, Vec<Code>) {
+        let fixture = Fixture::parse(source);
+        let codes = fixture.codes();
+        let Fixture { tokens, tree, .. } = fixture;
+        // Expression tests only care about what is inside the statements.
+        let roots: Vec<String> = tree
+            .roots()
+            .flat_map(|root| tree.children(root))
+            .map(|expr| sexpr(&tree, &tokens, expr))
+            .collect();
+        (roots.join(" "), codes)
+    }
+
+    fn sexpr(
+        tree: &ParseTree,
+        tokens: &crate::tokenized_buffer::TokenizedBuffer,
+        node: Node,
+    ) -> String {
+        let text = tree.text(tokens, node);
+        let children: Vec<String> = tree
+            .children(node)
+            .map(|child| sexpr(tree, tokens, child))
+            .collect();
+        if children.is_empty() {
+            text.to_string()
+        } else {
+            format!("({} {})", text, children.join(" "))
+        }
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(parse("1.0 + 9.732e-6").0, "(+ 1.0 9.732e-6)");
+        assert_eq!(parse("1 + 2 * 3 - 4").0, "(- (+ 1 (* 2 3)) 4)");
+        assert_eq!(parse("a || b && !c == d").0, "(|| a (&& b (== (! c) d)))");
+        assert_eq!(parse("-(1 + x) * 2").0, "(* (- () ( (+ 1 x))) 2)");
+        assert_eq!(parse("-f(1, g(x),) * 2").0, "(* (- () f 1 () g x))) 2)");
+    }
+
+    #[test]
+    fn test_newlines() {
+        assert_eq!(parse("1 +\n2\n3").0, "(+ 1 2) 3");
+        assert_eq!(parse("(1\n+ 2)").0, "() ( (+ 1 2))");
+    }
+
+    #[test]
+    fn test_errors() {
+        let (tree, codes) = parse("1 + * 2");
+        assert_eq!(tree, "(+ 1 (* + 2))");
+        assert_eq!(codes, vec![Code::P0003]);
+        assert_eq!(parse("1 2").1, vec![Code::P0002]);
+        assert_eq!(parse("(1 + 2").1, vec![Code::P0001]);
+        assert_eq!(parse("a < b < c").1, vec![Code::P0004]);
+        assert_eq!(parse("1 +").1, vec![Code::P0003]);
+        assert_eq!(parse("f(1 2)").1, vec![Code::P0001]);
+        assert_eq!(parse("f(1, ;)").1, vec![Code::P0003]);
+    }
+
+    #[test]
+    fn test_recovery_nodes() {
+        let (tree, codes) = kinds("let x = 1 2 3\n}\nfunc f() {}");
+        assert_eq!(codes, vec![Code::P0002, Code::P0002]);
+        assert_eq!(
+            tree,
+            "(LetDecl DeclName (Initializer IntLiteral)) Error Error \
+             (FuncDecl DeclName (ParamList ParamListStart) (Block BlockStart))"
+        );
+        // Recovery stops at the next declaration, even on the same line.
+        let (tree, codes) = kinds("let x = ) func f() {}");
+        assert_eq!(codes, vec![Code::P0003]);
+        assert!(tree.ends_with("(FuncDecl DeclName (ParamList ParamListStart) (Block BlockStart))"));
+    }
+
+    #[test]
+    fn test_cascade() {
+        // One typo, one error, however confused the rest of the line is.
+        assert_eq!(kinds("let = (1 + ]) * [").1, vec![Code::P0001]);
+        assert_eq!(kinds("f(1 2 3 4)").1, vec![Code::P0001]);
+        // Separate statements still report separately.
+        assert_eq!(kinds("1 +;\n2 +").1, vec![Code::P0003, Code::P0003]);
+    }
+
+    const SOURCES: &[&str] = &[
+        include_str!("../tests/ui/parser/attributes.mik"),
+        include_str!("../tests/ui/parser/closures.mik"),
+        include_str!("../tests/ui/parser/expressions.mik"),
+        include_str!("../tests/ui/parser/functions.mik"),
+        include_str!("../tests/ui/parser/generics.mik"),
+        include_str!("../tests/ui/parser/modules.mik"),
+        include_str!("../tests/ui/parser/patterns.mik"),
+        include_str!("../tests/ui/parser/statements.mik"),
+        include_str!("../tests/ui/parser/structs.mik"),
+        include_str!("../tests/ui/parser/types.mik"),
+    ];
+
+    /// Parses `source`, checking that the tree is well formed, and returns
+    /// the number of diagnostics.
+    fn parse_checked(source: &str) -> usize {
+        let fixture = Fixture::parse(source);
+        let Fixture { tokens, tree, .. } = &fixture;
+        let covered: usize = tree.roots().map(|root| tree.subtree_size_of(root)).sum();
+        assert_eq!(covered, tree.len(), "{}", source);
+        for node in tree.postorder() {
+            assert!(tree.subtree_size_of(node) <= *node + 1, "{}", source);
+            assert!(tree.token_of(node).0 < tokens.len(), "{}", source);
+        }
+        let count = fixture.take().len();
+        assert!(count == 0 || tree.has_errors(), "{}", source);
+        count
+    }
+
+    #[test]
+    fn test_truncated() {
+        for source in SOURCES {
+            let mut end = 0;
+            while end <= source.len() {
+                if source.is_char_boundary(end) {
+                    parse_checked(&source[..end]);
+                }
+                end += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_deletions() {
+        // A fixed xorshift sequence keeps failures reproducible.
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        for source in SOURCES {
+            let tokens = Lexer::new(source).tokenize();
+            let texts: Vec<&str> = (0..tokens.len())
+                .map(|i| tokens.str_of(&crate::token::Token(i)))
+                .collect();
+            let base = parse_checked(source);
+            for round in 0..200 {
+                let deletions = 1 + round % 3;
+                let mut kept = texts.clone();
+                let mut deleted = vec![];
+                for _ in 0..deletions {
+                    let i = next(kept.len());
+                    deleted.push(kept.remove(i));
+                }
+                let count = parse_checked(&kept.concat());
+                // A single deletion is reported once, or twice when what is
+                // left still reads as a broken construct, unless it unbalances
+                // braces and the blocks after it no longer line up.
+                if deleted.len() == 1 && !matches!(deleted[0], "{" | "}") {
+                    assert!(count <= base + 2, "{}", kept.concat());
+                }
+            }
+        }
     }
 }
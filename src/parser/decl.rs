@@ -0,0 +1,1007 @@
+use super::Parser;
+use crate::diagnostics::Code;
+use crate::parse_tree::NodeKind;
+use crate::token::TokenKind;
+
+impl Parser<'_> {
+    /// Parses the declaration starting at the current token. Its node starts
+    /// at `start`, so that modifiers pushed before it become its children.
+    /// Returns whether the declaration ended with a closing brace.
+    pub(super) fn parse_decl(&mut self, start: usize) -> bool {
+        match self.peek() {
+            TokenKind::FuncKeyword => {
+                self.parse_func_decl(start);
+                true
+            }
+            TokenKind::TypeKeyword => self.parse_type_decl(start),
+            TokenKind::ImplKeyword => self.parse_impl(start),
+            TokenKind::InterfaceKeyword => self.parse_interface(start),
+            TokenKind::ImportKeyword => {
+                self.parse_import(start);
+                false
+            }
+            TokenKind::ModuleKeyword => {
+                self.parse_module_decl(start);
+                false
+            }
+            _ => {
+                self.parse_binding(start);
+                false
+            }
+        }
+    }
+
+    /// Attributes such as `@inline` or `@deprecated("use g")`, on the same
+    /// line or the lines before the declaration they apply to. They become
+    /// its first children.
+    pub(super) fn parse_attributed_decl(&mut self) -> bool {
+        let start = self.tree.len();
+        while self.peek() == TokenKind::At {
+            self.parse_attribute();
+            self.skip_newlines();
+        }
+        match self.peek() {
+            TokenKind::PubKeyword => self.parse_pub(start),
+            TokenKind::FuncKeyword
+            | TokenKind::TypeKeyword
+            | TokenKind::ImplKeyword
+            | TokenKind::InterfaceKeyword
+            | TokenKind::LetKeyword
+            | TokenKind::VarKeyword
+            | TokenKind::ConstKeyword
+            | TokenKind::ImportKeyword
+            | TokenKind::ModuleKeyword => self.parse_decl(start),
+            _ => {
+                let token = self.current();
+                self.emit_error(
+                    token,
+                    Code::P0007,
+                    format!(
+                        "expected a declaration after attributes, found {}",
+                        self.describe(token)
+                    ),
+                );
+                self.push_missing();
+                false
+            }
+        }
+    }
+
+    /// `@name` or `@name(args)`, where arguments are expressions.
+    fn parse_attribute(&mut self) {
+        let start = self.tree.len();
+        let at = self.take();
+        let name_start = self.tree.len();
+        if self.peek() != TokenKind::Ident {
+            let token = self.current();
+            self.emit_error(
+                token,
+                Code::P0001,
+                format!(
+                    "expected attribute name after `@`, found {}",
+                    self.describe(token)
+                ),
+            );
+            self.push_missing();
+            self.tree.push(NodeKind::Attribute, at, start, true);
+            return;
+        }
+        let name = self.take();
+        self.tree
+            .push(NodeKind::AttributeName, name, name_start, false);
+
+        let mut found = true;
+        if self.peek() == TokenKind::OpenParen {
+            let args_start = self.tree.len();
+            let open = self.take();
+            self.tree
+                .push(NodeKind::AttributeArgsStart, open, args_start, false);
+            self.nesting += 1;
+            while self.peek() != TokenKind::CloseParen {
+                self.parse_expr();
+                match self.peek() {
+                    TokenKind::Comma => {
+                        self.take();
+                    }
+                    TokenKind::CloseParen => break,
+                    _ => {
+                        let token = self.current();
+                        self.emit_error(
+                            token,
+                            Code::P0001,
+                            format!("expected `,` or `)`, found {}", self.describe(token)),
+                        );
+                        found = false;
+                        break;
+                    }
+                }
+            }
+            self.nesting -= 1;
+            if !found {
+                self.skip_until(&[TokenKind::CloseParen, TokenKind::VerticalWhitespace]);
+            }
+            let close = match self.peek() {
+                TokenKind::CloseParen => self.take(),
+                _ => self.last.unwrap_or(open),
+            };
+            self.tree
+                .push(NodeKind::AttributeArgs, close, args_start, !found);
+        }
+        self.tree.push(NodeKind::Attribute, at, start, !found);
+    }
+
+    /// `pub` followed by the declaration it exports. The declaration's node
+    /// starts at `start`, which may include attributes before `pub`.
+    pub(super) fn parse_pub(&mut self, start: usize) -> bool {
+        let pub_start = self.tree.len();
+        let token = self.take();
+        let exportable = matches!(
+            self.peek(),
+            TokenKind::FuncKeyword
+                | TokenKind::TypeKeyword
+                | TokenKind::InterfaceKeyword
+                | TokenKind::LetKeyword
+                | TokenKind::VarKeyword
+                | TokenKind::ConstKeyword
+                | TokenKind::ImportKeyword
+        );
+        self.tree.push(NodeKind::Pub, token, pub_start, !exportable);
+        if exportable {
+            return self.parse_decl(start);
+        }
+        let token = self.current();
+        self.emit_error(
+            token,
+            Code::P0007,
+            format!(
+                "expected a declaration after `pub`, found {}",
+                self.describe(token)
+            ),
+        );
+        self.skip_line();
+        false
+    }
+
+    /// `func name(a: Int, b: Real = 1.0) -> Real { ... }`
+    ///
+    /// A malformed signature is skipped up to the body, which is still
+    /// parsed so that its closing brace ends the declaration.
+    fn parse_func_decl(&mut self, start: usize) {
+        let func = self.take();
+
+        let synchronized = self.parse_func_signature();
+        if !synchronized {
+            // Resume at the body or the next declaration. A `func` that is
+            // not followed by a name is a function type in the signature.
+            let skip_start = self.tree.len();
+            let first = self.current();
+            while !matches!(
+                self.peek(),
+                TokenKind::OpenBrace | TokenKind::CloseBrace | TokenKind::Eof
+            ) && !self.at_decl_start()
+            {
+                self.take();
+            }
+            self.push_error(skip_start, first);
+        }
+
+        if synchronized && self.peek() != TokenKind::OpenBrace {
+            let token = self.current();
+            self.emit_error(
+                token,
+                Code::P0001,
+                format!(
+                    "expected `{{` to start the function body, found {}",
+                    self.describe(token)
+                ),
+            );
+            // Resume at a `{` later on the same line, as in `func f() Int {`.
+            self.skip_until(&[
+                TokenKind::OpenBrace,
+                TokenKind::CloseBrace,
+                TokenKind::VerticalWhitespace,
+            ]);
+        }
+        if self.peek() == TokenKind::OpenBrace {
+            self.parse_block();
+        } else {
+            self.push_missing();
+        }
+        self.tree
+            .push(NodeKind::FuncDecl, func, start, !synchronized);
+    }
+
+    /// Returns whether the signature ended where the body should start.
+    fn parse_func_signature(&mut self) -> bool {
+        self.parse_decl_name("function name")
+            && self.parse_type_params()
+            && self.parse_param_list()
+            && self.parse_return_type()
+    }
+
+    /// Optional type parameters after a declaration's name, as in
+    /// `[T, U: Ord + Show]`. Returns whether they were well formed.
+    fn parse_type_params(&mut self) -> bool {
+        if self.peek() != TokenKind::OpenBracket {
+            return true;
+        }
+        let start = self.tree.len();
+        let open = self.take();
+        self.tree
+            .push(NodeKind::TypeParamListStart, open, start, false);
+        self.nesting += 1;
+        // An empty list is reported as a missing parameter name.
+        let mut found = self.peek() != TokenKind::CloseBracket || self.parse_type_param();
+        while found && self.peek() != TokenKind::CloseBracket {
+            if !self.parse_type_param() {
+                found = false;
+                break;
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseBracket => break,
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected `,` or `]`, found {}", self.describe(token)),
+                    );
+                    found = false;
+                    break;
+                }
+            }
+        }
+        self.nesting -= 1;
+        if !found {
+            self.skip_until(&[
+                TokenKind::CloseBracket,
+                TokenKind::OpenParen,
+                TokenKind::Equal,
+                TokenKind::VerticalWhitespace,
+            ]);
+        }
+        let close = match self.peek() {
+            TokenKind::CloseBracket => self.take(),
+            _ => self.last.unwrap_or(open),
+        };
+        self.tree
+            .push(NodeKind::TypeParamList, close, start, !found);
+        found
+    }
+
+    /// `T`, or `T: A + B` with the interfaces it has to implement.
+    fn parse_type_param(&mut self) -> bool {
+        let start = self.tree.len();
+        let token = self.current();
+        let mut found = self.parse_decl_name("type parameter name");
+        if found && self.peek() == TokenKind::Colon {
+            let bound_start = self.tree.len();
+            let colon = self.take();
+            found = self.parse_type();
+            while found && self.peek() == TokenKind::Plus {
+                self.take();
+                found = self.parse_type();
+            }
+            self.tree
+                .push(NodeKind::TypeBound, colon, bound_start, !found);
+        }
+        self.tree.push(NodeKind::TypeParam, token, start, !found);
+        found
+    }
+
+    /// An optional `-> T`, returning whether it was well formed.
+    pub(super) fn parse_return_type(&mut self) -> bool {
+        if self.peek() != TokenKind::Arrow {
+            return true;
+        }
+        let start = self.tree.len();
+        let arrow = self.take();
+        let found = self.parse_type();
+        self.tree.push(NodeKind::ReturnType, arrow, start, !found);
+        found
+    }
+
+    pub(super) fn parse_decl_name(&mut self, expected: &str) -> bool {
+        if self.peek() == TokenKind::Ident {
+            let start = self.tree.len();
+            let name = self.take();
+            self.tree.push(NodeKind::DeclName, name, start, false);
+            return true;
+        }
+        let token = self.current();
+        self.emit_error(
+            token,
+            Code::P0001,
+            format!("expected {}, found {}", expected, self.describe(token)),
+        );
+        self.push_missing();
+        false
+    }
+
+    /// Returns whether the list was closed by its `)`; malformed parameters
+    /// are skipped up to the next `,` or `)`.
+    pub(super) fn parse_param_list(&mut self) -> bool {
+        let start = self.tree.len();
+        let Some(open) = self.expect(TokenKind::OpenParen, "`(`") else {
+            return false;
+        };
+        self.tree.push(NodeKind::ParamListStart, open, start, false);
+
+        self.nesting += 1;
+        let mut has_error = false;
+        let mut seen_default = false;
+        while self.peek() != TokenKind::CloseParen {
+            if !self.parse_param(&mut seen_default) {
+                has_error = true;
+                self.skip_until(&[
+                    TokenKind::Comma,
+                    TokenKind::CloseParen,
+                    TokenKind::OpenBrace,
+                ]);
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseParen => break,
+                _ => {
+                    if !has_error {
+                        let token = self.current();
+                        self.emit_error(
+                            token,
+                            Code::P0001,
+                            format!("expected `,` or `)`, found {}", self.describe(token)),
+                        );
+                    }
+                    has_error = true;
+                    break;
+                }
+            }
+        }
+        self.nesting -= 1;
+
+        if self.peek() == TokenKind::CloseParen {
+            let close = self.take();
+            self.tree.push(NodeKind::ParamList, close, start, has_error);
+            true
+        } else {
+            self.tree.push(NodeKind::ParamList, open, start, true);
+            false
+        }
+    }
+
+    /// `name: Type` or `name: Type = default`. Once a parameter has a
+    /// default value, every later one needs one too. A method's receiver is
+    /// written `self`, and has the type of its `impl`.
+    fn parse_param(&mut self, seen_default: &mut bool) -> bool {
+        let start = self.tree.len();
+        let token = self.current();
+        let named = self.parse_decl_name("parameter name");
+        if named && self.buf.str_of(&token) == "self" && self.peek() != TokenKind::Colon {
+            self.tree.push(NodeKind::Param, token, start, false);
+            return true;
+        }
+        let found = named
+            && self
+                .expect(TokenKind::Colon, "`:` and the parameter type")
+                .is_some()
+            && self.parse_type();
+
+        let mut has_error = !found;
+        if found && self.peek() == TokenKind::Equal {
+            let default_start = self.tree.len();
+            let equal = self.take();
+            self.parse_expr();
+            self.tree
+                .push(NodeKind::DefaultValue, equal, default_start, false);
+            *seen_default = true;
+        } else if found && *seen_default {
+            self.emit_error(
+                token,
+                Code::P0006,
+                format!(
+                    "parameter `{}` needs a default value, as an earlier parameter has one",
+                    self.buf.str_of(&token)
+                ),
+            );
+            has_error = true;
+        }
+        self.tree.push(NodeKind::Param, token, start, has_error);
+        found
+    }
+
+    /// `type Name = struct { ... }`, `type Name = enum { ... }`, or an
+    /// alias `type Name = Type`. Returns whether the declaration ended with
+    /// a closing brace.
+    fn parse_type_decl(&mut self, start: usize) -> bool {
+        let keyword = self.take();
+        let mut found = self.parse_decl_name("type name")
+            && self.parse_type_params()
+            && self.expect(TokenKind::Equal, "`=`").is_some();
+        let ends_with_block = found
+            && matches!(
+                self.peek(),
+                TokenKind::StructKeyword | TokenKind::EnumKeyword
+            );
+        if found {
+            found = match self.peek() {
+                TokenKind::StructKeyword => self.parse_member_list(
+                    NodeKind::StructTypeStart,
+                    NodeKind::StructType,
+                    Self::parse_field,
+                ),
+                TokenKind::EnumKeyword => self.parse_member_list(
+                    NodeKind::EnumTypeStart,
+                    NodeKind::EnumType,
+                    Self::parse_variant,
+                ),
+                _ => self.parse_type(),
+            };
+        }
+        if !found {
+            self.skip_line();
+        }
+        self.tree.push(NodeKind::TypeDecl, keyword, start, !found);
+        ends_with_block
+    }
+
+    /// `impl Type { ... }`, declaring the methods of a type, or
+    /// `impl Interface for Type { ... }`, implementing an interface.
+    fn parse_impl(&mut self, start: usize) -> bool {
+        let keyword = self.take();
+        let interface_start = self.tree.len();
+        let mut found = self.parse_type();
+        if found && self.peek() == TokenKind::ForKeyword {
+            let token = self.take();
+            self.tree
+                .push(NodeKind::ImplInterface, token, interface_start, false);
+            found = self.parse_type();
+        }
+        let body_start = self.tree.len();
+        let open = match found {
+            true => self.expect(TokenKind::OpenBrace, "`{`"),
+            false => None,
+        };
+        let Some(open) = open else {
+            self.skip_line();
+            self.tree.push(NodeKind::ImplDecl, keyword, start, true);
+            return false;
+        };
+        self.tree
+            .push(NodeKind::ImplBodyStart, open, body_start, false);
+
+        let nesting = std::mem::replace(&mut self.nesting, 0);
+        let mut has_error = false;
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                TokenKind::CloseBrace | TokenKind::Eof => break,
+                TokenKind::FuncKeyword | TokenKind::PubKeyword | TokenKind::At => self.parse_stmt(),
+                _ => {
+                    self.errored = false;
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected a method, found {}", self.describe(token)),
+                    );
+                    has_error = true;
+                    self.skip_line();
+                    if self.peek() == TokenKind::Semi {
+                        self.take();
+                    }
+                }
+            }
+        }
+        self.nesting = nesting;
+
+        let closed = self.expect(TokenKind::CloseBrace, "`}`").is_some();
+        self.tree
+            .push(NodeKind::ImplDecl, keyword, start, has_error || !closed);
+        closed
+    }
+
+    /// `interface Name { func method(self) -> Type ... }`, the methods a
+    /// type has to provide to implement the interface, without bodies.
+    fn parse_interface(&mut self, start: usize) -> bool {
+        let keyword = self.take();
+        let found = self.parse_decl_name("interface name");
+        let body_start = self.tree.len();
+        let open = match found {
+            true => self.expect(TokenKind::OpenBrace, "`{`"),
+            false => None,
+        };
+        let Some(open) = open else {
+            self.skip_line();
+            self.tree
+                .push(NodeKind::InterfaceDecl, keyword, start, true);
+            return false;
+        };
+        self.tree
+            .push(NodeKind::InterfaceBodyStart, open, body_start, false);
+
+        let nesting = std::mem::replace(&mut self.nesting, 0);
+        let mut has_error = false;
+        loop {
+            self.skip_newlines();
+            self.errored = false;
+            match self.peek() {
+                TokenKind::CloseBrace | TokenKind::Eof => break,
+                TokenKind::FuncKeyword => {
+                    has_error |= !self.parse_method_signature();
+                }
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!(
+                            "expected a method signature, found {}",
+                            self.describe(token)
+                        ),
+                    );
+                    has_error = true;
+                    self.skip_line();
+                }
+            }
+        }
+        self.nesting = nesting;
+
+        let closed = self.expect(TokenKind::CloseBrace, "`}`").is_some();
+        self.tree.push(
+            NodeKind::InterfaceDecl,
+            keyword,
+            start,
+            has_error || !closed,
+        );
+        closed
+    }
+
+    /// `func name(self, ...) -> Type`, which ends at the end of its line.
+    fn parse_method_signature(&mut self) -> bool {
+        let start = self.tree.len();
+        let func = self.take();
+        let mut found = self.parse_func_signature();
+        if found && self.peek() == TokenKind::OpenBrace {
+            let token = self.current();
+            self.emit_error(
+                token,
+                Code::P0001,
+                "expected end of line, found `{`, methods of an interface have no body".to_string(),
+            );
+            // The body is skipped as a whole so that its lines are not
+            // taken for methods.
+            self.parse_block();
+            found = false;
+        } else if found {
+            self.expect_stmt_end();
+        } else {
+            self.skip_line();
+        }
+        self.tree.push(NodeKind::FuncDecl, func, start, !found);
+        found
+    }
+
+    /// The body of a `struct` or `enum`: members separated by commas or
+    /// newlines. Returns whether the closing brace was found.
+    fn parse_member_list(
+        &mut self,
+        start_kind: NodeKind,
+        kind: NodeKind,
+        parse_member: fn(&mut Self) -> bool,
+    ) -> bool {
+        self.take();
+        let start = self.tree.len();
+        let Some(open) = self.expect(TokenKind::OpenBrace, "`{`") else {
+            return false;
+        };
+        self.tree.push(start_kind, open, start, false);
+
+        let nesting = std::mem::replace(&mut self.nesting, 0);
+        let mut has_error = false;
+        loop {
+            self.skip_newlines();
+            if matches!(self.peek(), TokenKind::CloseBrace | TokenKind::Eof) {
+                break;
+            }
+            // Like statements, each member reports its own first error.
+            self.errored = false;
+            if !parse_member(self) {
+                // Skipping to the end of the line keeps the brackets of a
+                // broken member from confusing the rest of the list.
+                has_error = true;
+                self.skip_line();
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::VerticalWhitespace | TokenKind::CloseBrace | TokenKind::Eof => {}
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected `,` or `}}`, found {}", self.describe(token)),
+                    );
+                    has_error = true;
+                    self.skip_line();
+                }
+            }
+        }
+        self.nesting = nesting;
+
+        match self.expect(TokenKind::CloseBrace, "`}`") {
+            Some(close) => {
+                self.tree.push(kind, close, start, has_error);
+                true
+            }
+            None => {
+                let last = self.last.unwrap_or(open);
+                self.tree.push(kind, last, start, true);
+                false
+            }
+        }
+    }
+
+    /// `name: Type`
+    fn parse_field(&mut self) -> bool {
+        let start = self.tree.len();
+        let token = self.current();
+        let found = self.parse_decl_name("field name")
+            && self
+                .expect(TokenKind::Colon, "`:` and the field type")
+                .is_some()
+            && self.parse_type();
+        self.tree.push(NodeKind::Field, token, start, !found);
+        found
+    }
+
+    /// `Name`, `Name(A, B)` with a payload, or `Name = value` with an
+    /// explicit discriminant.
+    fn parse_variant(&mut self) -> bool {
+        let start = self.tree.len();
+        let token = self.current();
+        let mut found = self.parse_decl_name("variant name");
+        if found && self.peek() == TokenKind::OpenParen {
+            let payload_start = self.tree.len();
+            let open = self.take();
+            found = self.parse_type_list();
+            let close = self.last.unwrap_or(open);
+            self.tree
+                .push(NodeKind::VariantPayload, close, payload_start, !found);
+        }
+        if found && self.peek() == TokenKind::Equal {
+            let discriminant_start = self.tree.len();
+            let equal = self.take();
+            self.parse_expr();
+            self.tree
+                .push(NodeKind::Discriminant, equal, discriminant_start, false);
+        }
+        self.tree.push(NodeKind::Variant, token, start, !found);
+        found
+    }
+
+    /// `module geometry.vec`, naming the module a file defines.
+    fn parse_module_decl(&mut self, start: usize) {
+        let keyword = self.take();
+        let found = self.parse_module_path();
+        if !found {
+            self.skip_line();
+        }
+        self.tree.push(NodeKind::ModuleDecl, keyword, start, !found);
+    }
+
+    /// `import geometry.vec`, `import geometry.vec as v` or, importing
+    /// single declarations, `import geometry.vec.{Vec2, dot as d}`.
+    fn parse_import(&mut self, start: usize) {
+        let keyword = self.take();
+        let mut found = self.parse_module_path();
+        if found {
+            if self.peek() == TokenKind::OpenBrace {
+                found = self.parse_import_list();
+            } else if self.peek() == TokenKind::AsKeyword {
+                found = self.parse_import_alias();
+            }
+        }
+        if !found {
+            self.skip_line();
+        }
+        self.tree.push(NodeKind::Import, keyword, start, !found);
+    }
+
+    /// Dot separated names. A trailing `.` is left for a following `{`.
+    fn parse_module_path(&mut self) -> bool {
+        let start = self.tree.len();
+        loop {
+            let segment_start = self.tree.len();
+            if self.peek() != TokenKind::Ident {
+                let token = self.current();
+                self.emit_error(
+                    token,
+                    Code::P0001,
+                    format!("expected module name, found {}", self.describe(token)),
+                );
+                self.push_missing();
+                let last = self.last.unwrap_or(token);
+                self.tree.push(NodeKind::ModulePath, last, start, true);
+                return false;
+            }
+            let segment = self.take();
+            self.tree
+                .push(NodeKind::PathSegment, segment, segment_start, false);
+            if self.peek() != TokenKind::Dot {
+                break;
+            }
+            self.take();
+            if self.peek() == TokenKind::OpenBrace {
+                break;
+            }
+        }
+        let last = self.last.unwrap_or_else(|| self.current());
+        self.tree.push(NodeKind::ModulePath, last, start, false);
+        true
+    }
+
+    fn parse_import_alias(&mut self) -> bool {
+        let start = self.tree.len();
+        let keyword = self.take();
+        let found = self.parse_decl_name("a name after `as`");
+        self.tree
+            .push(NodeKind::ImportAlias, keyword, start, !found);
+        found
+    }
+
+    fn parse_import_list(&mut self) -> bool {
+        let start = self.tree.len();
+        self.take();
+        self.nesting += 1;
+        let mut found = true;
+        while self.peek() != TokenKind::CloseBrace {
+            let item_start = self.tree.len();
+            let name = self.current();
+            if self.peek() != TokenKind::Ident {
+                self.emit_error(
+                    name,
+                    Code::P0001,
+                    format!("expected a name to import, found {}", self.describe(name)),
+                );
+                found = false;
+                break;
+            }
+            self.take();
+            let mut item_found = true;
+            if self.peek() == TokenKind::AsKeyword {
+                item_found = self.parse_import_alias();
+            }
+            self.tree
+                .push(NodeKind::ImportItem, name, item_start, !item_found);
+            found &= item_found;
+
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseBrace => break,
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected `,` or `}}`, found {}", self.describe(token)),
+                    );
+                    found = false;
+                    break;
+                }
+            }
+        }
+        self.nesting -= 1;
+
+        if found {
+            let close = self.take();
+            self.tree.push(NodeKind::ImportList, close, start, false);
+            return true;
+        }
+        // Skip the rest of the list, up to and including its `}`.
+        self.skip_line();
+        if self.peek() == TokenKind::CloseBrace {
+            self.take();
+        }
+        let token = self.last.unwrap_or_else(|| self.current());
+        self.tree.push(NodeKind::ImportList, token, start, true);
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::Code;
+    use crate::parser::test::kinds;
+
+    #[test]
+    fn test_func_decl() {
+        let (tree, codes) = kinds("func add(a: Int, b: Real = 1.0,) -> Real {\n  a + b\n}");
+        assert_eq!(
+            tree,
+            "(FuncDecl DeclName (ParamList ParamListStart (Param DeclName TypeName) \
+             (Param DeclName TypeName (DefaultValue RealLiteral))) (ReturnType TypeName) \
+             (Block BlockStart (ExprStmt (InfixOperator Name Name))))"
+        );
+        assert!(codes.is_empty());
+        assert_eq!(
+            kinds("func main() {}").0,
+            "(FuncDecl DeclName (ParamList ParamListStart) (Block BlockStart))"
+        );
+    }
+
+    #[test]
+    fn test_malformed_signatures() {
+        assert_eq!(kinds("func (a: Int) {}").1, vec![Code::P0001]);
+        assert_eq!(kinds("func f(a Int) {}").1, vec![Code::P0001]);
+        assert_eq!(kinds("func f(a: 1) {}").1, vec![Code::P0005]);
+        assert_eq!(kinds("func f(a: Int = 1, b: Int) {}").1, vec![Code::P0006]);
+        assert_eq!(kinds("func f(a: Int,, b: Int) {}").1, vec![Code::P0001]);
+        assert_eq!(kinds("func f() -> {}").1, vec![Code::P0005]);
+        assert_eq!(kinds("func f()\n1").1, vec![Code::P0001]);
+    }
+
+    #[test]
+    fn test_recovery() {
+        // The body of a broken signature is still parsed, and the next
+        // declaration starts after its closing brace.
+        let (tree, codes) = kinds("func f(a: ) {\n  1 +\n}\nfunc g() {}");
+        assert_eq!(codes, vec![Code::P0005, Code::P0003]);
+        assert!(tree.ends_with("(FuncDecl DeclName (ParamList ParamListStart) (Block BlockStart))"));
+    }
+
+    #[test]
+    fn test_type_decls() {
+        assert_eq!(
+            kinds("type Point = struct { x: Real, y: Real }").0,
+            "(TypeDecl DeclName (StructType StructTypeStart (Field DeclName TypeName) \
+             (Field DeclName TypeName)))"
+        );
+        assert_eq!(
+            kinds("type Shape = enum {\n  Circle(Real)\n  Rect(Real, Real),\n  Empty = 4\n}").0,
+            "(TypeDecl DeclName (EnumType EnumTypeStart (Variant DeclName (VariantPayload TypeName)) \
+             (Variant DeclName (VariantPayload TypeName TypeName)) (Variant DeclName (Discriminant IntLiteral))))"
+        );
+        assert_eq!(
+            kinds("type Callback = func(Int) -> *Real").0,
+            "(TypeDecl DeclName (FuncType (FuncTypeParams TypeName) (ReturnType (PointerType TypeName))))"
+        );
+    }
+
+    #[test]
+    fn test_type_decl_errors() {
+        assert_eq!(kinds("type = Int").1, vec![Code::P0001]);
+        assert_eq!(kinds("type P struct {}").1, vec![Code::P0001]);
+        assert_eq!(
+            kinds("type P = struct { x Real, y: Real }").1,
+            vec![Code::P0001]
+        );
+        assert_eq!(
+            kinds("type P = struct { x: Real y: Real }").1,
+            vec![Code::P0001]
+        );
+        assert_eq!(kinds("type E = enum { A(Int B }").1, vec![Code::P0001]);
+        assert_eq!(kinds("type A = Int 1").1, vec![Code::P0002]);
+    }
+
+    #[test]
+    fn test_modules() {
+        assert_eq!(
+            kinds("module geometry.vec;").0,
+            "(ModuleDecl (ModulePath PathSegment PathSegment))"
+        );
+        assert_eq!(
+            kinds("import geometry.vec as v\nimport io").0,
+            "(Import (ModulePath PathSegment PathSegment) (ImportAlias DeclName)) \
+             (Import (ModulePath PathSegment))"
+        );
+        assert_eq!(
+            kinds("import geometry.vec.{Vec2, dot as d,}").0,
+            "(Import (ModulePath PathSegment PathSegment) (ImportList ImportItem \
+             (ImportItem (ImportAlias DeclName))))"
+        );
+        assert_eq!(
+            kinds("pub func f() {}\npub type T = Int").0,
+            "(FuncDecl Pub DeclName (ParamList ParamListStart) (Block BlockStart)) \
+             (TypeDecl Pub DeclName TypeName)"
+        );
+    }
+
+    #[test]
+    fn test_module_errors() {
+        assert_eq!(kinds("module;").1, vec![Code::P0001]);
+        assert_eq!(kinds("import a.;").1, vec![Code::P0001]);
+        assert_eq!(kinds("import a.{b c}").1, vec![Code::P0001]);
+        assert_eq!(kinds("import a as").1, vec![Code::P0001]);
+        let (tree, codes) = kinds("pub 1 + 2\nlet x = 1");
+        assert_eq!(codes, vec![Code::P0007]);
+        assert_eq!(
+            tree,
+            "Pub Error (LetDecl DeclName (Initializer IntLiteral))"
+        );
+    }
+    #[test]
+    fn test_attributes() {
+        assert_eq!(
+            kinds("@inline func f() {}").0,
+            "(FuncDecl (Attribute AttributeName) DeclName (ParamList ParamListStart) \
+             (Block BlockStart))"
+        );
+        assert_eq!(
+            kinds("@deprecated(\"old\")\n@test\npub type T = Int").0,
+            "(TypeDecl (Attribute AttributeName (AttributeArgs AttributeArgsStart StringLiteral)) \
+             (Attribute AttributeName) Pub DeclName TypeName)"
+        );
+        assert_eq!(
+            kinds("@deprecated interface I {}\n@allow(unused_variables) impl P {}").0,
+            "(InterfaceDecl (Attribute AttributeName) DeclName InterfaceBodyStart) \
+             (ImplDecl (Attribute AttributeName (AttributeArgs AttributeArgsStart Name)) \
+             TypeName ImplBodyStart)"
+        );
+        assert_eq!(kinds("@ func f() {}").1, vec![Code::P0001]);
+        assert_eq!(kinds("@a(1 2) func f() {}").1, vec![Code::P0001]);
+        let (tree, codes) = kinds("@test\n1 + 2");
+        assert_eq!(codes, vec![Code::P0007]);
+        assert_eq!(tree, "(Attribute AttributeName) Missing Error");
+    }
+    #[test]
+    fn test_generics() {
+        assert_eq!(
+            kinds("func max[T: Ord](a: T, b: T) -> T { a }").0,
+            "(FuncDecl DeclName (TypeParamList TypeParamListStart (TypeParam DeclName \
+             (TypeBound TypeName))) (ParamList ParamListStart (Param DeclName TypeName) \
+             (Param DeclName TypeName)) (ReturnType TypeName) (Block BlockStart (ExprStmt Name)))"
+        );
+        assert_eq!(
+            kinds("type Pair[A, B] = (A, B)").0,
+            "(TypeDecl DeclName (TypeParamList TypeParamListStart (TypeParam DeclName) \
+             (TypeParam DeclName)) (TupleType TupleTypeStart TypeName TypeName))"
+        );
+        assert_eq!(kinds("type T[] = Int").1, vec![Code::P0001]);
+        assert_eq!(kinds("func f[A B]() {}").1, vec![Code::P0001]);
+        assert_eq!(kinds("func f[A: +]() {}").1, vec![Code::P0005]);
+    }
+
+    #[test]
+    fn test_impl() {
+        assert_eq!(
+            kinds("impl Point {\n    func len(self) -> Real { 0.0 }\n    pub func origin() {}\n}")
+                .0,
+            "(ImplDecl TypeName ImplBodyStart (FuncDecl DeclName (ParamList ParamListStart \
+             (Param DeclName)) (ReturnType TypeName) (Block BlockStart (ExprStmt RealLiteral))) \
+             (FuncDecl Pub DeclName (ParamList ParamListStart) (Block BlockStart)))"
+        );
+        assert_eq!(kinds("impl P {\n    let x = 1\n}").1, vec![Code::P0001]);
+        assert_eq!(kinds("impl {}").1, vec![Code::P0005]);
+        assert_eq!(
+            kinds("impl Show for Point {}").0,
+            "(ImplDecl (ImplInterface TypeName) TypeName ImplBodyStart)"
+        );
+    }
+
+    #[test]
+    fn test_interface() {
+        assert_eq!(
+            kinds(
+                "interface Show {\n    func show(self) -> String\n    func id(self); func f()\n}"
+            )
+            .0,
+            "(InterfaceDecl DeclName InterfaceBodyStart (FuncDecl DeclName (ParamList \
+             ParamListStart (Param DeclName)) (ReturnType TypeName)) (FuncDecl DeclName \
+             (ParamList ParamListStart (Param DeclName))) (FuncDecl DeclName (ParamList \
+             ParamListStart)))"
+        );
+        assert_eq!(
+            kinds("interface Show {\n    func show(self) { 1 }\n    let x = 1\n}").1,
+            vec![Code::P0001, Code::P0001]
+        );
+        assert_eq!(kinds("interface {}").1, vec![Code::P0001]);
+    }
+}
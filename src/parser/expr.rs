@@ -0,0 +1,745 @@
+use super::Parser;
+use crate::diagnostics::Code;
+use crate::parse_tree::NodeKind;
+use crate::token::TokenKind;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Associativity {
+    Left,
+    None,
+}
+
+// Binding power of prefix operators, above every infix operator.
+const PREFIX_PRECEDENCE: u8 = 8;
+
+fn infix_precedence(kind: TokenKind) -> Option<(u8, Associativity)> {
+    use TokenKind::*;
+    match kind {
+        DotDot => Some((1, Associativity::None)),
+        PipePipe => Some((2, Associativity::Left)),
+        AmpAmp => Some((3, Associativity::Left)),
+        EqualEqual | BangEqual | Less | LessEqual | Greater | GreaterEqual => {
+            Some((4, Associativity::None))
+        }
+        LessLess | GreaterGreater => Some((5, Associativity::Left)),
+        Plus | Minus => Some((6, Associativity::Left)),
+        Star | Slash | Percent => Some((7, Associativity::Left)),
+        _ => None,
+    }
+}
+
+impl Parser<'_> {
+    pub fn parse_expr(&mut self) {
+        self.parse_expr_with(0);
+    }
+
+    /// The expression before the block of an `if`, `while`, `for` or
+    /// `match`. Struct literals have to be parenthesized there, as in
+    /// `if p == (Point { x: 0, y: 0 }) { ... }`.
+    pub(super) fn parse_condition(&mut self) {
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, true);
+        self.parse_expr();
+        self.no_struct_literal = no_struct_literal;
+    }
+
+    fn parse_expr_with(&mut self, min_precedence: u8) {
+        let start = self.tree.len();
+        self.parse_prefix();
+
+        let mut chained: Option<u8> = None;
+        while let Some((precedence, associativity)) = infix_precedence(self.peek()) {
+            if precedence < min_precedence {
+                break;
+            }
+            let op = self.take();
+            let has_error = chained == Some(precedence);
+            if has_error {
+                let operators = match self.buf.kind_of(&op) {
+                    TokenKind::DotDot => "range",
+                    _ => "comparison",
+                };
+                self.emit_error(
+                    op,
+                    Code::P0004,
+                    format!(
+                        "{} operators cannot be chained, add parentheses around one side of `{}`",
+                        operators,
+                        self.buf.str_of(&op)
+                    ),
+                );
+            }
+            chained = match associativity {
+                Associativity::None => Some(precedence),
+                Associativity::Left => None,
+            };
+
+            self.skip_newlines();
+            self.parse_expr_with(precedence + 1);
+            self.tree
+                .push(NodeKind::InfixOperator, op, start, has_error);
+        }
+    }
+
+    fn parse_prefix(&mut self) {
+        let start = self.tree.len();
+        match self.peek() {
+            TokenKind::Minus | TokenKind::Bang => {
+                let op = self.take();
+                self.skip_newlines();
+                self.parse_expr_with(PREFIX_PRECEDENCE);
+                self.tree.push(NodeKind::PrefixOperator, op, start, false);
+            }
+            _ => {
+                self.parse_primary();
+                self.parse_postfix(start);
+            }
+        }
+    }
+
+    /// Calls, indexing and field accesses bind tighter than any operator.
+    /// They have to start on the line of what they apply to, as a newline
+    /// ends the statement.
+    fn parse_postfix(&mut self, start: usize) {
+        loop {
+            match self.peek() {
+                TokenKind::OpenParen => self.parse_call(start),
+                TokenKind::OpenBracket => self.parse_index(start),
+                TokenKind::Dot => {
+                    let dot = self.take();
+                    match self.expect(TokenKind::Ident, "field or method name") {
+                        Some(name) => {
+                            self.tree.push(NodeKind::FieldAccess, name, start, false);
+                        }
+                        None => {
+                            self.tree.push(NodeKind::FieldAccess, dot, start, true);
+                            break;
+                        }
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn parse_call(&mut self, start: usize) {
+        self.take();
+        self.nesting += 1;
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, false);
+        let mut has_error = false;
+        while self.peek() != TokenKind::CloseParen {
+            let pos = self.pos;
+            self.parse_expr();
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseParen => break,
+                _ => {
+                    // An argument that could not be parsed at all has
+                    // already been reported.
+                    if self.pos != pos {
+                        let token = self.current();
+                        self.emit_error(
+                            token,
+                            Code::P0001,
+                            format!("expected `,` or `)`, found {}", self.describe(token)),
+                        );
+                    }
+                    has_error = true;
+                    self.skip_until(&[
+                        TokenKind::CloseParen,
+                        TokenKind::OpenBrace,
+                        TokenKind::CloseBrace,
+                    ]);
+                    break;
+                }
+            }
+        }
+        self.nesting -= 1;
+        self.no_struct_literal = no_struct_literal;
+
+        let close = match self.peek() {
+            TokenKind::CloseParen => self.take(),
+            _ => {
+                if !has_error {
+                    self.expect(TokenKind::CloseParen, "`)`");
+                }
+                has_error = true;
+                self.last.unwrap_or_else(|| self.current())
+            }
+        };
+        self.tree.push(NodeKind::Call, close, start, has_error);
+    }
+
+    /// `a[i]`, or `a[1..3]` for a slice.
+    fn parse_index(&mut self, start: usize) {
+        let open = self.take();
+        self.nesting += 1;
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, false);
+        self.parse_expr();
+        self.no_struct_literal = no_struct_literal;
+        self.nesting -= 1;
+        match self.expect(TokenKind::CloseBracket, "`]`") {
+            Some(close) => self.tree.push(NodeKind::IndexExpr, close, start, false),
+            None => self.tree.push(NodeKind::IndexExpr, open, start, true),
+        };
+    }
+
+    /// `[1, 2, 3]`, or `[0; 4]` for four zeros.
+    fn parse_array_literal(&mut self) {
+        let start = self.tree.len();
+        let open = self.take();
+        self.tree
+            .push(NodeKind::ArrayLiteralStart, open, start, false);
+        self.nesting += 1;
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, false);
+        let mut kind = NodeKind::ArrayLiteral;
+        let mut has_error = false;
+        let mut first = true;
+        while self.peek() != TokenKind::CloseBracket {
+            let pos = self.pos;
+            self.parse_expr();
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                    first = false;
+                }
+                TokenKind::Semi if first => {
+                    self.take();
+                    self.parse_expr();
+                    kind = NodeKind::ArrayRepeat;
+                    if self.peek() != TokenKind::CloseBracket {
+                        let token = self.current();
+                        self.emit_error(
+                            token,
+                            Code::P0001,
+                            format!("expected `]`, found {}", self.describe(token)),
+                        );
+                        has_error = true;
+                        self.skip_until(&[TokenKind::CloseBracket, TokenKind::CloseBrace]);
+                    }
+                    break;
+                }
+                TokenKind::CloseBracket => break,
+                _ => {
+                    if self.pos != pos {
+                        let token = self.current();
+                        self.emit_error(
+                            token,
+                            Code::P0001,
+                            format!("expected `,` or `]`, found {}", self.describe(token)),
+                        );
+                    }
+                    has_error = true;
+                    self.skip_until(&[TokenKind::CloseBracket, TokenKind::CloseBrace]);
+                    break;
+                }
+            }
+        }
+        self.no_struct_literal = no_struct_literal;
+        self.nesting -= 1;
+        let close = match self.peek() {
+            TokenKind::CloseBracket => self.take(),
+            _ => {
+                if !has_error {
+                    self.expect(TokenKind::CloseBracket, "`]`");
+                }
+                has_error = true;
+                self.last.unwrap_or_else(|| self.current())
+            }
+        };
+        self.tree.push(kind, close, start, has_error);
+    }
+
+    /// `(a)`, or a tuple: `(a, b)`, `(a,)`, or `()` for the unit value.
+    fn parse_paren_expr(&mut self) {
+        let start = self.tree.len();
+        let open = self.take();
+        self.tree.push(NodeKind::ParenExprStart, open, start, false);
+        self.nesting += 1;
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, false);
+        let mut kind = NodeKind::TupleLiteral;
+        let mut has_error = false;
+        let mut first = true;
+        while self.peek() != TokenKind::CloseParen {
+            let pos = self.pos;
+            self.parse_expr();
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                    first = false;
+                }
+                TokenKind::CloseParen => {
+                    if first {
+                        kind = NodeKind::ParenExpr;
+                    }
+                    break;
+                }
+                _ => {
+                    if self.pos != pos {
+                        let token = self.current();
+                        self.emit_error(
+                            token,
+                            Code::P0001,
+                            format!("expected `,` or `)`, found {}", self.describe(token)),
+                        );
+                    }
+                    if first {
+                        kind = NodeKind::ParenExpr;
+                    }
+                    has_error = true;
+                    self.skip_until(&[TokenKind::CloseParen, TokenKind::CloseBrace]);
+                    break;
+                }
+            }
+        }
+        self.no_struct_literal = no_struct_literal;
+        self.nesting -= 1;
+        let close = match self.peek() {
+            TokenKind::CloseParen => self.take(),
+            _ => {
+                if !has_error {
+                    self.expect(TokenKind::CloseParen, "`)`");
+                }
+                has_error = true;
+                self.last.unwrap_or_else(|| self.current())
+            }
+        };
+        self.tree.push(kind, close, start, has_error);
+    }
+
+    fn parse_primary(&mut self) {
+        let start = self.tree.len();
+        let kind = match self.peek() {
+            TokenKind::IntLiteral => NodeKind::IntLiteral,
+            TokenKind::RealLiteral => NodeKind::RealLiteral,
+            TokenKind::StringLiteral => NodeKind::StringLiteral,
+            TokenKind::TrueKeyword | TokenKind::FalseKeyword => NodeKind::BoolLiteral,
+            TokenKind::Ident if !self.no_struct_literal && self.struct_literal_ahead() => {
+                self.parse_struct_literal();
+                return;
+            }
+            TokenKind::Ident => {
+                let name = self.take();
+                self.tree.push(NodeKind::Name, name, start, false);
+                if self.peek() == TokenKind::OpenBracket && self.type_args_then_call() {
+                    let found = self.parse_type_args();
+                    self.tree.push(NodeKind::GenericName, name, start, !found);
+                }
+                return;
+            }
+            TokenKind::OpenParen => {
+                self.parse_paren_expr();
+                return;
+            }
+            TokenKind::OpenBracket => {
+                self.parse_array_literal();
+                return;
+            }
+            TokenKind::MatchKeyword => {
+                self.parse_match();
+                return;
+            }
+            TokenKind::Pipe | TokenKind::PipePipe => {
+                self.parse_closure();
+                return;
+            }
+            TokenKind::FuncKeyword => {
+                self.parse_func_expr();
+                return;
+            }
+            TokenKind::ReturnKeyword => {
+                self.parse_return();
+                return;
+            }
+            TokenKind::Invalid => {
+                self.take();
+                self.push_missing();
+                return;
+            }
+            _ => {
+                let token = self.current();
+                self.emit_error(
+                    token,
+                    Code::P0003,
+                    format!("expected expression, found {}", self.describe(token)),
+                );
+                self.push_missing();
+                return;
+            }
+        };
+        let token = self.take();
+        self.tree.push(kind, token, start, false);
+    }
+
+    /// `return` with an optional value. It is an expression, so that it can
+    /// end a `match` arm, as in `None => return 0`.
+    pub(super) fn parse_return(&mut self) {
+        let start = self.tree.len();
+        let keyword = self.take();
+        if !matches!(
+            self.peek(),
+            TokenKind::Semi
+                | TokenKind::VerticalWhitespace
+                | TokenKind::CloseBrace
+                | TokenKind::CloseParen
+                | TokenKind::CloseBracket
+                | TokenKind::Comma
+                | TokenKind::Eof
+        ) {
+            self.parse_expr();
+        }
+        self.tree.push(NodeKind::Return, keyword, start, false);
+    }
+
+    /// Whether the `[` after a name closes with a `]` that is followed by
+    /// `(`, as in `max[Int](a, b)`. Only then are the brackets read as type
+    /// arguments.
+    fn type_args_then_call(&mut self) -> bool {
+        let (pos, last) = (self.pos, self.last);
+        let mut depth = 0;
+        let call = loop {
+            match self.peek() {
+                TokenKind::OpenBracket => depth += 1,
+                TokenKind::CloseBracket if depth == 1 => {
+                    self.take();
+                    break self.peek() == TokenKind::OpenParen;
+                }
+                TokenKind::CloseBracket => depth -= 1,
+                TokenKind::Eof | TokenKind::VerticalWhitespace => break false,
+                _ => {}
+            }
+            self.take();
+        };
+        (self.pos, self.last) = (pos, last);
+        call
+    }
+
+    /// Whether a name is followed by `{` and then `}`, `field:`, or a field
+    /// name followed by `,` or `}`, which only struct literals can be.
+    fn struct_literal_ahead(&mut self) -> bool {
+        let (pos, last, nesting) = (self.pos, self.last, self.nesting);
+        self.take();
+        let literal = self.peek() == TokenKind::OpenBrace && {
+            self.take();
+            self.nesting += 1;
+            match self.peek() {
+                TokenKind::CloseBrace => true,
+                TokenKind::Ident => {
+                    self.take();
+                    matches!(
+                        self.peek(),
+                        TokenKind::Colon | TokenKind::Comma | TokenKind::CloseBrace
+                    )
+                }
+                _ => false,
+            }
+        };
+        (self.pos, self.last, self.nesting) = (pos, last, nesting);
+        literal
+    }
+
+    /// `Point { x: 1.0, y }`, where a field without a value is initialized
+    /// from the variable of the same name.
+    fn parse_struct_literal(&mut self) {
+        let start = self.tree.len();
+        let name = self.take();
+        self.tree.push(NodeKind::TypeName, name, start, false);
+        let fields_start = self.tree.len();
+        let open = self.take();
+        self.tree
+            .push(NodeKind::StructLiteralStart, open, fields_start, false);
+        self.nesting += 1;
+        let no_struct_literal = std::mem::replace(&mut self.no_struct_literal, false);
+        let mut has_error = false;
+        while self.peek() != TokenKind::CloseBrace {
+            if !self.parse_field_init() {
+                has_error = true;
+                self.skip_until(&[TokenKind::Comma, TokenKind::CloseBrace]);
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseBrace => break,
+                _ => {
+                    if !has_error {
+                        let token = self.current();
+                        self.emit_error(
+                            token,
+                            Code::P0001,
+                            format!("expected `,` or `}}`, found {}", self.describe(token)),
+                        );
+                    }
+                    has_error = true;
+                    self.skip_until(&[TokenKind::CloseBrace]);
+                    break;
+                }
+            }
+        }
+        self.no_struct_literal = no_struct_literal;
+        self.nesting -= 1;
+        match self.peek() {
+            TokenKind::CloseBrace => {
+                let close = self.take();
+                self.tree
+                    .push(NodeKind::StructLiteral, close, start, has_error);
+            }
+            _ => {
+                if !has_error {
+                    self.expect(TokenKind::CloseBrace, "`}`");
+                }
+                self.tree.push(NodeKind::StructLiteral, open, start, true);
+            }
+        }
+    }
+
+    /// `name: value`, or `name` for `name: name`.
+    fn parse_field_init(&mut self) -> bool {
+        let start = self.tree.len();
+        let Some(name) = self.expect(TokenKind::Ident, "field name") else {
+            return false;
+        };
+        if self.peek() == TokenKind::Colon {
+            self.take();
+            self.parse_expr();
+        } else {
+            self.tree.push(NodeKind::Name, name, start, false);
+        }
+        self.tree.push(NodeKind::FieldInit, name, start, false);
+        true
+    }
+
+    /// `|a: Int, b| a + b`, where parameter types may be left to inference.
+    /// The body may be a block, `|a| { a }`, which a return type requires:
+    /// `|a: Int| -> Int { a }`.
+    fn parse_closure(&mut self) {
+        let start = self.tree.len();
+        let open = self.take();
+        let params_start = self.tree.len();
+        let mut found = true;
+        if self.buf.kind_of(&open) == TokenKind::PipePipe {
+            self.tree
+                .push(NodeKind::ClosureParams, open, params_start, false);
+        } else {
+            self.nesting += 1;
+            while self.peek() != TokenKind::Pipe {
+                if !self.parse_closure_param() {
+                    found = false;
+                    break;
+                }
+                match self.peek() {
+                    TokenKind::Comma => {
+                        self.take();
+                    }
+                    TokenKind::Pipe => break,
+                    _ => {
+                        let token = self.current();
+                        self.emit_error(
+                            token,
+                            Code::P0001,
+                            format!("expected `,` or `|`, found {}", self.describe(token)),
+                        );
+                        found = false;
+                        break;
+                    }
+                }
+            }
+            self.nesting -= 1;
+            let close = match found {
+                true => Some(self.take()),
+                false => None,
+            };
+            let token = close.unwrap_or_else(|| self.last.unwrap_or(open));
+            self.tree
+                .push(NodeKind::ClosureParams, token, params_start, !found);
+        }
+
+        if found && self.peek() == TokenKind::Arrow {
+            found = match self.parse_return_type() {
+                true => self.parse_func_body("closure"),
+                false => {
+                    self.push_missing();
+                    false
+                }
+            };
+        } else if found && self.peek() == TokenKind::OpenBrace {
+            self.parse_block();
+        } else if found {
+            self.skip_newlines();
+            self.parse_expr();
+        } else {
+            self.push_missing();
+        }
+        self.tree.push(NodeKind::ClosureExpr, open, start, !found);
+    }
+
+    /// A parameter of a closure, with an optional type.
+    fn parse_closure_param(&mut self) -> bool {
+        let start = self.tree.len();
+        let token = self.current();
+        let mut found = self.parse_decl_name("parameter name");
+        if found && self.peek() == TokenKind::Colon {
+            self.take();
+            found = self.parse_type();
+        }
+        self.tree.push(NodeKind::Param, token, start, !found);
+        found
+    }
+
+    /// `func(a: Int) -> Int { a }`, a function without a name. At the start
+    /// of a statement `func` always begins a declaration.
+    fn parse_func_expr(&mut self) {
+        let start = self.tree.len();
+        let keyword = self.take();
+        let found = match self.parse_param_list() && self.parse_return_type() {
+            true => self.parse_func_body("function"),
+            false => {
+                self.push_missing();
+                false
+            }
+        };
+        self.tree.push(NodeKind::FuncExpr, keyword, start, !found);
+    }
+
+    /// The block after an anonymous function's return type.
+    fn parse_func_body(&mut self, what: &str) -> bool {
+        if self.peek() == TokenKind::OpenBrace {
+            self.parse_block();
+            return true;
+        }
+        let token = self.current();
+        self.emit_error(
+            token,
+            Code::P0001,
+            format!(
+                "expected `{{` to start the {} body, found {}",
+                what,
+                self.describe(token)
+            ),
+        );
+        self.push_missing();
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::Code;
+    use crate::parser::test::kinds;
+
+    #[test]
+    fn test_closures() {
+        assert_eq!(
+            kinds("let f = |x: Int, y| x * y").0,
+            "(LetDecl DeclName (Initializer (ClosureExpr (ClosureParams (Param DeclName TypeName) \
+             (Param DeclName)) (InfixOperator Name Name))))"
+        );
+        assert_eq!(
+            kinds("apply(|| 1, |x| -> Int { x }, 2)").0,
+            "(ExprStmt (Call Name (ClosureExpr ClosureParams IntLiteral) \
+             (ClosureExpr (ClosureParams (Param DeclName)) (ReturnType TypeName) \
+             (Block BlockStart (ExprStmt Name))) IntLiteral))"
+        );
+        assert_eq!(
+            kinds("apply(|x: Int| { x + 1 }, || {\n  run()\n})").0,
+            "(ExprStmt (Call Name (ClosureExpr (ClosureParams (Param DeclName TypeName)) \
+             (Block BlockStart (ExprStmt (InfixOperator Name IntLiteral)))) \
+             (ClosureExpr ClosureParams (Block BlockStart (ExprStmt (Call Name))))))"
+        );
+        assert_eq!(
+            kinds("let g = func(x: Int) -> Int {\n  x\n}(2)").0,
+            "(LetDecl DeclName (Initializer (Call (FuncExpr (ParamList ParamListStart \
+             (Param DeclName TypeName)) (ReturnType TypeName) (Block BlockStart (ExprStmt Name))) \
+             IntLiteral)))"
+        );
+        // Calls through function values, and functions returning them.
+        assert_eq!(
+            kinds("make(1)(2)").0,
+            "(ExprStmt (Call (Call Name IntLiteral) IntLiteral))"
+        );
+    }
+
+    #[test]
+    fn test_closure_errors() {
+        assert_eq!(kinds("let f = |x y| x").1, vec![Code::P0001]);
+        assert_eq!(kinds("let f = |x: | x").1, vec![Code::P0005]);
+        assert_eq!(kinds("let f = |x| -> Int x").1, vec![Code::P0001]);
+        assert_eq!(kinds("let f = func(x) {}").1, vec![Code::P0001]);
+        assert_eq!(kinds("let f = func() Int").1, vec![Code::P0001]);
+    }
+    #[test]
+    fn test_type_args() {
+        assert_eq!(
+            kinds("max[Int](1, 2)").0,
+            "(ExprStmt (Call (GenericName Name (TypeArgs TypeArgsStart TypeName)) \
+             IntLiteral IntLiteral))"
+        );
+        // Without a call after them, brackets index.
+        assert_eq!(kinds("f[Int]").0, "(ExprStmt (IndexExpr Name Name))");
+        assert_eq!(kinds("f[Int Real](1)").1, vec![Code::P0001]);
+    }
+
+    #[test]
+    fn test_arrays() {
+        assert_eq!(
+            kinds("let a = [1, 2][0]").0,
+            "(LetDecl DeclName (Initializer (IndexExpr (ArrayLiteral ArrayLiteralStart \
+             IntLiteral IntLiteral) IntLiteral)))"
+        );
+        assert_eq!(
+            kinds("a = [0; n][1..3]").0,
+            "(Assign Name (IndexExpr (ArrayRepeat ArrayLiteralStart IntLiteral Name) \
+             (InfixOperator IntLiteral IntLiteral)))"
+        );
+        assert_eq!(
+            kinds("m[i][j] = []").0,
+            "(Assign (IndexExpr (IndexExpr Name Name) Name) (ArrayLiteral ArrayLiteralStart))"
+        );
+        assert_eq!(kinds("[1, 2; 3]").1, vec![Code::P0001]);
+        assert_eq!(kinds("[1; 2, 3]").1, vec![Code::P0001]);
+        assert_eq!(kinds("a[1").1, vec![Code::P0001]);
+    }
+
+    #[test]
+    fn test_tuples() {
+        assert_eq!(
+            kinds("match (x, (y)) {}").0,
+            "(ExprStmt (MatchExpr (TupleLiteral ParenExprStart Name \
+             (ParenExpr ParenExprStart Name)) (MatchArmList MatchArmListStart)))"
+        );
+        assert_eq!(
+            kinds("f((a,), ())").0,
+            "(ExprStmt (Call Name (TupleLiteral ParenExprStart Name) \
+             (TupleLiteral ParenExprStart)))"
+        );
+        assert_eq!(kinds("(1 2)").1, vec![Code::P0001]);
+        assert_eq!(kinds("(1, 2").1, vec![Code::P0001]);
+    }
+
+    #[test]
+    fn test_structs() {
+        assert_eq!(
+            kinds("p = Point { x: 1.0, y }.x").0,
+            "(Assign Name (FieldAccess (StructLiteral TypeName StructLiteralStart \
+             (FieldInit RealLiteral) (FieldInit Name))))"
+        );
+        assert_eq!(
+            kinds("v.scale(2.0).len()").0,
+            "(ExprStmt (Call (FieldAccess (Call (FieldAccess Name) RealLiteral))))"
+        );
+        // Before a block, `x {` starts the block.
+        assert_eq!(
+            kinds("if x { y }").0,
+            "(If Name (Block BlockStart (ExprStmt Name)))"
+        );
+        assert_eq!(
+            kinds("match (P {}) { _ => 1 }").0,
+            "(ExprStmt (MatchExpr (ParenExpr ParenExprStart (StructLiteral TypeName \
+             StructLiteralStart)) (MatchArmList MatchArmListStart (MatchArm WildcardPattern \
+             IntLiteral))))"
+        );
+        assert_eq!(kinds("p.").1, vec![Code::P0001]);
+        assert_eq!(kinds("P { x: 1 y: 2 }").1, vec![Code::P0001]);
+    }
+}
@@ -0,0 +1,470 @@
+use super::Parser;
+use crate::diagnostics::Code;
+use crate::parse_tree::NodeKind;
+use crate::token::TokenKind;
+
+impl Parser<'_> {
+    /// `match value { pattern if guard => result, ... }`. Arms are separated
+    /// by commas or newlines, and a result may be an expression or a block.
+    pub(super) fn parse_match(&mut self) {
+        let start = self.tree.len();
+        let keyword = self.take();
+        self.parse_condition();
+
+        let list_start = self.tree.len();
+        let Some(open) = self.expect(TokenKind::OpenBrace, "`{`") else {
+            self.push_missing();
+            self.tree.push(NodeKind::MatchExpr, keyword, start, true);
+            return;
+        };
+        self.tree
+            .push(NodeKind::MatchArmListStart, open, list_start, false);
+
+        let nesting = std::mem::replace(&mut self.nesting, 0);
+        let mut has_error = false;
+        loop {
+            self.skip_newlines();
+            if matches!(self.peek(), TokenKind::CloseBrace | TokenKind::Eof) {
+                break;
+            }
+            // Like statements, each arm reports its own first error.
+            self.errored = false;
+            if !self.parse_match_arm() {
+                // Skipping to the end of the line keeps braces in a broken
+                // pattern from closing the match.
+                has_error = true;
+                self.skip_line();
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::VerticalWhitespace | TokenKind::CloseBrace | TokenKind::Eof => {}
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected `,` or `}}`, found {}", self.describe(token)),
+                    );
+                    has_error = true;
+                    self.skip_line();
+                }
+            }
+        }
+        self.nesting = nesting;
+
+        let close = self.expect(TokenKind::CloseBrace, "`}`");
+        let token = close.unwrap_or_else(|| self.last.unwrap_or(open));
+        self.tree.push(
+            NodeKind::MatchArmList,
+            token,
+            list_start,
+            has_error || close.is_none(),
+        );
+        self.tree
+            .push(NodeKind::MatchExpr, keyword, start, close.is_none());
+    }
+
+    fn parse_match_arm(&mut self) -> bool {
+        let start = self.tree.len();
+        let mut found = self.parse_pattern();
+        if found && self.peek() == TokenKind::IfKeyword {
+            let guard_start = self.tree.len();
+            let keyword = self.take();
+            self.parse_expr();
+            self.tree
+                .push(NodeKind::MatchGuard, keyword, guard_start, false);
+        }
+        let arrow = match found {
+            true => self.expect(TokenKind::FatArrow, "`=>`"),
+            false => None,
+        };
+        found &= arrow.is_some();
+        if let Some(arrow) = arrow {
+            self.skip_newlines();
+            match self.peek() {
+                TokenKind::OpenBrace => self.parse_block(),
+                _ => self.parse_expr(),
+            }
+            self.tree.push(NodeKind::MatchArm, arrow, start, false);
+        } else {
+            let token = self.last.unwrap_or_else(|| self.current());
+            self.tree.push(NodeKind::MatchArm, token, start, true);
+        }
+        found
+    }
+
+    /// Parses a pattern, returning whether one was found:
+    ///
+    /// - `_`, matching anything
+    /// - `name`, binding the value
+    /// - `1`, `-2.5`, `true` and ranges `1..10`
+    /// - `(a, b)` tuples
+    /// - `Shape.Circle(r)` and `Empty` variants, with or without a payload
+    /// - `Point { x, y: 0, .. }` structs
+    /// - `a | b` alternatives
+    ///
+    /// A bare name is a binding unless it is followed by a payload; telling
+    /// it apart from a variant without one is left to name resolution.
+    pub(super) fn parse_pattern(&mut self) -> bool {
+        let start = self.tree.len();
+        if !self.parse_primary_pattern() {
+            return false;
+        }
+        while self.peek() == TokenKind::Pipe {
+            let pipe = self.take();
+            self.skip_newlines();
+            let found = self.parse_primary_pattern();
+            self.tree.push(NodeKind::AltPattern, pipe, start, !found);
+            if !found {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn parse_primary_pattern(&mut self) -> bool {
+        let start = self.tree.len();
+        match self.peek() {
+            TokenKind::Ident if self.buf.str_of(&self.current()) == "_" => {
+                let token = self.take();
+                self.tree
+                    .push(NodeKind::WildcardPattern, token, start, false);
+                true
+            }
+            TokenKind::Ident => self.parse_path_pattern(),
+            TokenKind::IntLiteral
+            | TokenKind::RealLiteral
+            | TokenKind::TrueKeyword
+            | TokenKind::FalseKeyword
+            | TokenKind::Minus => self.parse_literal_pattern(),
+            TokenKind::OpenParen => self.parse_tuple_pattern(),
+            _ => {
+                let token = self.current();
+                self.emit_error(
+                    token,
+                    Code::P0008,
+                    format!("expected pattern, found {}", self.describe(token)),
+                );
+                self.push_missing();
+                false
+            }
+        }
+    }
+
+    fn parse_literal_pattern(&mut self) -> bool {
+        let start = self.tree.len();
+        let found = self.parse_pattern_literal();
+        let token = self.last.unwrap_or_else(|| self.current());
+        self.tree
+            .push(NodeKind::LiteralPattern, token, start, !found);
+        if !found || self.peek() != TokenKind::DotDot {
+            return found;
+        }
+
+        let op = self.take();
+        let end_start = self.tree.len();
+        let found = self.parse_pattern_literal();
+        let token = self.last.unwrap_or(op);
+        self.tree
+            .push(NodeKind::LiteralPattern, token, end_start, !found);
+        self.tree.push(NodeKind::RangePattern, op, start, !found);
+        found
+    }
+
+    /// A literal, optionally negated.
+    fn parse_pattern_literal(&mut self) -> bool {
+        let start = self.tree.len();
+        let minus = match self.peek() {
+            TokenKind::Minus => Some(self.take()),
+            _ => None,
+        };
+        let kind = match self.peek() {
+            TokenKind::IntLiteral => NodeKind::IntLiteral,
+            TokenKind::RealLiteral => NodeKind::RealLiteral,
+            TokenKind::TrueKeyword | TokenKind::FalseKeyword if minus.is_none() => {
+                NodeKind::BoolLiteral
+            }
+            _ => {
+                let token = self.current();
+                self.emit_error(
+                    token,
+                    Code::P0008,
+                    format!("expected a literal, found {}", self.describe(token)),
+                );
+                self.push_missing();
+                if let Some(minus) = minus {
+                    self.tree.push(NodeKind::PrefixOperator, minus, start, true);
+                }
+                return false;
+            }
+        };
+        let token = self.take();
+        let literal_start = self.tree.len();
+        self.tree.push(kind, token, literal_start, false);
+        if let Some(minus) = minus {
+            self.tree
+                .push(NodeKind::PrefixOperator, minus, start, false);
+        }
+        true
+    }
+
+    fn parse_path_pattern(&mut self) -> bool {
+        let start = self.tree.len();
+        let first = self.take();
+        if !matches!(
+            self.peek(),
+            TokenKind::Dot | TokenKind::OpenParen | TokenKind::OpenBrace
+        ) {
+            self.tree
+                .push(NodeKind::BindingPattern, first, start, false);
+            return true;
+        }
+
+        self.tree.push(NodeKind::PathSegment, first, start, false);
+        while self.peek() == TokenKind::Dot {
+            self.take();
+            let segment_start = self.tree.len();
+            if self.peek() != TokenKind::Ident {
+                let token = self.current();
+                self.emit_error(
+                    token,
+                    Code::P0001,
+                    format!("expected a name after `.`, found {}", self.describe(token)),
+                );
+                self.push_missing();
+                let last = self.last.unwrap_or(first);
+                self.tree.push(NodeKind::PatternPath, last, start, true);
+                self.tree.push(NodeKind::VariantPattern, last, start, true);
+                return false;
+            }
+            let segment = self.take();
+            self.tree
+                .push(NodeKind::PathSegment, segment, segment_start, false);
+        }
+        let name = self.last.unwrap_or(first);
+        self.tree.push(NodeKind::PatternPath, name, start, false);
+
+        match self.peek() {
+            TokenKind::OpenParen => {
+                let found = self.parse_tuple_pattern();
+                self.tree
+                    .push(NodeKind::VariantPattern, name, start, !found);
+                found
+            }
+            TokenKind::OpenBrace => self.parse_struct_pattern(start),
+            _ => {
+                self.tree.push(NodeKind::VariantPattern, name, start, false);
+                true
+            }
+        }
+    }
+
+    fn parse_tuple_pattern(&mut self) -> bool {
+        let start = self.tree.len();
+        let open = self.take();
+        self.tree
+            .push(NodeKind::TuplePatternStart, open, start, false);
+        self.nesting += 1;
+        let mut found = true;
+        while self.peek() != TokenKind::CloseParen {
+            if !self.parse_pattern() {
+                found = false;
+                break;
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseParen => break,
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected `,` or `)`, found {}", self.describe(token)),
+                    );
+                    found = false;
+                    break;
+                }
+            }
+        }
+        self.nesting -= 1;
+        self.close_pattern_list(start, NodeKind::TuplePattern, TokenKind::CloseParen, found)
+    }
+
+    /// The fields after a struct pattern's path, which starts at `start`.
+    fn parse_struct_pattern(&mut self, start: usize) -> bool {
+        let open_start = self.tree.len();
+        let open = self.take();
+        self.tree
+            .push(NodeKind::StructPatternStart, open, open_start, false);
+        self.nesting += 1;
+        let mut found = true;
+        while self.peek() != TokenKind::CloseBrace {
+            let field_start = self.tree.len();
+            match self.peek() {
+                TokenKind::DotDot => {
+                    // `..` ignores the remaining fields, so it comes last.
+                    let rest = self.take();
+                    self.tree
+                        .push(NodeKind::RestPattern, rest, field_start, false);
+                    break;
+                }
+                TokenKind::Ident => {
+                    let name = self.take();
+                    let mut field_found = true;
+                    if self.peek() == TokenKind::Colon {
+                        self.take();
+                        field_found = self.parse_pattern();
+                    }
+                    self.tree
+                        .push(NodeKind::FieldPattern, name, field_start, !field_found);
+                    if !field_found {
+                        found = false;
+                        break;
+                    }
+                }
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected field name, found {}", self.describe(token)),
+                    );
+                    found = false;
+                    break;
+                }
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseBrace => break,
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected `,` or `}}`, found {}", self.describe(token)),
+                    );
+                    found = false;
+                    break;
+                }
+            }
+        }
+        self.nesting -= 1;
+        self.close_pattern_list(start, NodeKind::StructPattern, TokenKind::CloseBrace, found)
+    }
+
+    /// Ends a parenthesized or braced list of patterns with its closing
+    /// delimiter. After an error the rest of the list is skipped.
+    fn close_pattern_list(
+        &mut self,
+        start: usize,
+        kind: NodeKind,
+        close: TokenKind,
+        found: bool,
+    ) -> bool {
+        if !found {
+            self.skip_until(&[close, TokenKind::VerticalWhitespace, TokenKind::FatArrow]);
+            if self.peek() == close {
+                self.take();
+            }
+            let token = self.last.unwrap_or_else(|| self.current());
+            self.tree.push(kind, token, start, true);
+            return false;
+        }
+        let expected = match close {
+            TokenKind::CloseParen => "`)`",
+            _ => "`}`",
+        };
+        let token = self.expect(close, expected);
+        let last = token.unwrap_or_else(|| self.last.unwrap_or_else(|| self.current()));
+        self.tree.push(kind, last, start, token.is_none());
+        token.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::Code;
+    use crate::parser::test::kinds;
+
+    /// The pattern of a single arm.
+    fn pattern(source: &str) -> (String, Vec<Code>) {
+        let (tree, codes) = kinds(&format!("match x {{ {} => 0 }}", source));
+        let pattern = tree
+            .strip_prefix("(ExprStmt (MatchExpr Name (MatchArmList MatchArmListStart (MatchArm ")
+            .and_then(|rest| rest.strip_suffix(" IntLiteral))))"))
+            .unwrap_or(&tree)
+            .to_string();
+        (pattern, codes)
+    }
+
+    #[test]
+    fn test_match() {
+        let (tree, codes) =
+            kinds("let y = match x {\n  0 => 1,\n  n if n > 0 => {\n    n\n  }\n  _ => -1\n}");
+        assert!(codes.is_empty());
+        assert_eq!(
+            tree,
+            "(LetDecl DeclName (Initializer (MatchExpr Name (MatchArmList MatchArmListStart \
+             (MatchArm (LiteralPattern IntLiteral) IntLiteral) \
+             (MatchArm BindingPattern (MatchGuard (InfixOperator Name IntLiteral)) \
+             (Block BlockStart (ExprStmt Name))) \
+             (MatchArm WildcardPattern (PrefixOperator IntLiteral))))))"
+        );
+        // `return` is an expression, so an arm can return.
+        let (tree, codes) = kinds("match x {\n  true => return 1,\n  false => f(return)\n}");
+        assert!(codes.is_empty());
+        assert_eq!(
+            tree,
+            "(ExprStmt (MatchExpr Name (MatchArmList MatchArmListStart \
+             (MatchArm (LiteralPattern BoolLiteral) (Return IntLiteral)) \
+             (MatchArm (LiteralPattern BoolLiteral) (Call Name Return)))))"
+        );
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert_eq!(
+            pattern("-1..10").0,
+            "(RangePattern (LiteralPattern (PrefixOperator IntLiteral)) (LiteralPattern IntLiteral))"
+        );
+        assert_eq!(
+            pattern("Shape.Circle(r) | Empty").0,
+            "(AltPattern (VariantPattern (PatternPath PathSegment PathSegment) \
+             (TuplePattern TuplePatternStart BindingPattern)) BindingPattern)"
+        );
+        assert_eq!(
+            pattern("Point { x, y: 0, .. }").0,
+            "(StructPattern (PatternPath PathSegment) StructPatternStart FieldPattern \
+             (FieldPattern (LiteralPattern IntLiteral)) RestPattern)"
+        );
+        assert_eq!(
+            pattern("(true, _)").0,
+            "(TuplePattern TuplePatternStart (LiteralPattern BoolLiteral) WildcardPattern)"
+        );
+        assert_eq!(
+            pattern("Some(_)").0,
+            "(VariantPattern (PatternPath PathSegment) (TuplePattern TuplePatternStart WildcardPattern))"
+        );
+    }
+
+    #[test]
+    fn test_match_errors() {
+        assert_eq!(pattern("+").1, vec![Code::P0008]);
+        assert_eq!(pattern("1..x").1, vec![Code::P0008]);
+        assert_eq!(pattern("(a b)").1, vec![Code::P0001]);
+        assert_eq!(pattern("P { 1 }").1, vec![Code::P0001]);
+        assert_eq!(pattern("Shape.").1, vec![Code::P0001]);
+        assert_eq!(kinds("match x { a 1 }").1, vec![Code::P0001]);
+        assert_eq!(kinds("match x 1").1, vec![Code::P0001]);
+        // Each arm reports its own error.
+        let (tree, codes) = kinds("match x {\n  + => 1\n  a => 2\n  b =>\n}");
+        assert_eq!(codes, vec![Code::P0008, Code::P0003]);
+        assert!(tree.contains("(MatchArm BindingPattern IntLiteral)"));
+    }
+}
@@ -0,0 +1,350 @@
+use super::Parser;
+use crate::diagnostics::Code;
+use crate::parse_tree::NodeKind;
+use crate::token::TokenKind;
+
+impl Parser<'_> {
+    /// Statements end at a `;`, at the end of their line, or before the `}`
+    /// closing their block, so several statements on one line need `;`.
+    /// Statements ending in a block (declarations, `if`, `while`, `for` and
+    /// nested blocks) need no terminator.
+    pub(super) fn parse_stmt(&mut self) {
+        self.errored = false;
+        let ends_with_block = match self.peek() {
+            TokenKind::PubKeyword => {
+                let start = self.tree.len();
+                self.parse_pub(start)
+            }
+            TokenKind::At => self.parse_attributed_decl(),
+            TokenKind::FuncKeyword
+            | TokenKind::TypeKeyword
+            | TokenKind::ImplKeyword
+            | TokenKind::InterfaceKeyword
+            | TokenKind::LetKeyword
+            | TokenKind::VarKeyword
+            | TokenKind::ConstKeyword
+            | TokenKind::ImportKeyword
+            | TokenKind::ModuleKeyword => {
+                let start = self.tree.len();
+                self.parse_decl(start)
+            }
+            TokenKind::IfKeyword => {
+                self.parse_if();
+                true
+            }
+            TokenKind::WhileKeyword => {
+                self.parse_while();
+                true
+            }
+            TokenKind::ForKeyword => {
+                self.parse_for();
+                true
+            }
+            TokenKind::OpenBrace => {
+                self.parse_block();
+                true
+            }
+            TokenKind::BreakKeyword | TokenKind::ContinueKeyword => {
+                let start = self.tree.len();
+                let kind = match self.peek() {
+                    TokenKind::BreakKeyword => NodeKind::Break,
+                    _ => NodeKind::Continue,
+                };
+                let keyword = self.take();
+                self.tree.push(kind, keyword, start, false);
+                false
+            }
+            TokenKind::ReturnKeyword => {
+                self.parse_return();
+                false
+            }
+            TokenKind::Semi => {
+                self.take();
+                return;
+            }
+            _ => {
+                self.parse_expr_stmt();
+                false
+            }
+        };
+
+        if !ends_with_block {
+            self.expect_stmt_end();
+        } else if self.peek() == TokenKind::Semi {
+            self.take();
+        }
+    }
+
+    pub(super) fn parse_block(&mut self) {
+        let start = self.tree.len();
+        let open = match self.expect(TokenKind::OpenBrace, "`{`") {
+            Some(open) => open,
+            None => {
+                // Resume at a `{` later on the same line, as in `if a b {`.
+                self.skip_until(&[
+                    TokenKind::OpenBrace,
+                    TokenKind::CloseBrace,
+                    TokenKind::VerticalWhitespace,
+                ]);
+                if self.peek() != TokenKind::OpenBrace {
+                    self.push_missing();
+                    return;
+                }
+                self.take()
+            }
+        };
+        self.tree.push(NodeKind::BlockStart, open, start, false);
+
+        // Lines inside a block are significant even within parentheses.
+        let nesting = std::mem::replace(&mut self.nesting, 0);
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                TokenKind::CloseBrace | TokenKind::Eof => break,
+                _ => self.parse_stmt(),
+            }
+        }
+        self.nesting = nesting;
+
+        match self.expect(TokenKind::CloseBrace, "`}`") {
+            Some(close) => self.tree.push(NodeKind::Block, close, start, false),
+            None => {
+                let last = self.last.unwrap_or(open);
+                self.tree.push(NodeKind::Block, last, start, true)
+            }
+        };
+    }
+
+    pub(super) fn expect_stmt_end(&mut self) {
+        let kind = match self.peek() {
+            TokenKind::Semi => {
+                self.take();
+                return;
+            }
+            TokenKind::VerticalWhitespace | TokenKind::Eof | TokenKind::CloseBrace => return,
+            kind => kind,
+        };
+        // Invalid tokens were already reported by the lexer.
+        if kind != TokenKind::Invalid {
+            let token = self.current();
+            self.emit_error(
+                token,
+                Code::P0002,
+                format!(
+                    "expected `;` or end of line, found {}",
+                    self.describe(token)
+                ),
+            );
+        }
+        // A statement starting with a keyword is most likely intact, so it
+        // is parsed next.
+        if self.at_decl_start() || starts_stmt(kind) {
+            return;
+        }
+        self.skip_line();
+        if self.peek() == TokenKind::Semi {
+            self.take();
+        }
+    }
+
+    /// `let name: Type = value` or `var name: Type = value`, where either
+    /// the type or the value may be left out. `const name: Type = value`
+    /// always has a value.
+    pub(super) fn parse_binding(&mut self, start: usize) {
+        let kind = match self.peek() {
+            TokenKind::LetKeyword => NodeKind::LetDecl,
+            TokenKind::ConstKeyword => NodeKind::ConstDecl,
+            _ => NodeKind::VarDecl,
+        };
+        let keyword = self.take();
+
+        let mut has_error = !self.parse_decl_name("variable name");
+        if !has_error && self.peek() == TokenKind::Colon {
+            let annotation_start = self.tree.len();
+            let colon = self.take();
+            let found = self.parse_type();
+            self.tree
+                .push(NodeKind::TypeAnnotation, colon, annotation_start, !found);
+            has_error = !found;
+        }
+        if !has_error && self.peek() == TokenKind::Equal {
+            let initializer_start = self.tree.len();
+            let equal = self.take();
+            self.skip_newlines();
+            self.parse_expr();
+            self.tree
+                .push(NodeKind::Initializer, equal, initializer_start, false);
+        } else if !has_error && kind == NodeKind::ConstDecl {
+            has_error = self
+                .expect(TokenKind::Equal, "`=` and the value of the constant")
+                .is_none();
+        }
+        if has_error {
+            self.skip_line();
+        }
+        self.tree.push(kind, keyword, start, has_error);
+    }
+
+    /// An expression, or an assignment when an assignment operator follows.
+    fn parse_expr_stmt(&mut self) {
+        let start = self.tree.len();
+        let first = self.current();
+        self.parse_expr();
+        if self.current() == first {
+            // Nothing could be parsed, and the error is already reported.
+            self.skip_line();
+            self.tree.push(NodeKind::ExprStmt, first, start, true);
+            return;
+        }
+
+        match self.peek() {
+            TokenKind::Equal
+            | TokenKind::PlusEqual
+            | TokenKind::MinusEqual
+            | TokenKind::StarEqual
+            | TokenKind::SlashEqual
+            | TokenKind::PercentEqual => {
+                let op = self.take();
+                self.skip_newlines();
+                self.parse_expr();
+                self.tree.push(NodeKind::Assign, op, start, false);
+            }
+            _ => {
+                self.tree.push(NodeKind::ExprStmt, first, start, false);
+            }
+        }
+    }
+
+    /// `if cond { ... } else if cond { ... } else { ... }`, where `else` may
+    /// start a new line.
+    fn parse_if(&mut self) {
+        let start = self.tree.len();
+        let keyword = self.take();
+        self.parse_condition();
+        self.parse_block();
+
+        if self.peek_past_newlines() == TokenKind::ElseKeyword {
+            self.skip_newlines();
+            let else_start = self.tree.len();
+            let else_keyword = self.take();
+            if self.peek() == TokenKind::IfKeyword {
+                self.parse_if();
+            } else {
+                self.parse_block();
+            }
+            self.tree
+                .push(NodeKind::Else, else_keyword, else_start, false);
+        }
+        self.tree.push(NodeKind::If, keyword, start, false);
+    }
+
+    fn parse_while(&mut self) {
+        let start = self.tree.len();
+        let keyword = self.take();
+        self.parse_condition();
+        self.parse_block();
+        self.tree.push(NodeKind::While, keyword, start, false);
+    }
+
+    /// `for name in range { ... }`
+    fn parse_for(&mut self) {
+        let start = self.tree.len();
+        let keyword = self.take();
+        let found = self.parse_decl_name("loop variable")
+            && self.expect(TokenKind::InKeyword, "`in`").is_some();
+        if found {
+            self.parse_condition();
+            self.parse_block();
+        } else {
+            self.skip_until(&[
+                TokenKind::OpenBrace,
+                TokenKind::CloseBrace,
+                TokenKind::VerticalWhitespace,
+            ]);
+            if self.peek() == TokenKind::OpenBrace {
+                self.parse_block();
+            } else {
+                self.push_missing();
+            }
+        }
+        self.tree.push(NodeKind::For, keyword, start, !found);
+    }
+}
+
+/// Keywords that start a statement. `return` can also be part of an
+/// expression, but mostly starts one.
+fn starts_stmt(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::LetKeyword
+            | TokenKind::VarKeyword
+            | TokenKind::ConstKeyword
+            | TokenKind::IfKeyword
+            | TokenKind::WhileKeyword
+            | TokenKind::ForKeyword
+            | TokenKind::BreakKeyword
+            | TokenKind::ContinueKeyword
+            | TokenKind::ReturnKeyword
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::Code;
+    use crate::parser::test::kinds;
+
+    #[test]
+    fn test_bindings() {
+        assert_eq!(
+            kinds("let x: Int = 1\nvar y = x").0,
+            "(LetDecl DeclName (TypeAnnotation TypeName) (Initializer IntLiteral)) \
+             (VarDecl DeclName (Initializer Name))"
+        );
+        assert_eq!(
+            kinds("var z: Real").0,
+            "(VarDecl DeclName (TypeAnnotation TypeName))"
+        );
+        assert_eq!(kinds("let = 1").1, vec![Code::P0001]);
+        assert_eq!(
+            kinds("const N: u8 = 1 << 2 + 3").0,
+            "(ConstDecl DeclName (TypeAnnotation TypeName) \
+             (Initializer (InfixOperator IntLiteral (InfixOperator IntLiteral IntLiteral))))"
+        );
+        assert_eq!(kinds("const N: Int").1, vec![Code::P0001]);
+    }
+
+    #[test]
+    fn test_assignments() {
+        assert_eq!(
+            kinds("x = 1; y += x * 2").0,
+            "(Assign Name IntLiteral) (Assign Name (InfixOperator Name IntLiteral))"
+        );
+        assert_eq!(kinds("x = 1 y = 2").1, vec![Code::P0002]);
+    }
+
+    #[test]
+    fn test_control_flow() {
+        assert_eq!(
+            kinds("if a { b } else if c {\n} \nelse { return 1 }").0,
+            "(If Name (Block BlockStart (ExprStmt Name)) (Else (If Name (Block BlockStart) \
+             (Else (Block BlockStart (Return IntLiteral))))))"
+        );
+        assert_eq!(
+            kinds("while i < 10 { i += 1; continue }").0,
+            "(While (InfixOperator Name IntLiteral) (Block BlockStart (Assign Name IntLiteral) Continue))"
+        );
+        assert_eq!(
+            kinds("for i in 0..n {\n  break\n}").0,
+            "(For DeclName (InfixOperator IntLiteral Name) (Block BlockStart Break))"
+        );
+        assert_eq!(kinds("{ return }").0, "(Block BlockStart Return)");
+    }
+
+    #[test]
+    fn test_stmt_errors() {
+        assert_eq!(kinds("for in x {}").1, vec![Code::P0001]);
+        assert_eq!(kinds("if x\ny").1, vec![Code::P0001]);
+        assert_eq!(kinds("{ 1").1, vec![Code::P0001]);
+        assert_eq!(kinds(") + 1\nx").1, vec![Code::P0003]);
+    }
+}
@@ -0,0 +1,256 @@
+use super::Parser;
+use crate::diagnostics::Code;
+use crate::parse_tree::NodeKind;
+use crate::token::TokenKind;
+
+impl Parser<'_> {
+    /// Parses a type expression, returning whether one was found:
+    ///
+    /// - `Name`, or `Name[A, B]` with type arguments
+    /// - `*T` and `&T`
+    /// - `[T; N]` arrays and `[T]` slices
+    /// - `func(A, B) -> C`
+    /// - `(A, B)` tuples, where `()` is the unit type and `(T)` is just `T`
+    pub(super) fn parse_type(&mut self) -> bool {
+        let start = self.tree.len();
+        match self.peek() {
+            TokenKind::Ident => {
+                let name = self.take();
+                self.tree.push(NodeKind::TypeName, name, start, false);
+                if self.peek() != TokenKind::OpenBracket {
+                    return true;
+                }
+                let found = self.parse_type_args();
+                self.tree.push(NodeKind::GenericType, name, start, !found);
+                found
+            }
+            TokenKind::Star | TokenKind::Amp => {
+                let kind = match self.peek() {
+                    TokenKind::Star => NodeKind::PointerType,
+                    _ => NodeKind::ReferenceType,
+                };
+                let op = self.take();
+                let found = self.parse_type();
+                self.tree.push(kind, op, start, !found);
+                found
+            }
+            TokenKind::OpenBracket => self.parse_array_type(),
+            TokenKind::FuncKeyword => self.parse_func_type(),
+            TokenKind::OpenParen => {
+                let open = self.take();
+                self.tree.push(NodeKind::TupleTypeStart, open, start, false);
+                let closed = self.parse_type_list();
+                let close = self.last.unwrap_or(open);
+                self.tree.push(NodeKind::TupleType, close, start, !closed);
+                closed
+            }
+            _ => {
+                let token = self.current();
+                self.emit_error(
+                    token,
+                    Code::P0005,
+                    format!("expected type, found {}", self.describe(token)),
+                );
+                self.push_missing();
+                false
+            }
+        }
+    }
+
+    /// `[T; N]` or `[T]`.
+    fn parse_array_type(&mut self) -> bool {
+        let start = self.tree.len();
+        let open = self.take();
+        self.tree.push(NodeKind::ArrayTypeStart, open, start, false);
+        self.nesting += 1;
+        let mut found = self.parse_type();
+        let kind = if found && self.peek() == TokenKind::Semi {
+            self.take();
+            self.parse_expr();
+            NodeKind::ArrayType
+        } else {
+            NodeKind::SliceType
+        };
+        self.nesting -= 1;
+        if !found {
+            self.skip_until(&[
+                TokenKind::CloseBracket,
+                TokenKind::CloseBrace,
+                TokenKind::VerticalWhitespace,
+            ]);
+            if self.peek() == TokenKind::CloseBracket {
+                self.take();
+            }
+        }
+
+        let close = match found {
+            true => self.expect(TokenKind::CloseBracket, "`]`"),
+            false => None,
+        };
+        found &= close.is_some();
+        let token = close.unwrap_or_else(|| self.last.unwrap_or(open));
+        self.tree.push(kind, token, start, !found);
+        found
+    }
+
+    /// `[A, B]` after a generic type or function. Returns whether the
+    /// closing `]` was found.
+    pub(super) fn parse_type_args(&mut self) -> bool {
+        let start = self.tree.len();
+        let open = self.take();
+        self.tree.push(NodeKind::TypeArgsStart, open, start, false);
+        self.nesting += 1;
+        let mut found = true;
+        while self.peek() != TokenKind::CloseBracket {
+            if !self.parse_type() {
+                found = false;
+                break;
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseBracket => break,
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected `,` or `]`, found {}", self.describe(token)),
+                    );
+                    found = false;
+                    break;
+                }
+            }
+        }
+        if !found {
+            self.skip_until(&[
+                TokenKind::CloseBracket,
+                TokenKind::CloseParen,
+                TokenKind::CloseBrace,
+                TokenKind::Semi,
+            ]);
+        }
+        self.nesting -= 1;
+        let close = match self.peek() {
+            TokenKind::CloseBracket => self.take(),
+            _ => self.last.unwrap_or(open),
+        };
+        self.tree.push(NodeKind::TypeArgs, close, start, !found);
+        found
+    }
+
+    /// `func(A, B) -> C`, where the return type may be left out.
+    fn parse_func_type(&mut self) -> bool {
+        let start = self.tree.len();
+        let func = self.take();
+        let params_start = self.tree.len();
+        let mut found = match self.expect(TokenKind::OpenParen, "`(`") {
+            Some(open) => {
+                let closed = self.parse_type_list();
+                let close = self.last.unwrap_or(open);
+                self.tree
+                    .push(NodeKind::FuncTypeParams, close, params_start, !closed);
+                closed
+            }
+            None => false,
+        };
+        if found && self.peek() == TokenKind::Arrow {
+            let return_start = self.tree.len();
+            let arrow = self.take();
+            found = self.parse_type();
+            self.tree
+                .push(NodeKind::ReturnType, arrow, return_start, !found);
+        }
+        self.tree.push(NodeKind::FuncType, func, start, !found);
+        found
+    }
+
+    /// The comma separated types after an opening `(`, up to and including
+    /// the closing `)`. Returns whether the `)` was found.
+    pub(super) fn parse_type_list(&mut self) -> bool {
+        self.nesting += 1;
+        let mut found = true;
+        while self.peek() != TokenKind::CloseParen {
+            if !self.parse_type() {
+                found = false;
+                break;
+            }
+            match self.peek() {
+                TokenKind::Comma => {
+                    self.take();
+                }
+                TokenKind::CloseParen => break,
+                _ => {
+                    let token = self.current();
+                    self.emit_error(
+                        token,
+                        Code::P0001,
+                        format!("expected `,` or `)`, found {}", self.describe(token)),
+                    );
+                    found = false;
+                    break;
+                }
+            }
+        }
+        if !found {
+            // Skip the rest of the list, so that recovery resumes after it.
+            self.skip_until(&[
+                TokenKind::CloseParen,
+                TokenKind::CloseBrace,
+                TokenKind::Semi,
+            ]);
+            if self.peek() == TokenKind::CloseParen {
+                self.take();
+            }
+        }
+        self.nesting -= 1;
+        found && self.expect(TokenKind::CloseParen, "`)`").is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diagnostics::Code;
+    use crate::parser::test::kinds;
+
+    fn annotation(ty: &str) -> (String, Vec<Code>) {
+        let (tree, codes) = kinds(&format!("var x: {}", ty));
+        let prefix = "(VarDecl DeclName (TypeAnnotation ";
+        assert!(tree.starts_with(prefix), "{}", tree);
+        (tree[prefix.len()..tree.len() - 2].to_string(), codes)
+    }
+
+    #[test]
+    fn test_type_expressions() {
+        assert_eq!(annotation("*Int").0, "(PointerType TypeName)");
+        assert_eq!(
+            annotation("&[Real]").0,
+            "(ReferenceType (SliceType ArrayTypeStart TypeName))"
+        );
+        assert_eq!(
+            annotation("[Int; 2 * N]").0,
+            "(ArrayType ArrayTypeStart TypeName (InfixOperator IntLiteral Name))"
+        );
+        assert_eq!(
+            annotation("func(Int, *Real) -> (Int, Bool)").0,
+            "(FuncType (FuncTypeParams TypeName (PointerType TypeName)) \
+             (ReturnType (TupleType TupleTypeStart TypeName TypeName)))"
+        );
+        assert_eq!(annotation("func()").0, "(FuncType FuncTypeParams)");
+        assert_eq!(annotation("()").0, "(TupleType TupleTypeStart)");
+        assert_eq!(
+            annotation("Map[String, [Option[Int]]]").0,
+            "(GenericType TypeName (TypeArgs TypeArgsStart TypeName (SliceType ArrayTypeStart \
+             (GenericType TypeName (TypeArgs TypeArgsStart TypeName)))))"
+        );
+    }
+
+    #[test]
+    fn test_type_errors() {
+        assert_eq!(annotation("[Int; 3").1, vec![Code::P0001]);
+        assert_eq!(annotation("(Int Real)").1, vec![Code::P0001]);
+        assert_eq!(annotation("*").1, vec![Code::P0005]);
+        assert_eq!(annotation("Option[Int Real]").1, vec![Code::P0001]);
+    }
+}
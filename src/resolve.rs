@@ -0,0 +1,846 @@
+use std::collections::HashMap;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::diagnostics::{self, Code, Diagnostic, DiagnosticEmitter};
+use crate::parse_tree::{Node, NodeKind, ParseTree};
+use crate::token::Token;
+use crate::tokenized_buffer::TokenizedBuffer;
+
+/// Types every module can refer to without declaring them.
+pub const BUILTIN_TYPES: &[&str] = &[
+    "Int", "Real", "Bool", "String", "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64",
+];
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct DeclId(pub usize);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeclKind {
+    Func,
+    Type,
+    TypeParam,
+    /// An `interface`, which is only a type as the bound of a type
+    /// parameter or in an `impl`.
+    Interface,
+    Param,
+    /// A `let` or `var` binding, or a `for` loop variable.
+    Local {
+        mutable: bool,
+    },
+    /// A name bound by a pattern.
+    PatternBinding,
+    /// A `const`, whose value is known at compile time.
+    Const,
+    /// A module or declaration brought in by `import`.
+    Import,
+    BuiltinType,
+}
+
+impl DeclKind {
+    pub fn is_type(self) -> bool {
+        matches!(
+            self,
+            DeclKind::Type
+                | DeclKind::TypeParam
+                | DeclKind::Interface
+                | DeclKind::BuiltinType
+                | DeclKind::Import
+        )
+    }
+
+    /// Whether the declaration lives in a function's frame, so that a
+    /// closure referring to it has to capture it.
+    fn is_local(self) -> bool {
+        matches!(
+            self,
+            DeclKind::Param | DeclKind::Local { .. } | DeclKind::PatternBinding
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Decl {
+    pub name: String,
+    pub kind: DeclKind,
+    /// The node that introduces the name, such as its `DeclName`. `None`
+    /// for builtins.
+    pub node: Option<Node>,
+}
+
+/// What name resolution found: every declaration, and for each node that
+/// declares or refers to a name, the declaration it stands for.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    decls: Vec<Decl>,
+    decl_ids: Vec<Option<DeclId>>,
+    captures: Vec<Vec<DeclId>>,
+}
+
+impl Resolution {
+    pub fn decl(&self, id: DeclId) -> &Decl {
+        &self.decls[id.0]
+    }
+
+    pub fn decls(&self) -> impl Iterator<Item = (DeclId, &Decl)> {
+        self.decls
+            .iter()
+            .enumerate()
+            .map(|(id, decl)| (DeclId(id), decl))
+    }
+
+    /// The declaration `node` introduces or refers to.
+    pub fn decl_of(&self, node: Node) -> Option<DeclId> {
+        self.decl_ids.get(*node).copied().flatten()
+    }
+
+    /// The locals of enclosing functions that a closure or function
+    /// expression refers to, in order of first use.
+    pub fn captures_of(&self, node: Node) -> &[DeclId] {
+        self.captures.get(*node).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Builds the symbol table of a parse tree. Module scope sees all of its
+/// declarations, so functions and types can be used before they appear;
+/// in blocks, a name is visible from its declaration on and may shadow
+/// earlier ones.
+pub struct Resolver {
+    diagnostics: Rc<RefCell<dyn DiagnosticEmitter>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            diagnostics: Rc::new(RefCell::new(diagnostics::DefaultEmitter)),
+        }
+    }
+
+    pub fn with_emitter(mut self, emitter: Rc<RefCell<dyn DiagnosticEmitter>>) -> Self {
+        self.diagnostics = emitter;
+        self
+    }
+
+    pub fn resolve(&self, tree: &ParseTree, tokens: &TokenizedBuffer) -> Resolution {
+        let mut context = Context {
+            resolver: self,
+            tree,
+            tokens,
+            resolution: Resolution {
+                decl_ids: vec![None; tree.len()],
+                captures: vec![vec![]; tree.len()],
+                ..Resolution::default()
+            },
+            scopes: vec![],
+            functions: vec![],
+        };
+        context.push_scope(ScopeKind::Unique);
+        for name in BUILTIN_TYPES {
+            context.declare_builtin(name);
+        }
+
+        context.push_scope(ScopeKind::Unique);
+        for root in tree.roots() {
+            context.declare_item(root);
+        }
+        for root in tree.roots() {
+            context.visit(root);
+        }
+        context.resolution
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum ScopeKind {
+    /// Names must be unique, as in module scope and parameter lists.
+    Unique,
+    /// Later declarations shadow earlier ones.
+    Block,
+}
+
+struct Scope {
+    kind: ScopeKind,
+    decls: Vec<DeclId>,
+    /// The last declaration of each name, which hides earlier ones.
+    names: HashMap<String, DeclId>,
+    /// How many functions enclose the scope.
+    depth: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Function {
+    Decl,
+    /// A closure or function expression, which can capture locals.
+    Closure(Node),
+}
+
+struct Context<'r, 't> {
+    resolver: &'r Resolver,
+    tree: &'t ParseTree,
+    tokens: &'t TokenizedBuffer<'t>,
+    resolution: Resolution,
+    scopes: Vec<Scope>,
+    functions: Vec<Function>,
+}
+
+impl<'t> Context<'_, 't> {
+    fn push_scope(&mut self, kind: ScopeKind) {
+        self.scopes.push(Scope {
+            kind,
+            decls: vec![],
+            names: HashMap::new(),
+            depth: self.functions.len(),
+        });
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare_builtin(&mut self, name: &str) {
+        let id = DeclId(self.resolution.decls.len());
+        self.resolution.decls.push(Decl {
+            name: name.to_string(),
+            kind: DeclKind::BuiltinType,
+            node: None,
+        });
+        self.add_to_scope(name, id);
+    }
+
+    fn add_to_scope(&mut self, name: &str, id: DeclId) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.decls.push(id);
+        scope.names.insert(name.to_string(), id);
+    }
+
+    /// Declares the name `node` introduces in the innermost scope.
+    fn declare(&mut self, node: Node, kind: DeclKind) -> DeclId {
+        let name = self.tree.text(self.tokens, node).to_string();
+        let scope = self.scopes.last().unwrap();
+        let duplicate = scope.kind == ScopeKind::Unique && scope.names.contains_key(&name);
+        if duplicate {
+            let message = format!(
+                "the name `{}` is defined more than once in this scope",
+                name
+            );
+            self.emit_error(self.tree.token_of(node), Code::R0002, message);
+        }
+
+        let id = DeclId(self.resolution.decls.len());
+        self.add_to_scope(&name, id);
+        self.resolution.decls.push(Decl {
+            name,
+            kind,
+            node: Some(node),
+        });
+        self.resolution.decl_ids[*node] = Some(id);
+        id
+    }
+
+    fn declare_name_of(&mut self, node: Node, kind: DeclKind) {
+        if let Some(name) = self.tree.child(node, NodeKind::DeclName) {
+            self.declare(name, kind);
+        }
+    }
+
+    /// Declares what a module-level node makes visible to the whole module.
+    fn declare_item(&mut self, node: Node) {
+        match self.tree.kind_of(node) {
+            NodeKind::FuncDecl => self.declare_name_of(node, DeclKind::Func),
+            NodeKind::TypeDecl => self.declare_name_of(node, DeclKind::Type),
+            NodeKind::InterfaceDecl => self.declare_name_of(node, DeclKind::Interface),
+            NodeKind::LetDecl => self.declare_name_of(node, DeclKind::Local { mutable: false }),
+            NodeKind::VarDecl => self.declare_name_of(node, DeclKind::Local { mutable: true }),
+            NodeKind::ConstDecl => self.declare_name_of(node, DeclKind::Const),
+            NodeKind::Import => self.declare_import(node),
+            _ => {}
+        }
+    }
+
+    /// `import a.b` declares `b`, `import a.b as c` declares `c`, and
+    /// `import a.{b, c as d}` declares `b` and `d`.
+    fn declare_import(&mut self, node: Node) {
+        if let Some(list) = self.tree.child(node, NodeKind::ImportList) {
+            for item in self.tree.children(list) {
+                if self.tree.kind_of(item) != NodeKind::ImportItem {
+                    continue;
+                }
+                match self.alias_of(item) {
+                    Some(alias) => self.declare(alias, DeclKind::Import),
+                    None => self.declare(item, DeclKind::Import),
+                };
+            }
+        } else if let Some(alias) = self.alias_of(node) {
+            self.declare(alias, DeclKind::Import);
+        } else if let Some(path) = self.tree.child(node, NodeKind::ModulePath) {
+            if let Some(last) = self.tree.children(path).last() {
+                if self.tree.kind_of(last) == NodeKind::PathSegment {
+                    self.declare(last, DeclKind::Import);
+                }
+            }
+        }
+    }
+
+    fn alias_of(&self, node: Node) -> Option<Node> {
+        let alias = self.tree.child(node, NodeKind::ImportAlias)?;
+        self.tree.child(alias, NodeKind::DeclName)
+    }
+
+    fn visit_children(&mut self, node: Node) {
+        for child in self.tree.children(node) {
+            self.visit(child);
+        }
+    }
+
+    fn visit(&mut self, node: Node) {
+        match self.tree.kind_of(node) {
+            NodeKind::Name => self.resolve_use(node, false),
+            NodeKind::TypeName => self.resolve_use(node, true),
+
+            NodeKind::FuncDecl => self.visit_func_decl(node),
+            NodeKind::FuncExpr | NodeKind::ClosureExpr => self.visit_closure(node),
+            NodeKind::TypeDecl => self.visit_type_decl(node),
+            NodeKind::ImplDecl | NodeKind::InterfaceDecl => self.visit_impl(node),
+            NodeKind::LetDecl | NodeKind::VarDecl | NodeKind::ConstDecl => self.visit_binding(node),
+            NodeKind::Block => {
+                self.push_scope(ScopeKind::Block);
+                self.visit_children(node);
+                self.pop_scope();
+            }
+            NodeKind::For => self.visit_for(node),
+            NodeKind::MatchArm => {
+                self.push_scope(ScopeKind::Block);
+                let mut children = self.tree.children(node);
+                if let Some(pattern) = children.next() {
+                    self.declare_pattern(pattern);
+                }
+                for child in children {
+                    self.visit(child);
+                }
+                self.pop_scope();
+            }
+
+            // Names that do not refer to declarations in scope, or were
+            // declared before the walk.
+            NodeKind::DeclName
+            | NodeKind::Import
+            | NodeKind::ModuleDecl
+            | NodeKind::Attribute
+            | NodeKind::Pub
+            | NodeKind::Missing
+            | NodeKind::Error => {}
+            _ => self.visit_children(node),
+        }
+    }
+
+    /// Declares a local, or finds the declaration made for a module-level
+    /// binding before the walk.
+    fn visit_binding(&mut self, node: Node) {
+        let name = self.tree.child(node, NodeKind::DeclName);
+        for child in self.tree.children(node) {
+            if Some(child) != name {
+                self.visit(child);
+            }
+        }
+        let Some(name) = name else {
+            return;
+        };
+        if self.resolution.decl_ids[*name].is_none() {
+            let kind = match self.tree.kind_of(node) {
+                NodeKind::ConstDecl => DeclKind::Const,
+                kind => DeclKind::Local {
+                    mutable: kind == NodeKind::VarDecl,
+                },
+            };
+            self.declare(name, kind);
+        }
+    }
+
+    fn visit_func_decl(&mut self, node: Node) {
+        let name = self.tree.child(node, NodeKind::DeclName);
+        if let Some(name) = name {
+            if self.resolution.decl_ids[*name].is_none() {
+                self.declare(name, DeclKind::Func);
+            }
+        }
+        self.functions.push(Function::Decl);
+        self.visit_signature(node);
+        self.functions.pop();
+    }
+
+    fn visit_closure(&mut self, node: Node) {
+        self.functions.push(Function::Closure(node));
+        self.visit_signature(node);
+        self.functions.pop();
+    }
+
+    /// Type parameters, then parameters, each in a scope of their own, and
+    /// the return type and body inside both.
+    fn visit_signature(&mut self, node: Node) {
+        self.push_scope(ScopeKind::Unique);
+        let mut scopes = 1;
+        for child in self.tree.children(node) {
+            match self.tree.kind_of(child) {
+                NodeKind::TypeParamList => self.visit_type_params(child),
+                NodeKind::ParamList | NodeKind::ClosureParams => {
+                    self.push_scope(ScopeKind::Unique);
+                    scopes += 1;
+                    for param in self.tree.children(child) {
+                        if self.tree.kind_of(param) != NodeKind::Param {
+                            continue;
+                        }
+                        self.visit_children(param);
+                        self.check_receiver(node, child, param);
+                        self.declare_name_of(param, DeclKind::Param);
+                    }
+                }
+                _ => self.visit(child),
+            }
+        }
+        for _ in 0..scopes {
+            self.pop_scope();
+        }
+    }
+
+    /// Methods are only reachable through their type or interface, so
+    /// their names go in a scope of their own rather than the module's.
+    fn visit_impl(&mut self, node: Node) {
+        self.push_scope(ScopeKind::Unique);
+        for child in self.tree.children(node) {
+            if self.tree.kind_of(child) == NodeKind::FuncDecl {
+                self.declare_name_of(child, DeclKind::Func);
+            }
+        }
+        self.pop_scope();
+        self.visit_children(node);
+    }
+
+    /// `self` without a type is the receiver of a method, so it has to be
+    /// the first parameter of a function in an `impl` or `interface`.
+    fn check_receiver(&mut self, func: Node, list: Node, param: Node) {
+        let Some(name) = self.tree.child(param, NodeKind::DeclName) else {
+            return;
+        };
+        if self.tree.text(self.tokens, name) != "self" || self.tree.children(param).count() > 1 {
+            return;
+        }
+        let first = self.tree.child(list, NodeKind::Param);
+        let in_impl = self.tree.kind_of(func) == NodeKind::FuncDecl
+            && self.tree.parent(func).is_some_and(|parent| {
+                matches!(
+                    self.tree.kind_of(parent),
+                    NodeKind::ImplDecl | NodeKind::InterfaceDecl
+                )
+            });
+        let message = match (in_impl, first == Some(param)) {
+            (true, true) => return,
+            (true, false) => "`self` has to be the first parameter",
+            (false, _) => "`self` can only be a parameter of a method in an `impl` or `interface`",
+        };
+        self.emit_error(self.tree.token_of(name), Code::R0005, message.to_string());
+    }
+
+    fn visit_type_params(&mut self, list: Node) {
+        let params: Vec<Node> = self
+            .tree
+            .children(list)
+            .filter(|&param| self.tree.kind_of(param) == NodeKind::TypeParam)
+            .collect();
+        for &param in &params {
+            self.declare_name_of(param, DeclKind::TypeParam);
+        }
+        for &param in &params {
+            if let Some(bound) = self.tree.child(param, NodeKind::TypeBound) {
+                self.visit_children(bound);
+            }
+        }
+    }
+
+    fn visit_type_decl(&mut self, node: Node) {
+        let name = self.tree.child(node, NodeKind::DeclName);
+        if let Some(name) = name {
+            if self.resolution.decl_ids[*name].is_none() {
+                self.declare(name, DeclKind::Type);
+            }
+        }
+        self.push_scope(ScopeKind::Unique);
+        for child in self.tree.children(node) {
+            match self.tree.kind_of(child) {
+                NodeKind::TypeParamList => self.visit_type_params(child),
+                NodeKind::StructType | NodeKind::EnumType => self.visit_members(child),
+                _ => self.visit(child),
+            }
+        }
+        self.pop_scope();
+    }
+
+    /// Fields and variants, whose names must differ from each other but are
+    /// not visible as names.
+    fn visit_members(&mut self, node: Node) {
+        let mut seen: Vec<&str> = vec![];
+        for member in self.tree.children(node) {
+            let Some(name) = self.tree.child(member, NodeKind::DeclName) else {
+                continue;
+            };
+            let text = self.tree.text(self.tokens, name);
+            if seen.contains(&text) {
+                let member = match self.tree.kind_of(node) {
+                    NodeKind::StructType => "field",
+                    _ => "variant",
+                };
+                let message = format!("the {} `{}` is defined more than once", member, text);
+                self.emit_error(self.tree.token_of(name), Code::R0002, message);
+            }
+            seen.push(text);
+            self.visit_children(member);
+        }
+    }
+
+    fn visit_for(&mut self, node: Node) {
+        let name = self.tree.child(node, NodeKind::DeclName);
+        let mut children = self
+            .tree
+            .children(node)
+            .filter(|&child| Some(child) != name);
+        if let Some(iterable) = children.next() {
+            self.visit(iterable);
+        }
+        self.push_scope(ScopeKind::Block);
+        if let Some(name) = name {
+            self.declare(name, DeclKind::Local { mutable: false });
+        }
+        for child in children {
+            self.visit(child);
+        }
+        self.pop_scope();
+    }
+
+    /// Declares the bindings of a pattern. The alternatives of `a | b` have
+    /// to bind the same names, so later alternatives reuse the bindings of
+    /// the first.
+    fn declare_pattern(&mut self, pattern: Node) {
+        let mut bound = vec![];
+        self.bind_pattern(pattern, &mut bound);
+    }
+
+    fn bind_pattern(&mut self, node: Node, bound: &mut Vec<DeclId>) {
+        match self.tree.kind_of(node) {
+            NodeKind::BindingPattern => self.bind(node, bound),
+            NodeKind::FieldPattern => match self.tree.children(node).next() {
+                Some(pattern) => self.bind_pattern(pattern, bound),
+                // `{x}` is short for `{x: x}`.
+                None => self.bind(node, bound),
+            },
+            NodeKind::AltPattern => {
+                let mut alternatives = self.tree.children(node);
+                if let Some(first) = alternatives.next() {
+                    self.bind_pattern(first, bound);
+                }
+                let shared = bound.clone();
+                for alternative in alternatives {
+                    let mut again = vec![];
+                    self.bind_pattern(alternative, &mut again);
+                    for id in again {
+                        let name = self.resolution.decl(id).name.clone();
+                        let first = shared
+                            .iter()
+                            .find(|&&first| self.resolution.decl(first).name == name);
+                        if let (Some(&first), Some(node)) = (first, self.resolution.decl(id).node) {
+                            self.resolution.decl_ids[*node] = Some(first);
+                            let scope = self.scopes.last_mut().unwrap();
+                            scope.decls.retain(|&d| d != id);
+                            scope.names.insert(name, first);
+                        } else {
+                            bound.push(id);
+                        }
+                    }
+                }
+            }
+            NodeKind::PatternPath => {
+                // `Shape.Circle` names a type and its variant. A single
+                // segment is a variant of the type of the value matched,
+                // which the type checker looks up.
+                let segments: Vec<Node> = self.tree.children(node).collect();
+                if segments.len() > 1 {
+                    self.resolve_use(segments[0], false);
+                }
+            }
+            NodeKind::StructPattern => {
+                let mut children = self.tree.children(node);
+                if let Some(path) = children.next() {
+                    let segments: Vec<Node> = self.tree.children(path).collect();
+                    match segments.as_slice() {
+                        [name] => self.resolve_use(*name, true),
+                        _ => self.bind_pattern(path, bound),
+                    }
+                }
+                for child in children {
+                    self.bind_pattern(child, bound);
+                }
+            }
+            NodeKind::LiteralPattern | NodeKind::RangePattern => self.visit_children(node),
+            _ => {
+                for child in self.tree.children(node) {
+                    self.bind_pattern(child, bound);
+                }
+            }
+        }
+    }
+
+    fn bind(&mut self, node: Node, bound: &mut Vec<DeclId>) {
+        let name = self.tree.text(self.tokens, node);
+        if bound
+            .iter()
+            .any(|&id| self.resolution.decl(id).name == name)
+        {
+            let message = format!("`{}` is bound more than once in this pattern", name);
+            self.emit_error(self.tree.token_of(node), Code::R0002, message);
+        }
+        let id = self.declare(node, DeclKind::PatternBinding);
+        bound.push(id);
+    }
+
+    fn lookup(&self, name: &str) -> Option<(DeclId, usize)> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| Some((*scope.names.get(name)?, scope.depth)))
+    }
+
+    fn resolve_use(&mut self, node: Node, as_type: bool) {
+        let name = self.tree.text(self.tokens, node);
+        let token = self.tree.token_of(node);
+        let Some((id, depth)) = self.lookup(name) else {
+            let mut message = match as_type {
+                true => format!("cannot find type `{}` in this scope", name),
+                false => format!("cannot find `{}` in this scope", name),
+            };
+            if let Some(suggestion) = self.suggest(name, as_type) {
+                message.push_str(&format!(", did you mean `{}`?", suggestion));
+            }
+            self.emit_error(token, Code::R0001, message);
+            return;
+        };
+        let kind = self.resolution.decl(id).kind;
+        if as_type && !kind.is_type() {
+            let message = format!("`{}` is not a type", name);
+            self.emit_error(token, Code::R0003, message);
+            return;
+        }
+        self.resolution.decl_ids[*node] = Some(id);
+        if kind.is_local() && depth < self.functions.len() {
+            self.capture(id, depth, token);
+        }
+    }
+
+    /// Records that every closure between the declaration of a local and
+    /// its use captures it. Named functions cannot capture.
+    fn capture(&mut self, id: DeclId, depth: usize, token: Token) {
+        if self.functions[depth..].contains(&Function::Decl) {
+            let name = &self.resolution.decl(id).name;
+            let message = format!(
+                "a nested function cannot refer to `{}` of the function around it, use a closure instead",
+                name
+            );
+            self.emit_error(token, Code::R0004, message);
+            return;
+        }
+        for function in &self.functions[depth..] {
+            let Function::Closure(closure) = *function else {
+                continue;
+            };
+            let captures = &mut self.resolution.captures[*closure];
+            if !captures.contains(&id) {
+                captures.push(id);
+            }
+        }
+    }
+
+    /// The visible name closest to `name` by edit distance, if it is close
+    /// enough to be a likely typo.
+    fn suggest(&self, name: &str, as_type: bool) -> Option<String> {
+        let limit = (name.chars().count() / 3).max(1);
+        let mut best: Option<(usize, &str)> = None;
+        for scope in self.scopes.iter().rev() {
+            for &id in scope.decls.iter().rev() {
+                let decl = self.resolution.decl(id);
+                if as_type && !decl.kind.is_type() {
+                    continue;
+                }
+                let distance = edit_distance(name, &decl.name);
+                if distance <= limit && best.is_none_or(|(best, _)| distance < best) {
+                    best = Some((distance, &decl.name));
+                }
+            }
+        }
+        best.map(|(_, name)| name.to_string())
+    }
+
+    fn emit_error(&self, token: Token, code: Code, message: String) {
+        self.resolver.diagnostics.borrow_mut().emit(Diagnostic {
+            level: diagnostics::Level::Error,
+            code: Some(code),
+            message,
+            location: self.tokens.location_of(&token).into_owned(),
+            span: self.tokens.span_of(&token),
+        });
+    }
+}
+
+/// The edit distance between `a` and `b` in characters, where swapping
+/// two adjacent characters counts as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    rows[0] = (0..=b.len()).collect();
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{edit_distance, DeclKind, Resolution};
+    use crate::diagnostics::{Code, Diagnostic};
+    use crate::fixture::Fixture;
+    use crate::parse_tree::NodeKind;
+
+    fn resolve(source: &str) -> (Fixture<'_>, Resolution, Vec<Diagnostic<'static>>) {
+        let fixture = Fixture::parse(source);
+        fixture.assert_clean();
+        let resolution = fixture.resolve();
+        let diagnostics = fixture.take();
+        (fixture, resolution, diagnostics)
+    }
+
+    fn codes(source: &str) -> Vec<Code> {
+        resolve(source).2.iter().filter_map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("lenght", "length"), 1);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let source = "func main() {\n  let x = twice(1)\n  let x = x + 1\n  x\n}\n\
+                      func twice(x: Int) -> Int { x * 2 }";
+        let (Fixture { tokens, tree, .. }, resolution, diagnostics) = resolve(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let uses: Vec<(&str, DeclKind, usize)> = tree
+            .postorder()
+            .filter(|&node| tree.kind_of(node) == NodeKind::Name)
+            .map(|node| {
+                let decl = resolution.decl(resolution.decl_of(node).unwrap());
+                let row = tokens.location_of(&tree.token_of(decl.node.unwrap())).row;
+                (decl.name.as_str(), decl.kind, row)
+            })
+            .collect();
+        let local = DeclKind::Local { mutable: false };
+        assert_eq!(
+            uses,
+            vec![
+                ("twice", DeclKind::Func, 5),
+                ("x", local, 1),
+                ("x", local, 2),
+                ("x", DeclKind::Param, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let (_, _, diagnostics) = resolve("func f(count: Int) { cuont }");
+        assert_eq!(
+            diagnostics[0].message,
+            "cannot find `cuont` in this scope, did you mean `count`?"
+        );
+        assert_eq!(codes("func f() {}\ntype f = Int"), vec![Code::R0002]);
+        assert_eq!(codes("func f(a: Int, a: Int) {}"), vec![Code::R0002]);
+        assert_eq!(
+            codes("type P = struct { x: Int, x: Int }"),
+            vec![Code::R0002]
+        );
+        assert_eq!(codes("let x = 1\nlet y: x = 2"), vec![Code::R0003]);
+        assert_eq!(codes("let y: Strng = 2"), vec![Code::R0001]);
+        assert_eq!(codes("func f() { let x = 1\n let x = x }"), vec![]);
+        assert_eq!(
+            codes("func f() { match 1 { (a, a) => 1, B(b) | C(b) => b, Shape.C => 0 } }"),
+            vec![Code::R0002, Code::R0001]
+        );
+    }
+
+    #[test]
+    fn test_methods() {
+        let source = "type P = struct { x: Int }\n\
+                      impl P {\n  func new() -> P { P { x: 0 } }\n  func x(self) -> Int { self.x }\n}\n\
+                      func new() {}";
+        assert_eq!(codes(source), vec![]);
+        // Methods are not in scope by their own name.
+        assert_eq!(
+            codes("impl Int {\n  func a() {}\n  func b() { a() }\n}"),
+            vec![Code::R0001]
+        );
+        assert_eq!(
+            codes("impl Int {\n  func a() {}\n  func a() {}\n}"),
+            vec![Code::R0002]
+        );
+        assert_eq!(codes("func f(self) {}"), vec![Code::R0005]);
+        assert_eq!(
+            codes("impl Int { func f(a: Int, self) {} }"),
+            vec![Code::R0005]
+        );
+        assert_eq!(codes("impl Q { }"), vec![Code::R0001]);
+        let source = "interface Show {\n  func show(self) -> String\n}\n\
+                      impl Show for Int {\n  func show(self) -> String { \"\" }\n}\n\
+                      func print[T: Show](x: T) {}";
+        assert_eq!(codes(source), vec![]);
+        assert_eq!(codes("impl Shw for Int { }"), vec![Code::R0001]);
+    }
+
+    #[test]
+    fn test_captures() {
+        let source = "func f(a: Int) {\n  let b = 1\n  let g = |x| a + x + |y| b + y + a\n}";
+        let (Fixture { tree, .. }, resolution, diagnostics) = resolve(source);
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let closures: Vec<Vec<&str>> = tree
+            .postorder()
+            .filter(|&node| tree.kind_of(node) == NodeKind::ClosureExpr)
+            .map(|node| {
+                resolution
+                    .captures_of(node)
+                    .iter()
+                    .map(|&id| resolution.decl(id).name.as_str())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(closures, vec![vec!["b", "a"], vec!["a", "b"]]);
+
+        assert_eq!(
+            codes("func f(a: Int) {\n  func g() -> Int { a }\n}"),
+            vec![Code::R0004]
+        );
+    }
+}
@@ -82,13 +82,14 @@ where
             }
 
             self.store = Storage { stack };
+            self.is_heap = false;
         }
 
         item
     }
 
     pub fn capacity(&self) -> usize {
-        if self.len < N {
+        if !self.is_heap {
             N
         } else {
             unsafe { self.store.heap.1 }
@@ -112,7 +113,7 @@ where
     }
 
     fn as_ptr(&self) -> *const T {
-        if self.len < N {
+        if !self.is_heap {
             unsafe { self.store.stack.as_ptr() as *const T }
         } else {
             unsafe { self.store.heap.0 as *const T }
@@ -120,10 +121,10 @@ where
     }
 
     fn as_mut_ptr(&mut self) -> *mut T {
-        if self.len < N {
+        if !self.is_heap {
             unsafe { self.store.stack.as_mut_ptr() as *mut T }
         } else {
-            unsafe { self.store.heap.0 as *mut T }
+            unsafe { self.store.heap.0 }
         }
     }
 
@@ -131,7 +132,7 @@ where
         if self.is_heap {
             unsafe { std::slice::from_raw_parts(self.store.heap.0, self.len) }
         } else {
-            unsafe { mem::transmute(self.store.stack.as_slice()) }
+            unsafe { mem::transmute::<&[MaybeUninit<T>], &[T]>(self.store.stack.as_slice()) }
         }
     }
 
@@ -139,7 +140,7 @@ where
         if self.is_heap {
             unsafe { std::slice::from_raw_parts_mut(self.store.heap.0, self.capacity()) }
         } else {
-            unsafe { mem::transmute(self.store.stack.as_mut()) }
+            unsafe { mem::transmute::<&mut [MaybeUninit<T>], &mut [T]>(self.store.stack.as_mut()) }
         }
     }
 }
@@ -162,7 +163,7 @@ where
 {
     fn from(slice: &'a mut [T]) -> Self {
         let len = slice.len();
-        let vec = if len < N {
+        if len < N {
             SmallVec {
                 store: Storage {
                     stack: unsafe {
@@ -187,8 +188,7 @@ where
                 small.is_heap = true;
             }
             small
-        };
-        vec
+        }
     }
 }
 
@@ -0,0 +1,536 @@
+use std::path::{Path, PathBuf};
+use std::{cell::RefCell, rc::Rc};
+
+use crate::attributes::AttributeRegistry;
+use crate::bounds::{BoundsChecker, Trap};
+use crate::check::TypeChecker;
+use crate::consteval::ConstEvaluator;
+use crate::diagnostics::{CollectingEmitter, Diagnostic, Level};
+use crate::error::CompileError;
+use crate::flow::FlowChecker;
+use crate::lexer::Lexer;
+use crate::lint::LintRegistry;
+use crate::parser::Parser;
+use crate::resolve::Resolver;
+
+/// A diagnostic a test file expects, written as a comment either at the end
+/// of the offending line:
+///
+/// ```text
+/// 1 + $ // ERROR(L0001): invalid character
+/// ```
+///
+/// or on its own line below it, with carets marking the columns:
+///
+/// ```text
+/// 1 + $
+/// //  ^ ERROR(L0001): invalid character
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expectation {
+    pub line: usize,
+    pub columns: Option<(usize, usize)>,
+    pub level: Level,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Annotations {
+    pub expectations: Vec<Expectation>,
+    /// Lines of `// RUN-OUTPUT:` comments, if the file has any.
+    pub run_output: Option<Vec<String>>,
+}
+
+pub struct Compiled {
+    pub diagnostics: Vec<Diagnostic<'static>>,
+    /// The bounds checks the program makes when it runs, if it compiled.
+    pub traps: Vec<Trap>,
+    /// What the program printed, `None` while programs cannot be executed.
+    pub output: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Mismatch {
+    Unexpected(Diagnostic<'static>),
+    Missing(Expectation),
+    Output { expected: String, actual: String },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Unexpected(diagnostic) => write!(
+                f,
+                "unexpected diagnostic at {}:{}: {}",
+                diagnostic.location.row + 1,
+                diagnostic.location.col + 1,
+                format_annotation(diagnostic),
+            ),
+            Mismatch::Missing(expectation) => {
+                write!(f, "missing diagnostic at {}", expectation.line + 1)?;
+                if let Some((start, _)) = expectation.columns {
+                    write!(f, ":{}", start + 1)?;
+                }
+                write!(f, ": {}", expectation.level)?;
+                if let Some(code) = &expectation.code {
+                    write!(f, "({})", code)?;
+                }
+                write!(f, ": {}", expectation.message)
+            }
+            Mismatch::Output { expected, actual } => write!(
+                f,
+                "output mismatch\n--- expected\n{}\n--- actual\n{}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub mismatches: Vec<Mismatch>,
+    /// Set when the file has `RUN-OUTPUT` expectations that could not be
+    /// checked because the program was not executed.
+    pub output_unchecked: bool,
+    pub blessed: bool,
+}
+
+impl FileReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Runs the compiler over `source`, collecting its diagnostics instead of
+/// printing them.
+pub fn compile(file: &str, source: &str) -> Compiled {
+    compile_with(file, source, LintRegistry::new(), true)
+}
+
+/// Like `compile`, with the lints of `lints` at the levels it sets, and
+/// without runtime bounds checks unless `bounds_checks` is set.
+pub fn compile_with(
+    file: &str,
+    source: &str,
+    lints: LintRegistry,
+    bounds_checks: bool,
+) -> Compiled {
+    let emitter = Rc::new(RefCell::new(CollectingEmitter::new()));
+    let tokens = Lexer::new_file(file, source)
+        .with_emitter(emitter.clone())
+        .tokenize();
+    let tree = Parser::new(&tokens).with_emitter(emitter.clone()).parse();
+    // Later phases expect well formed input, so each only runs when the
+    // phases before it found no errors. Attributes that fail their checks
+    // are left out of `attributes`, so their errors do not stop the rest.
+    let parsed = !has_errors(&emitter.borrow());
+    let attributes = AttributeRegistry::new()
+        .with_lints(&lints)
+        .with_emitter(emitter.clone())
+        .check(&tree, &tokens);
+    let attribute_errors = errors(&emitter.borrow());
+    let has_errors = |emitter: &CollectingEmitter| errors(emitter) > attribute_errors;
+    let mut traps = vec![];
+    if parsed {
+        let resolution = Resolver::new()
+            .with_emitter(emitter.clone())
+            .resolve(&tree, &tokens);
+        if !has_errors(&emitter.borrow()) {
+            let typing =
+                TypeChecker::new()
+                    .with_emitter(emitter.clone())
+                    .check(&tree, &tokens, &resolution);
+            if !has_errors(&emitter.borrow()) {
+                FlowChecker::new().with_emitter(emitter.clone()).check(
+                    &tree,
+                    &tokens,
+                    &resolution,
+                    &typing,
+                );
+                ConstEvaluator::new()
+                    .with_emitter(emitter.clone())
+                    .evaluate(&tree, &tokens, &resolution, &typing);
+                if !has_errors(&emitter.borrow()) {
+                    lints.with_emitter(emitter.clone()).check(
+                        &tree,
+                        &tokens,
+                        &resolution,
+                        &typing,
+                        &attributes,
+                    );
+                    traps = BoundsChecker::new().with_checks(bounds_checks).check(
+                        &tree,
+                        &tokens,
+                        &resolution,
+                        &typing,
+                    );
+                }
+            }
+        }
+    }
+    let diagnostics = emitter.borrow_mut().take();
+    Compiled {
+        diagnostics,
+        traps,
+        output: None,
+    }
+}
+
+/// Runs the compiler over `source` for callers that only need to know
+/// whether it compiles: fails with the first error, and returns the
+/// warnings otherwise.
+pub fn check(file: &str, source: &str) -> crate::Result<Vec<Diagnostic<'static>>> {
+    let mut diagnostics = compile(file, source).diagnostics;
+    match diagnostics
+        .iter()
+        .position(|diagnostic| diagnostic.level == Level::Error)
+    {
+        Some(first) => Err(CompileError::from(diagnostics.swap_remove(first)).into()),
+        None => Ok(diagnostics),
+    }
+}
+
+fn has_errors(emitter: &CollectingEmitter) -> bool {
+    errors(emitter) > 0
+}
+
+fn errors(emitter: &CollectingEmitter) -> usize {
+    emitter
+        .diagnostics()
+        .iter()
+        .filter(|diagnostic| diagnostic.level == Level::Error)
+        .count()
+}
+
+/// Checks every `.mik` file below `root`, in path order. With `bless`,
+/// files are rewritten to expect what the compiler currently reports.
+pub fn run(root: &Path, bless: bool) -> crate::Result<Vec<FileReport>> {
+    let mut files = vec![];
+    collect_files(root, &mut files)?;
+    files.sort();
+    files.iter().map(|path| run_file(path, bless)).collect()
+}
+
+pub fn run_file(path: &Path, bless: bool) -> crate::Result<FileReport> {
+    let source = std::fs::read_to_string(path)?;
+    let annotations = parse_annotations(&source);
+    let compiled = compile(&path.to_string_lossy(), &source);
+
+    let mut mismatches = compare(&annotations.expectations, &compiled.diagnostics);
+    let mut output_unchecked = false;
+    if let Some(expected) = &annotations.run_output {
+        match &compiled.output {
+            Some(actual) => {
+                let expected = expected.join("\n");
+                if expected.trim_end() != actual.trim_end() {
+                    mismatches.push(Mismatch::Output {
+                        expected,
+                        actual: actual.clone(),
+                    });
+                }
+            }
+            None => output_unchecked = true,
+        }
+    }
+
+    let blessed = bless && !mismatches.is_empty();
+    if blessed {
+        std::fs::write(path, bless_source(&source, &compiled))?;
+        mismatches.clear();
+    }
+
+    Ok(FileReport {
+        path: path.to_path_buf(),
+        mismatches,
+        output_unchecked,
+        blessed,
+    })
+}
+
+/// Adds `dir` to `files` if it is a file, and every `.mik` file below it if
+/// it is a directory.
+pub fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> crate::Result<()> {
+    if dir.is_file() {
+        files.push(dir.to_path_buf());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "mik") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_annotations(source: &str) -> Annotations {
+    let mut annotations = Annotations::default();
+    let mut target = 0;
+    for (index, line) in source.lines().enumerate() {
+        if let Some(output) = run_output_line(line) {
+            annotations
+                .run_output
+                .get_or_insert_with(Vec::new)
+                .push(output.to_string());
+            continue;
+        }
+        if let Some((columns, (level, code, message))) = caret_line(line) {
+            annotations.expectations.push(Expectation {
+                line: target,
+                columns: Some(columns),
+                level,
+                code,
+                message,
+            });
+            continue;
+        }
+        target = index;
+        for (_, (level, code, message)) in trailing_annotations(line) {
+            annotations.expectations.push(Expectation {
+                line: index,
+                columns: None,
+                level,
+                code,
+                message,
+            });
+        }
+    }
+    annotations
+}
+
+/// Pairs expectations with diagnostics. Each expectation consumes the first
+/// unused diagnostic on its line with the same level and code whose message
+/// contains the expected text.
+pub fn compare(expectations: &[Expectation], diagnostics: &[Diagnostic<'static>]) -> Vec<Mismatch> {
+    let mut used = vec![false; diagnostics.len()];
+    let mut mismatches = vec![];
+    for expectation in expectations {
+        let found = diagnostics
+            .iter()
+            .enumerate()
+            .position(|(index, diagnostic)| !used[index] && matches(expectation, diagnostic));
+        match found {
+            Some(index) => used[index] = true,
+            None => mismatches.push(Mismatch::Missing(expectation.clone())),
+        }
+    }
+    for (diagnostic, used) in diagnostics.iter().zip(used) {
+        if !used {
+            mismatches.push(Mismatch::Unexpected(diagnostic.clone()));
+        }
+    }
+    mismatches
+}
+
+fn matches(expectation: &Expectation, diagnostic: &Diagnostic) -> bool {
+    let location = &diagnostic.location;
+    let code_matches = match &expectation.code {
+        Some(code) => diagnostic
+            .code
+            .is_some_and(|actual| actual.to_string() == *code),
+        None => true,
+    };
+    let column_matches = match expectation.columns {
+        Some((start, end)) => (start..=end).contains(&location.col),
+        None => true,
+    };
+    expectation.line == location.row
+        && expectation.level == diagnostic.level
+        && code_matches
+        && column_matches
+        && diagnostic.message.contains(&expectation.message)
+}
+
+/// Rewrites `source` so that its annotations describe `compiled`. Existing
+/// diagnostic annotations are dropped and regenerated; `RUN-OUTPUT` lines
+/// are only replaced when there is output to replace them with.
+pub fn bless_source(source: &str, compiled: &Compiled) -> String {
+    let mut lines = vec![];
+    let line_count = source.lines().count();
+    for (index, line) in source.lines().enumerate() {
+        if caret_line(line).is_some()
+            || (compiled.output.is_some() && run_output_line(line).is_some())
+        {
+            continue;
+        }
+        let mut line = match trailing_annotations(line).first() {
+            Some((start, _)) => line[..*start].trim_end().to_string(),
+            None => line.to_string(),
+        };
+
+        let mut carets = vec![];
+        let on_line = compiled.diagnostics.iter().filter(|diagnostic| {
+            let row = diagnostic.location.row;
+            row == index || (index + 1 == line_count && row > index)
+        });
+        for diagnostic in on_line {
+            let col = diagnostic.location.col;
+            if col < 2 {
+                line.push_str(&format!(" // {}", format_annotation(diagnostic)));
+            } else {
+                carets.push(format!(
+                    "//{}^ {}",
+                    " ".repeat(col - 2),
+                    format_annotation(diagnostic)
+                ));
+            }
+        }
+        lines.push(line);
+        lines.extend(carets);
+    }
+    if let Some(output) = &compiled.output {
+        lines.extend(
+            output
+                .lines()
+                .map(|line| format!("// RUN-OUTPUT: {}", line)),
+        );
+    }
+
+    let mut blessed = lines.join("\n");
+    if source.ends_with('\n') || source.is_empty() {
+        blessed.push('\n');
+    }
+    blessed
+}
+
+fn format_annotation(diagnostic: &Diagnostic) -> String {
+    match diagnostic.code {
+        Some(code) => format!("{}({}): {}", diagnostic.level, code, diagnostic.message),
+        None => format!("{}: {}", diagnostic.level, diagnostic.message),
+    }
+}
+
+type Annotation = (Level, Option<String>, String);
+
+/// Parses `ERROR(P0003): message`, `WARN: message` and the like.
+fn parse_annotation(text: &str) -> Option<Annotation> {
+    let text = text.trim_start();
+    let (level, rest) = [
+        ("ERROR", Level::Error),
+        ("WARN", Level::Warning),
+        ("INFO", Level::Info),
+    ]
+    .into_iter()
+    .find_map(|(keyword, level)| text.strip_prefix(keyword).map(|rest| (level, rest)))?;
+
+    let (code, rest) = match rest.strip_prefix('(') {
+        Some(rest) => {
+            let (code, rest) = rest.split_once(')')?;
+            (Some(code.trim().to_string()), rest)
+        }
+        None => (None, rest),
+    };
+    let message = rest.strip_prefix(':')?;
+    Some((level, code, message.trim().to_string()))
+}
+
+/// Finds every annotation comment on a line, with the byte offset of the
+/// `//` that starts it. A message runs until the next annotation.
+fn trailing_annotations(line: &str) -> Vec<(usize, Annotation)> {
+    let starts: Vec<usize> = line
+        .match_indices("//")
+        .map(|(start, _)| start)
+        .filter(|start| parse_annotation(&line[start + 2..]).is_some())
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &start)| {
+            let end = starts.get(index + 1).copied().unwrap_or(line.len());
+            parse_annotation(&line[start + 2..end]).map(|annotation| (start, annotation))
+        })
+        .collect()
+}
+
+fn caret_line(line: &str) -> Option<((usize, usize), Annotation)> {
+    let comment = line.trim_start().strip_prefix("//")?;
+    let carets = comment.trim_start();
+    if !carets.starts_with('^') {
+        return None;
+    }
+    let start = line.len() - carets.len();
+    let count = carets.chars().take_while(|&c| c == '^').count();
+    let annotation = parse_annotation(&carets[count..])?;
+    Some(((start, start + count - 1), annotation))
+}
+
+fn run_output_line(line: &str) -> Option<&str> {
+    let comment = line.trim_start().strip_prefix("//")?;
+    let output = comment.trim_start().strip_prefix("RUN-OUTPUT:")?;
+    Some(output.strip_prefix(' ').unwrap_or(output))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::diagnostics::Code;
+    use crate::token::Span;
+    use crate::Error;
+
+    #[test]
+    fn test_parse_annotations() {
+        let source = "1 + $ // ERROR(L0001): invalid character\n\
+                      1 $ $\n\
+                      //^ ERROR(L0001): `$`\n\
+                      //  ^^ ERROR: invalid\n\
+                      // RUN-OUTPUT: 10\n";
+        let annotations = parse_annotations(source);
+        assert_eq!(annotations.expectations.len(), 3);
+        assert_eq!(annotations.expectations[0].line, 0);
+        assert_eq!(annotations.expectations[0].code.as_deref(), Some("L0001"));
+        assert_eq!(annotations.expectations[0].message, "invalid character");
+        assert_eq!(annotations.expectations[1].line, 1);
+        assert_eq!(annotations.expectations[1].columns, Some((2, 2)));
+        assert_eq!(annotations.expectations[2].columns, Some((4, 5)));
+        assert_eq!(annotations.expectations[2].code, None);
+        assert_eq!(annotations.run_output, Some(vec!["10".to_string()]));
+    }
+
+    #[test]
+    fn test_compare() {
+        let source = "1 $\n2 + $ // ERROR(L0001): invalid\n";
+        let compiled = compile("test.mik", source);
+        let mismatches = compare(
+            &parse_annotations(source).expectations,
+            &compiled.diagnostics,
+        );
+        assert_eq!(mismatches.len(), 1);
+        assert!(matches!(&mismatches[0], Mismatch::Unexpected(d) if d.location.row == 0));
+    }
+
+    #[test]
+    fn test_check() {
+        let warnings = check("test.mik", "func f() {\n  let x = 1\n}").unwrap();
+        assert_eq!(warnings[0].code, Some(Code::W0001));
+
+        let error = check("test.mik", "let a = 1\nlet b = c + $\nlet d = e").unwrap_err();
+        let Error::Compile(error) = error else {
+            panic!("expected a compile error, found {:?}", error);
+        };
+        assert_eq!(error.code, Some(Code::L0001));
+        assert_eq!(error.span, Span::new(22, 23));
+        assert_eq!(error.to_string(), "test.mik:2:13: invalid character `$`");
+    }
+
+    #[test]
+    fn test_bless_source() {
+        let source = "$\n1 + $ // ERROR(L0001): stale\n";
+        let blessed = bless_source(source, &compile("test.mik", source));
+        assert_eq!(
+            blessed,
+            "$ // ERROR(L0001): invalid character `$`\n\
+             1 + $\n\
+             //  ^ ERROR(L0001): invalid character `$`\n"
+        );
+        let compiled = compile("test.mik", &blessed);
+        assert!(compare(
+            &parse_annotations(&blessed).expectations,
+            &compiled.diagnostics
+        )
+        .is_empty());
+    }
+}
@@ -1,6 +1,7 @@
+use std::borrow::Cow;
 use std::ops::Deref;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Token(pub usize);
 
 impl Deref for Token {
@@ -11,15 +12,28 @@ impl Deref for Token {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct SourceLocation<'a> {
-    pub file: Option<&'a str>,
+    pub file: Option<Cow<'a, str>>,
     pub row: usize,
     pub col: usize,
 }
 
 impl<'a> SourceLocation<'a> {
     pub fn new(file: Option<&'a str>, row: usize, col: usize) -> Self {
-        Self { file, row, col }
+        Self {
+            file: file.map(Cow::Borrowed),
+            row,
+            col,
+        }
+    }
+
+    pub fn into_owned(self) -> SourceLocation<'static> {
+        SourceLocation {
+            file: self.file.map(|file| Cow::Owned(file.into_owned())),
+            row: self.row,
+            col: self.col,
+        }
     }
 }
 
@@ -59,13 +73,36 @@ pub enum TokenKind {
 
     IntLiteral,
     RealLiteral,
+    StringLiteral,
 
     FuncKeyword,
     TypeKeyword,
     ModuleKeyword,
+    StructKeyword,
+    EnumKeyword,
+    ImportKeyword,
+    AsKeyword,
+    PubKeyword,
+    TrueKeyword,
+    FalseKeyword,
+    LetKeyword,
+    VarKeyword,
+    ConstKeyword,
+    IfKeyword,
+    ElseKeyword,
+    WhileKeyword,
+    ForKeyword,
+    InKeyword,
+    BreakKeyword,
+    ContinueKeyword,
+    ReturnKeyword,
+    MatchKeyword,
+    ImplKeyword,
+    InterfaceKeyword,
 
     HorizontalWhitespace,
     VerticalWhitespace,
+    Comment,
 
     OpenParen,
     CloseParen,
@@ -78,6 +115,34 @@ pub enum TokenKind {
     Minus,
     Star,
     Slash,
+    Percent,
+    Bang,
+    Equal,
+    EqualEqual,
+    BangEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    LessLess,
+    GreaterGreater,
+    Amp,
+    AmpAmp,
+    Pipe,
+    PipePipe,
+    Arrow,
+    FatArrow,
+    At,
+    Colon,
+    Comma,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    Semi,
+    Dot,
+    DotDot,
 
     Eof,
     Invalid,
@@ -90,13 +155,36 @@ impl std::fmt::Display for TokenKind {
 
             TokenKind::IntLiteral => write!(f, "IntLiteral"),
             TokenKind::RealLiteral => write!(f, "RealLiteral"),
+            TokenKind::StringLiteral => write!(f, "StringLiteral"),
 
             TokenKind::FuncKeyword => write!(f, "FuncKeyword"),
             TokenKind::TypeKeyword => write!(f, "TypeKeyword"),
             TokenKind::ModuleKeyword => write!(f, "ModuleKeyword"),
+            TokenKind::StructKeyword => write!(f, "StructKeyword"),
+            TokenKind::EnumKeyword => write!(f, "EnumKeyword"),
+            TokenKind::ImportKeyword => write!(f, "ImportKeyword"),
+            TokenKind::AsKeyword => write!(f, "AsKeyword"),
+            TokenKind::PubKeyword => write!(f, "PubKeyword"),
+            TokenKind::TrueKeyword => write!(f, "TrueKeyword"),
+            TokenKind::FalseKeyword => write!(f, "FalseKeyword"),
+            TokenKind::LetKeyword => write!(f, "LetKeyword"),
+            TokenKind::VarKeyword => write!(f, "VarKeyword"),
+            TokenKind::ImplKeyword => write!(f, "ImplKeyword"),
+            TokenKind::InterfaceKeyword => write!(f, "InterfaceKeyword"),
+            TokenKind::ConstKeyword => write!(f, "ConstKeyword"),
+            TokenKind::IfKeyword => write!(f, "IfKeyword"),
+            TokenKind::ElseKeyword => write!(f, "ElseKeyword"),
+            TokenKind::WhileKeyword => write!(f, "WhileKeyword"),
+            TokenKind::ForKeyword => write!(f, "ForKeyword"),
+            TokenKind::InKeyword => write!(f, "InKeyword"),
+            TokenKind::BreakKeyword => write!(f, "BreakKeyword"),
+            TokenKind::ContinueKeyword => write!(f, "ContinueKeyword"),
+            TokenKind::ReturnKeyword => write!(f, "ReturnKeyword"),
+            TokenKind::MatchKeyword => write!(f, "MatchKeyword"),
 
             TokenKind::HorizontalWhitespace => write!(f, "HorizontalWhitespace"),
             TokenKind::VerticalWhitespace => write!(f, "VerticalWhitespace"),
+            TokenKind::Comment => write!(f, "Comment"),
 
             TokenKind::OpenParen => write!(f, "OpenParen"),
             TokenKind::CloseParen => write!(f, "CloseParen"),
@@ -109,6 +197,34 @@ impl std::fmt::Display for TokenKind {
             TokenKind::Minus => write!(f, "Minus"),
             TokenKind::Star => write!(f, "Star"),
             TokenKind::Slash => write!(f, "Slash"),
+            TokenKind::Percent => write!(f, "Percent"),
+            TokenKind::Bang => write!(f, "Bang"),
+            TokenKind::Equal => write!(f, "Equal"),
+            TokenKind::EqualEqual => write!(f, "EqualEqual"),
+            TokenKind::BangEqual => write!(f, "BangEqual"),
+            TokenKind::Less => write!(f, "Less"),
+            TokenKind::LessEqual => write!(f, "LessEqual"),
+            TokenKind::Greater => write!(f, "Greater"),
+            TokenKind::GreaterEqual => write!(f, "GreaterEqual"),
+            TokenKind::LessLess => write!(f, "LessLess"),
+            TokenKind::GreaterGreater => write!(f, "GreaterGreater"),
+            TokenKind::Amp => write!(f, "Amp"),
+            TokenKind::AmpAmp => write!(f, "AmpAmp"),
+            TokenKind::Pipe => write!(f, "Pipe"),
+            TokenKind::PipePipe => write!(f, "PipePipe"),
+            TokenKind::Arrow => write!(f, "Arrow"),
+            TokenKind::FatArrow => write!(f, "FatArrow"),
+            TokenKind::At => write!(f, "At"),
+            TokenKind::Colon => write!(f, "Colon"),
+            TokenKind::Comma => write!(f, "Comma"),
+            TokenKind::PlusEqual => write!(f, "PlusEqual"),
+            TokenKind::MinusEqual => write!(f, "MinusEqual"),
+            TokenKind::StarEqual => write!(f, "StarEqual"),
+            TokenKind::SlashEqual => write!(f, "SlashEqual"),
+            TokenKind::PercentEqual => write!(f, "PercentEqual"),
+            TokenKind::Semi => write!(f, "Semi"),
+            TokenKind::Dot => write!(f, "Dot"),
+            TokenKind::DotDot => write!(f, "DotDot"),
 
             TokenKind::Eof => write!(f, "Eof"),
             TokenKind::Invalid => write!(f, "Invalid"),
@@ -1,5 +1,6 @@
 use super::token::{SourceLocation, Span, Token, TokenInfo, TokenKind};
 
+#[derive(Default)]
 pub struct TokenizedBuffer<'a> {
     file: Option<&'a str>,
     source: &'a [u8],
@@ -40,6 +41,27 @@ impl<'a> TokenizedBuffer<'a> {
         }
     }
 
+    pub fn with_file(mut self, file: Option<&'a str>) -> Self {
+        self.file = file;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn last(&self) -> Token {
+        Token(self.tokens.len() - 1)
+    }
+
+    pub fn file(&self) -> Option<&'a str> {
+        self.file
+    }
+
     pub fn push(&mut self, kind: TokenKind, info: TokenInfo) {
         let next_token = self.next_token();
         self.tokens.push(next_token);
@@ -72,14 +94,10 @@ impl<'a> TokenizedBuffer<'a> {
         self.kinds[index]
     }
 
-    pub fn location_of(&self, token: &Token) -> SourceLocation {
+    pub fn location_of(&self, token: &Token) -> SourceLocation<'a> {
         let index = token.0;
         let span = self.locations[index];
-        SourceLocation {
-            file: self.file,
-            row: span.start,
-            col: span.end,
-        }
+        SourceLocation::new(self.file, span.start, span.end)
     }
 
     pub fn str_of(&self, token: &Token) -> &'a str {
@@ -105,21 +123,6 @@ impl std::fmt::Debug for TokenizedBuffer<'_> {
     }
 }
 
-impl Default for TokenizedBuffer<'_> {
-    fn default() -> Self {
-        TokenizedBuffer {
-            file: None,
-            source: &[],
-            tokens: vec![],
-            locations: vec![],
-            spans: vec![],
-            lines: vec![],
-            kinds: vec![],
-            count: 0,
-        }
-    }
-}
-
 pub struct TokenizedBufferIter<'a> {
     buffer: &'a TokenizedBuffer<'a>,
     index: usize,
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use crate::resolve::{DeclId, DeclKind, Resolution};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct TypeId(pub usize);
+
+/// The integer types. `Int` is a 64-bit signed integer of its own, so
+/// mixing it with `i64` needs a conversion like any other pair.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum IntType {
+    Int,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl IntType {
+    pub const ALL: [IntType; 9] = [
+        IntType::Int,
+        IntType::I8,
+        IntType::I16,
+        IntType::I32,
+        IntType::I64,
+        IntType::U8,
+        IntType::U16,
+        IntType::U32,
+        IntType::U64,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IntType::Int => "Int",
+            IntType::I8 => "i8",
+            IntType::I16 => "i16",
+            IntType::I32 => "i32",
+            IntType::I64 => "i64",
+            IntType::U8 => "u8",
+            IntType::U16 => "u16",
+            IntType::U32 => "u32",
+            IntType::U64 => "u64",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<IntType> {
+        IntType::ALL.into_iter().find(|ty| ty.name() == name)
+    }
+
+    pub fn bits(self) -> u32 {
+        match self {
+            IntType::Int | IntType::I64 | IntType::U64 => 64,
+            IntType::I8 | IntType::U8 => 8,
+            IntType::I16 | IntType::U16 => 16,
+            IntType::I32 | IntType::U32 => 32,
+        }
+    }
+
+    /// The smallest value of the type.
+    pub fn min(self) -> i128 {
+        match self {
+            IntType::Int | IntType::I64 => i64::MIN.into(),
+            IntType::I8 => i8::MIN.into(),
+            IntType::I16 => i16::MIN.into(),
+            IntType::I32 => i32::MIN.into(),
+            IntType::U8 | IntType::U16 | IntType::U32 | IntType::U64 => 0,
+        }
+    }
+
+    /// The largest value of the type.
+    pub fn max(self) -> i128 {
+        match self {
+            IntType::Int | IntType::I64 => i64::MAX.into(),
+            IntType::I8 => i8::MAX.into(),
+            IntType::I16 => i16::MAX.into(),
+            IntType::I32 => i32::MAX.into(),
+            IntType::U8 => u8::MAX.into(),
+            IntType::U16 => u16::MAX.into(),
+            IntType::U32 => u32::MAX.into(),
+            IntType::U64 => u64::MAX.into(),
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        !matches!(
+            self,
+            IntType::U8 | IntType::U16 | IntType::U32 | IntType::U64
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Type {
+    /// A type that could not be determined. It has been reported already,
+    /// or belongs to a construct the checker does not model yet, so
+    /// diagnostics involving it are suppressed.
+    Error,
+    Unit,
+    Bool,
+    Real,
+    String,
+    Int(IntType),
+    /// What `a..b` evaluates to, iterated by `for`.
+    Range(TypeId),
+    /// `[T; N]`, with its length known when checking.
+    Array(TypeId, u64),
+    /// `[T]`, a view of part of an array.
+    Slice(TypeId),
+    Tuple(Vec<TypeId>),
+    Func {
+        params: Vec<TypeId>,
+        ret: TypeId,
+    },
+    /// A struct or enum, by its declaration, with the type arguments of a
+    /// generic one, as in `Opt[Int]`.
+    Named(DeclId, Vec<TypeId>),
+    Param(DeclId),
+    /// A type still to be inferred.
+    Var(u32),
+    /// The type of an integer literal still to be inferred. It can only
+    /// become one of the integer types, and is `Int` if nothing says which.
+    IntVar(u32),
+}
+
+/// Interns types, so that equal types have equal ids.
+#[derive(Debug)]
+pub struct Types {
+    types: Vec<Type>,
+    ids: HashMap<Type, TypeId>,
+}
+
+impl Default for Types {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Types {
+    pub const ERROR: TypeId = TypeId(0);
+    pub const UNIT: TypeId = TypeId(1);
+    pub const BOOL: TypeId = TypeId(2);
+    pub const REAL: TypeId = TypeId(3);
+    pub const STRING: TypeId = TypeId(4);
+
+    pub fn new() -> Self {
+        let mut types = Self {
+            types: vec![],
+            ids: HashMap::new(),
+        };
+        for ty in [
+            Type::Error,
+            Type::Unit,
+            Type::Bool,
+            Type::Real,
+            Type::String,
+        ] {
+            types.intern(ty);
+        }
+        types
+    }
+
+    pub fn intern(&mut self, ty: Type) -> TypeId {
+        if let Some(&id) = self.ids.get(&ty) {
+            return id;
+        }
+        let id = TypeId(self.types.len());
+        self.types.push(ty.clone());
+        self.ids.insert(ty, id);
+        id
+    }
+
+    pub fn int(&mut self, ty: IntType) -> TypeId {
+        self.intern(Type::Int(ty))
+    }
+
+    pub fn get(&self, id: TypeId) -> &Type {
+        &self.types[id.0]
+    }
+
+    /// How many types there are, so that `TypeId(0)` up to this are all of
+    /// them.
+    pub(crate) fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn is_numeric(&self, id: TypeId) -> bool {
+        matches!(self.get(id), Type::Int(_) | Type::IntVar(_) | Type::Real)
+    }
+
+    pub fn is_integer(&self, id: TypeId) -> bool {
+        matches!(self.get(id), Type::Int(_) | Type::IntVar(_))
+    }
+
+    /// The type as it is written in source.
+    pub fn display(&self, id: TypeId, resolution: &Resolution) -> String {
+        let list = |ids: &[TypeId]| {
+            let names: Vec<String> = ids.iter().map(|&id| self.display(id, resolution)).collect();
+            names.join(", ")
+        };
+        match self.get(id) {
+            Type::Error => "{error}".to_string(),
+            Type::Unit => "()".to_string(),
+            Type::Bool => "Bool".to_string(),
+            Type::Real => "Real".to_string(),
+            Type::String => "String".to_string(),
+            Type::Int(ty) => ty.name().to_string(),
+            Type::Range(ty) => format!("Range[{}]", self.display(*ty, resolution)),
+            Type::Array(ty, len) => format!("[{}; {}]", self.display(*ty, resolution), len),
+            Type::Slice(ty) => format!("[{}]", self.display(*ty, resolution)),
+            Type::Tuple(elements) => format!("({})", list(elements)),
+            Type::Func { params, ret } => match *ret {
+                Types::UNIT => format!("func({})", list(params)),
+                ret => format!(
+                    "func({}) -> {}",
+                    list(params),
+                    self.display(ret, resolution)
+                ),
+            },
+            // The parameter of an interface is the type implementing it.
+            Type::Param(decl) if resolution.decl(*decl).kind == DeclKind::Interface => {
+                "Self".to_string()
+            }
+            Type::Named(decl, args) if !args.is_empty() => {
+                format!("{}[{}]", resolution.decl(*decl).name, list(args))
+            }
+            Type::Named(decl, _) | Type::Param(decl) => resolution.decl(*decl).name.clone(),
+            Type::Var(_) => "_".to_string(),
+            Type::IntVar(_) => "{integer}".to_string(),
+        }
+    }
+}
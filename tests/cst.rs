@@ -0,0 +1,26 @@
+use std::path::Path;
+use std::{cell::RefCell, rc::Rc};
+
+use mik::cst::Cst;
+use mik::diagnostics::CollectingEmitter;
+use mik::lexer::Lexer;
+use mik::parser::Parser;
+use mik::test_runner::collect_files;
+
+#[test]
+fn test_round_trip() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut files = vec![];
+    collect_files(&root.join("examples"), &mut files).unwrap();
+    collect_files(&root.join("tests/ui"), &mut files).unwrap();
+    assert!(!files.is_empty());
+
+    for file in files {
+        let source = std::fs::read_to_string(&file).unwrap();
+        let emitter = Rc::new(RefCell::new(CollectingEmitter::new()));
+        let tokens = Lexer::new(&source).with_emitter(emitter.clone()).tokenize();
+        let tree = Parser::new(&tokens).with_emitter(emitter).parse();
+        let cst = Cst::new(&tree, &tokens);
+        assert_eq!(cst.to_string(), source, "{}", file.display());
+    }
+}
@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use mik::test_runner;
+
+// Set `MIK_BLESS=1` to update the annotations of failing files.
+#[test]
+fn test_ui() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/ui");
+    let bless = std::env::var_os("MIK_BLESS").is_some();
+    let reports = test_runner::run(&root, bless).unwrap();
+
+    let failures: Vec<String> = reports
+        .iter()
+        .filter(|report| !report.passed())
+        .map(|report| {
+            let mismatches: Vec<String> = report.mismatches.iter().map(|m| m.to_string()).collect();
+            format!(
+                "{}:\n    {}",
+                report.path.display(),
+                mismatches.join("\n    ")
+            )
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}